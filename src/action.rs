@@ -0,0 +1,241 @@
+use crate::git_context::{SystemRunner, prepend_git_context};
+use crate::logger::LogEntry;
+use crate::template::{self, TemplateVars};
+use crate::{
+    ClaudeOptions, ClaudeOutput, KEEPALIVE_PROMPT, SHELL, claude_args, run_claude_command,
+    run_exec_command,
+};
+use anyhow::Result;
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::pin::Pin;
+
+/// The future returned by [`Action::execute`].
+pub type ActionFuture<'a> = Pin<Box<dyn Future<Output = Result<ClaudeOutput>> + Send + 'a>>;
+
+/// What the scheduler does when a scheduled time arrives.
+///
+/// The run modes only deal with timing, retries, logging and notifications;
+/// everything specific to one kind of run lives behind this trait.
+pub trait Action: Send + Sync {
+    /// Identifies the action in log entries, e.g. `claude` or `ping`.
+    fn name(&self) -> &str;
+
+    /// Runs the action once, as part of loop cycle `cycle_number` if there is one.
+    fn execute(&self, cycle_number: Option<u32>) -> ActionFuture<'_>;
+
+    /// The log entry recorded for a successful run.
+    fn success_entry(&self, output: &ClaudeOutput, cycle_number: Option<u32>) -> LogEntry;
+
+    /// Console summary of a successful run, e.g. "ping completed successfully!".
+    fn success_line(&self, _output: &ClaudeOutput) -> String {
+        format!("{} completed successfully!", self.noun())
+    }
+
+    /// What the action is called in console messages.
+    fn noun(&self) -> &str {
+        self.name()
+    }
+
+    /// The full command line, logged with `--verbose`. `None` for actions
+    /// whose exact invocation isn't worth recording.
+    fn argv(&self) -> Option<Vec<String>> {
+        None
+    }
+}
+
+/// An action run at each fire point after the main one, chosen with `--also`.
+#[derive(clap::ValueEnum, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExtraAction {
+    /// Send the ping prompt, as in ping mode
+    Ping,
+    /// Send a minimal keepalive prompt, as with --keepalive
+    Keepalive,
+}
+
+/// Sends the configured message to claude, with any `{{date}}`-style tokens
+/// filled in as it is sent.
+pub struct ClaudeAction {
+    message: String,
+    options: ClaudeOptions,
+    strict_template: bool,
+    git_context: bool,
+}
+
+impl ClaudeAction {
+    pub fn new(message: &str, options: ClaudeOptions) -> Self {
+        Self {
+            message: message.to_string(),
+            options,
+            strict_template: false,
+            git_context: false,
+        }
+    }
+
+    /// Fails the run instead of sending unknown tokens through as written.
+    pub fn with_strict_template(mut self, strict: bool) -> Self {
+        self.strict_template = strict;
+        self
+    }
+
+    /// Puts the recent commits in the working directory before the message.
+    pub fn with_git_context(mut self, git_context: bool) -> Self {
+        self.git_context = git_context;
+        self
+    }
+}
+
+impl Action for ClaudeAction {
+    fn name(&self) -> &str {
+        "claude"
+    }
+
+    fn execute(&self, cycle_number: Option<u32>) -> ActionFuture<'_> {
+        let vars = TemplateVars {
+            now: Local::now(),
+            cycle_number,
+        };
+        Box::pin(async move {
+            let mut message = template::render(&self.message, &vars, self.strict_template)?;
+            let mut warnings = Vec::new();
+            if self.git_context {
+                let working_dir = self.options.working_dir.as_deref();
+                let (with_context, warning) =
+                    prepend_git_context(message, &SystemRunner, working_dir).await;
+                message = with_context;
+                warnings.extend(warning);
+            }
+            let mut output = run_claude_command(&message, &self.options).await?;
+            output.warnings = warnings;
+            Ok(output)
+        })
+    }
+
+    fn success_entry(&self, output: &ClaudeOutput, cycle_number: Option<u32>) -> LogEntry {
+        LogEntry::claude_success(&output.response, cycle_number, output.duration)
+            .with_stderr(&output.stderr)
+            .with_exit_code(output.exit_code)
+    }
+
+    fn noun(&self) -> &str {
+        "command"
+    }
+
+    fn argv(&self) -> Option<Vec<String>> {
+        let mut argv = vec![self.options.program.clone()];
+        argv.extend(claude_args(&self.message, &self.options.extra_args));
+        Some(argv)
+    }
+}
+
+/// Sends the ping prompt, which is meant to consume tokens rather than do useful work.
+pub struct PingAction {
+    prompt: String,
+    options: ClaudeOptions,
+}
+
+impl PingAction {
+    pub fn new(prompt: &str, options: ClaudeOptions) -> Self {
+        Self {
+            prompt: prompt.to_string(),
+            options,
+        }
+    }
+}
+
+impl Action for PingAction {
+    fn name(&self) -> &str {
+        "ping"
+    }
+
+    fn execute(&self, _cycle_number: Option<u32>) -> ActionFuture<'_> {
+        Box::pin(run_claude_command(&self.prompt, &self.options))
+    }
+
+    fn success_entry(&self, output: &ClaudeOutput, cycle_number: Option<u32>) -> LogEntry {
+        LogEntry::ping_success(&output.response, cycle_number, output.duration)
+            .with_stderr(&output.stderr)
+            .with_exit_code(output.exit_code)
+    }
+}
+
+/// Sends a minimal prompt and records only how long claude took to answer.
+pub struct KeepaliveAction {
+    options: ClaudeOptions,
+}
+
+impl KeepaliveAction {
+    pub fn new(options: ClaudeOptions) -> Self {
+        Self { options }
+    }
+}
+
+impl Action for KeepaliveAction {
+    fn name(&self) -> &str {
+        "keepalive"
+    }
+
+    fn execute(&self, _cycle_number: Option<u32>) -> ActionFuture<'_> {
+        Box::pin(run_claude_command(KEEPALIVE_PROMPT, &self.options))
+    }
+
+    /// The response itself is discarded.
+    fn success_entry(&self, output: &ClaudeOutput, cycle_number: Option<u32>) -> LogEntry {
+        LogEntry::keepalive(output.duration, cycle_number)
+    }
+
+    fn success_line(&self, output: &ClaudeOutput) -> String {
+        format!("keepalive answered in {} ms", output.duration.as_millis())
+    }
+}
+
+/// Runs an arbitrary shell command (`--exec`) in place of claude.
+pub struct ExecAction {
+    command: String,
+    options: ClaudeOptions,
+}
+
+impl ExecAction {
+    pub fn new(command: &str, options: ClaudeOptions) -> Self {
+        Self {
+            command: command.to_string(),
+            options,
+        }
+    }
+}
+
+impl Action for ExecAction {
+    fn name(&self) -> &str {
+        "exec"
+    }
+
+    fn execute(&self, _cycle_number: Option<u32>) -> ActionFuture<'_> {
+        Box::pin(run_exec_command(&self.command, &self.options))
+    }
+
+    fn success_entry(&self, output: &ClaudeOutput, cycle_number: Option<u32>) -> LogEntry {
+        LogEntry::success_with_response(
+            "exec",
+            Some("Command executed successfully".to_string()),
+            Some(output.response.clone()),
+            cycle_number,
+        )
+        .with_duration(output.duration)
+        .with_stderr(&output.stderr)
+        .with_exit_code(output.exit_code)
+    }
+
+    fn noun(&self) -> &str {
+        "command"
+    }
+
+    fn argv(&self) -> Option<Vec<String>> {
+        Some(vec![
+            SHELL[0].to_string(),
+            SHELL[1].to_string(),
+            self.command.clone(),
+        ])
+    }
+}
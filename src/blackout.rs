@@ -0,0 +1,105 @@
+use crate::parse_clock_time;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local, NaiveTime};
+use chrono_tz::Tz;
+use std::fmt;
+
+/// A daily `--blackout` window, e.g. `02:00-04:00`, during which fire points
+/// are skipped. The start is inside the window and the end is not; a window
+/// whose end comes before its start wraps past midnight.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Blackout {
+    start: NaiveTime,
+    end: NaiveTime,
+}
+
+impl Blackout {
+    pub fn parse(window: &str) -> Result<Self> {
+        let (start, end) = window
+            .split_once('-')
+            .with_context(|| format!("Invalid blackout window '{window}'. Expected HH:MM-HH:MM"))?;
+        let start = parse_boundary(start, window)?;
+        let end = parse_boundary(end, window)?;
+        if start == end {
+            anyhow::bail!("Invalid blackout window '{window}'. Start and end must differ");
+        }
+        Ok(Self { start, end })
+    }
+
+    /// Whether the wall-clock `time` falls inside the window.
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start < self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+
+    /// Whether `fire_time` falls inside the window, read on the clock of
+    /// `timezone` when given.
+    pub fn covers(&self, fire_time: DateTime<Local>, timezone: Option<Tz>) -> bool {
+        let time = match timezone {
+            Some(tz) => fire_time.with_timezone(&tz).time(),
+            None => fire_time.time(),
+        };
+        self.contains(time)
+    }
+}
+
+impl fmt::Display for Blackout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}-{}",
+            self.start.format("%H:%M"),
+            self.end.format("%H:%M")
+        )
+    }
+}
+
+fn parse_boundary(time_str: &str, window: &str) -> Result<NaiveTime> {
+    let time_str = time_str.trim();
+    let (hour, minute, second) = parse_clock_time(time_str)
+        .with_context(|| format!("Invalid blackout window '{window}'"))?;
+    NaiveTime::from_hms_opt(hour, minute, second)
+        .with_context(|| format!("Invalid blackout window '{window}'"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(hour: u32, minute: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn test_blackout_contains() {
+        let blackout = Blackout::parse("02:00-04:00").unwrap();
+        assert!(!blackout.contains(at(1, 59)));
+        assert!(blackout.contains(at(2, 0)));
+        assert!(blackout.contains(at(3, 30)));
+        assert!(!blackout.contains(at(4, 0)));
+        assert!(!blackout.contains(at(14, 0)));
+        assert_eq!(blackout.to_string(), "02:00-04:00");
+    }
+
+    #[test]
+    fn test_blackout_wrapping_midnight() {
+        let blackout = Blackout::parse("23:00 - 01:30").unwrap();
+        assert!(!blackout.contains(at(22, 59)));
+        assert!(blackout.contains(at(23, 0)));
+        assert!(blackout.contains(at(0, 0)));
+        assert!(blackout.contains(at(1, 29)));
+        assert!(!blackout.contains(at(1, 30)));
+        assert!(!blackout.contains(at(12, 0)));
+        assert_eq!(blackout.to_string(), "23:00-01:30");
+    }
+
+    #[test]
+    fn test_parse_blackout_errors() {
+        assert!(Blackout::parse("02:00").is_err());
+        assert!(Blackout::parse("02:00-25:00").is_err());
+        assert!(Blackout::parse("03:00-03:00").is_err());
+    }
+}
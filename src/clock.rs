@@ -0,0 +1,80 @@
+use chrono::{DateTime, Local};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// The future returned by [`Clock::sleep`].
+pub type SleepFuture<'a> = Pin<Box<dyn Future<Output = ()> + Send + 'a>>;
+
+/// Where the scheduling loops get the time from and how they wait for it.
+///
+/// The scheduler uses [`SystemClock`]; tests can use [`MockClock`] to run a
+/// schedule hours long without waiting for it.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Local>;
+
+    fn sleep(&self, duration: Duration) -> SleepFuture<'_>;
+}
+
+/// The wall clock and tokio's timer.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Local> {
+        Local::now()
+    }
+
+    fn sleep(&self, duration: Duration) -> SleepFuture<'_> {
+        Box::pin(tokio::time::sleep(duration))
+    }
+}
+
+/// A clock that only moves when something sleeps on it, and then jumps
+/// straight to the end of the sleep.
+#[derive(Debug)]
+pub struct MockClock {
+    now: Mutex<DateTime<Local>>,
+}
+
+impl MockClock {
+    pub fn new(start: DateTime<Local>) -> Self {
+        Self {
+            now: Mutex::new(start),
+        }
+    }
+
+    /// Moves the clock forward by `duration`.
+    pub fn advance(&self, duration: Duration) {
+        let duration = chrono::Duration::from_std(duration).expect("duration is in range");
+        *self.now.lock().unwrap() += duration;
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> DateTime<Local> {
+        *self.now.lock().unwrap()
+    }
+
+    fn sleep(&self, duration: Duration) -> SleepFuture<'_> {
+        self.advance(duration);
+        // Still give other tasks, e.g. a shutdown request, a chance to run
+        Box::pin(tokio::task::yield_now())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_mock_clock_sleep_advances_time() {
+        let start = Local::now();
+        let clock = MockClock::new(start);
+        assert_eq!(clock.now(), start);
+
+        clock.sleep(Duration::from_secs(3600)).await;
+        assert_eq!(clock.now(), start + chrono::Duration::hours(1));
+    }
+}
@@ -0,0 +1,522 @@
+use crate::action::ExtraAction;
+use crate::logger::{LogFailurePolicy, LogFormat, LogLevel};
+use crate::syslog_sink::parse_facility;
+use crate::{
+    Config, MAX_COOLDOWN_SECONDS, MAX_GRACE_SECONDS, MAX_JITTER_SECONDS, MAX_RETRIES,
+    MAX_SPACING_SECONDS, OverlapPolicy, PidFormat,
+};
+use anyhow::{Context, Result, bail};
+use chrono_tz::Tz;
+use clap::parser::ValueSource;
+use clap::{ArgMatches, FromArgMatches};
+use regex::Regex;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+
+/// Settings loaded from a `--config` TOML file. Every key is optional and
+/// named after its command-line flag, e.g. `log-dir = "logs"`.
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct FileConfig {
+    time: Option<Vec<String>>,
+    message: Option<OneOrMany>,
+    message_file: Option<String>,
+    strict_template: Option<bool>,
+    git_context: Option<bool>,
+    json: Option<bool>,
+    quiet: Option<bool>,
+    progress_bar: Option<bool>,
+    no_banner: Option<bool>,
+    fail_fast: Option<bool>,
+    no_color: Option<bool>,
+    verbose: Option<bool>,
+    ping_mode: Option<bool>,
+    keepalive: Option<bool>,
+    ping_prompt: Option<String>,
+    exec: Option<String>,
+    also: Option<Vec<ExtraAction>>,
+    pre_hook: Option<String>,
+    post_hook: Option<String>,
+    log_dir: Option<String>,
+    log_format: Option<LogFormat>,
+    log_level: Option<LogLevel>,
+    single_log_file: Option<String>,
+    log_stdout: Option<bool>,
+    no_log_file: Option<bool>,
+    log_failure_policy: Option<LogFailurePolicy>,
+    syslog: Option<bool>,
+    syslog_facility: Option<String>,
+    sqlite: Option<String>,
+    no_response_log: Option<bool>,
+    redact: Option<Vec<String>>,
+    max_response_log_chars: Option<usize>,
+    max_log_size: Option<u64>,
+    log_retention_days: Option<u32>,
+    compress_logs: Option<bool>,
+    loop_mode: Option<bool>,
+    catch_up: Option<bool>,
+    start_now: Option<bool>,
+    reset_cycle: Option<bool>,
+    count: Option<u32>,
+    max_runtime: Option<String>,
+    blackout: Option<String>,
+    until: Option<String>,
+    on_overlap: Option<OverlapPolicy>,
+    control_file: Option<String>,
+    schedule: Option<String>,
+    days: Option<String>,
+    interval: Option<String>,
+    interval_start: Option<String>,
+    pid_file: Option<String>,
+    pid_format: Option<PidFormat>,
+    legacy_pid_format: Option<bool>,
+    jitter: Option<u64>,
+    jitter_seed: Option<u64>,
+    min_spacing: Option<u64>,
+    verbose_timing: Option<bool>,
+    metrics_port: Option<u16>,
+    health_port: Option<u16>,
+    unhealthy_after: Option<u32>,
+    retries: Option<u32>,
+    continue_on_error: Option<bool>,
+    network_retries: Option<u32>,
+    retry_delay: Option<u64>,
+    rate_limit_cooldown: Option<u64>,
+    timeout: Option<u64>,
+    working_dir: Option<String>,
+    env: Option<BTreeMap<String, String>>,
+    output_file: Option<String>,
+    journal: Option<String>,
+    api_key_file: Option<String>,
+    claude_bin: Option<String>,
+    claude_args: Option<Vec<String>>,
+    webhook: Option<String>,
+    slack_webhook: Option<String>,
+    notify: Option<bool>,
+    alert_after: Option<u32>,
+    timezone: Option<String>,
+    tz_display: Option<bool>,
+    grace_seconds: Option<u64>,
+}
+
+/// A key that takes one value or a list, e.g. `message = "..."` or
+/// `message = ["first step", "second step"]`.
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+enum OneOrMany {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl From<OneOrMany> for Vec<String> {
+    fn from(value: OneOrMany) -> Self {
+        match value {
+            OneOrMany::One(value) => vec![value],
+            OneOrMany::Many(values) => values,
+        }
+    }
+}
+
+pub fn load_config(path: &str) -> Result<FileConfig> {
+    let contents =
+        fs::read_to_string(path).with_context(|| format!("Failed to read config file {path}"))?;
+    toml::from_str(&contents).with_context(|| format!("Invalid config file {path}"))
+}
+
+/// A `--config` file together with the command line it was given on, so the
+/// file can be read again (on SIGHUP) with the same precedence.
+#[derive(Clone, Debug)]
+pub struct ConfigFile {
+    path: String,
+    matches: ArgMatches,
+}
+
+impl ConfigFile {
+    pub fn new(path: &str, matches: ArgMatches) -> Self {
+        Self {
+            path: path.to_string(),
+            matches,
+        }
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Reads the file and merges it with the command line.
+    pub fn load(&self) -> Result<Config> {
+        let mut args = Config::from_arg_matches(&self.matches)?;
+        apply_config(&mut args, &self.matches, load_config(&self.path)?)?;
+        Ok(args)
+    }
+}
+
+/// Whether the argument with this id was given explicitly, on the command line
+/// or through its environment variable (e.g. `CCS_MESSAGE`).
+fn set_explicitly(matches: &ArgMatches, id: &str) -> bool {
+    matches!(
+        matches.value_source(id),
+        Some(ValueSource::CommandLine | ValueSource::EnvVariable)
+    )
+}
+
+/// Fills in `args` from `file`. Settings resolve as command line, then
+/// environment, then config file, then built-in default: a file value is only
+/// used when the flag was not given explicitly, and it replaces clap's default.
+pub fn apply_config(args: &mut Config, matches: &ArgMatches, file: FileConfig) -> Result<()> {
+    macro_rules! apply {
+        ($($field:ident),* $(,)?) => {$(
+            if let Some(value) = file.$field
+                && !set_explicitly(matches, stringify!($field))
+            {
+                args.$field = value.into();
+            }
+        )*};
+    }
+
+    apply!(
+        time,
+        message,
+        message_file,
+        strict_template,
+        git_context,
+        json,
+        quiet,
+        progress_bar,
+        no_banner,
+        fail_fast,
+        no_color,
+        verbose,
+        ping_mode,
+        keepalive,
+        ping_prompt,
+        exec,
+        also,
+        pre_hook,
+        post_hook,
+        log_dir,
+        log_format,
+        log_level,
+        single_log_file,
+        log_stdout,
+        no_log_file,
+        log_failure_policy,
+        syslog,
+        sqlite,
+        no_response_log,
+        max_response_log_chars,
+        max_log_size,
+        log_retention_days,
+        compress_logs,
+        loop_mode,
+        catch_up,
+        start_now,
+        reset_cycle,
+        count,
+        max_runtime,
+        blackout,
+        until,
+        on_overlap,
+        control_file,
+        schedule,
+        days,
+        interval,
+        interval_start,
+        pid_file,
+        pid_format,
+        legacy_pid_format,
+        jitter,
+        jitter_seed,
+        min_spacing,
+        verbose_timing,
+        metrics_port,
+        health_port,
+        unhealthy_after,
+        retries,
+        continue_on_error,
+        network_retries,
+        retry_delay,
+        rate_limit_cooldown,
+        timeout,
+        working_dir,
+        output_file,
+        journal,
+        api_key_file,
+        claude_bin,
+        claude_args,
+        webhook,
+        slack_webhook,
+        notify,
+        alert_after,
+        tz_display,
+        grace_seconds,
+    );
+
+    if let Some(env) = file.env
+        && !set_explicitly(matches, "env")
+    {
+        args.env = env.into_iter().collect();
+    }
+
+    if let Some(patterns) = file.redact
+        && !set_explicitly(matches, "redact")
+    {
+        args.redact = patterns
+            .iter()
+            .map(|pattern| {
+                Regex::new(pattern)
+                    .with_context(|| format!("Invalid redact pattern '{pattern}' in config file"))
+            })
+            .collect::<Result<_>>()?;
+    }
+
+    if let Some(facility) = file.syslog_facility
+        && !set_explicitly(matches, "syslog_facility")
+    {
+        args.syslog_facility = parse_facility(&facility)?;
+    }
+
+    if let Some(timezone) = file.timezone
+        && !set_explicitly(matches, "timezone")
+    {
+        let tz = timezone
+            .parse::<Tz>()
+            .map_err(|e| anyhow::anyhow!("Invalid timezone '{timezone}' in config file: {e}"))?;
+        args.timezone = Some(tz);
+    }
+
+    validate(args)
+}
+
+/// Re-checks the constraints clap enforces on the command line, since values
+/// from the config file bypass them.
+fn validate(args: &Config) -> Result<()> {
+    if args.schedule.is_some() && args.interval.is_some() {
+        bail!("schedule and interval cannot be used together");
+    }
+    if args.interval_start.is_some() && args.interval.is_none() {
+        bail!("interval-start requires interval");
+    }
+    if args.tz_display && args.timezone.is_none() {
+        bail!("tz-display requires timezone");
+    }
+    if args.catch_up && !args.loop_mode {
+        bail!("catch-up requires loop-mode");
+    }
+    if args.start_now && !args.loop_mode {
+        bail!("start-now requires loop-mode");
+    }
+    if args.start_now && args.catch_up {
+        bail!("start-now and catch-up cannot be used together");
+    }
+    if args.reset_cycle && !args.loop_mode {
+        bail!("reset-cycle requires loop-mode");
+    }
+    if args.control_file.is_some() && !args.loop_mode {
+        bail!("control-file requires loop-mode");
+    }
+    if args.keepalive && args.ping_mode {
+        bail!("keepalive and ping-mode cannot be used together");
+    }
+    if args.exec.is_some() && (args.ping_mode || args.keepalive) {
+        bail!("exec cannot be used with ping-mode or keepalive");
+    }
+    if args.ping_prompt.is_some() && !args.ping_mode {
+        bail!("ping-prompt requires ping-mode");
+    }
+    if args.legacy_pid_format && args.pid_file.is_none() {
+        bail!("legacy-pid-format requires pid-file");
+    }
+    if args.legacy_pid_format && args.pid_format != PidFormat::Json {
+        bail!("legacy-pid-format cannot be used with pid-format");
+    }
+    if args.jitter > MAX_JITTER_SECONDS {
+        bail!("jitter must be at most {MAX_JITTER_SECONDS}");
+    }
+    if args.grace_seconds > MAX_GRACE_SECONDS {
+        bail!("grace-seconds must be at most {MAX_GRACE_SECONDS}");
+    }
+    if args
+        .min_spacing
+        .is_some_and(|spacing| spacing > MAX_SPACING_SECONDS)
+    {
+        bail!("min-spacing must be at most {MAX_SPACING_SECONDS}");
+    }
+    if args.rate_limit_cooldown > MAX_COOLDOWN_SECONDS {
+        bail!("rate-limit-cooldown must be at most {MAX_COOLDOWN_SECONDS}");
+    }
+    if args.retries > MAX_RETRIES {
+        bail!("retries must be at most {MAX_RETRIES}");
+    }
+    if args.network_retries > MAX_RETRIES {
+        bail!("network-retries must be at most {MAX_RETRIES}");
+    }
+    if args.count == Some(0) {
+        bail!("count must be at least 1");
+    }
+    if args.alert_after == Some(0) {
+        bail!("alert-after must be at least 1");
+    }
+    if args.unhealthy_after == 0 {
+        bail!("unhealthy-after must be at least 1");
+    }
+    if args.max_response_log_chars == Some(0) {
+        bail!("max-response-log-chars must be at least 1");
+    }
+    if args.max_log_size == Some(0) {
+        bail!("max-log-size must be at least 1");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Cli;
+    use clap::{CommandFactory, FromArgMatches};
+
+    const SAMPLE: &str = r#"
+        message = "Review open pull requests"
+        schedule = "08:00,14:00,20:00"
+        log-dir = "/var/log/ccschedule"
+        log-format = "text"
+        loop-mode = true
+        retries = 2
+        claude-args = ["--model", "opus"]
+        env = { HTTPS_PROXY = "http://proxy:8080" }
+        timezone = "America/New_York"
+    "#;
+
+    fn merged(argv: &[&str], config: &str) -> Result<Config> {
+        let matches = Cli::command().get_matches_from(argv);
+        let mut args = Config::from_arg_matches(&matches).unwrap();
+        apply_config(&mut args, &matches, toml::from_str(config).unwrap())?;
+        Ok(args)
+    }
+
+    #[test]
+    fn test_config_fills_unset_flags() {
+        let args = merged(
+            &["ccschedule", "--retries", "5", "--log-dir", "logs"],
+            SAMPLE,
+        )
+        .unwrap();
+
+        // From the command line
+        assert_eq!(args.retries, 5);
+        assert_eq!(args.log_dir, "logs");
+        // From the file
+        assert_eq!(args.message, vec!["Review open pull requests"]);
+        assert_eq!(args.schedule.as_deref(), Some("08:00,14:00,20:00"));
+        assert_eq!(args.log_format, LogFormat::Text);
+        assert!(args.loop_mode);
+        assert_eq!(args.claude_args, vec!["--model", "opus"]);
+        assert_eq!(
+            args.env,
+            vec![("HTTPS_PROXY".to_string(), "http://proxy:8080".to_string())]
+        );
+        assert_eq!(args.timezone, Some(chrono_tz::America::New_York));
+        // Built-in defaults
+        assert_eq!(args.retry_delay, 30);
+        assert_eq!(args.jitter, 0);
+        assert!(args.time.is_empty());
+    }
+
+    #[test]
+    fn test_command_line_overrides_config() {
+        let args = merged(
+            &[
+                "ccschedule",
+                "--message",
+                "From CLI",
+                "--claude-arg",
+                "--verbose",
+            ],
+            SAMPLE,
+        )
+        .unwrap();
+        assert_eq!(args.message, vec!["From CLI"]);
+        assert_eq!(args.claude_args, vec!["--verbose"]);
+    }
+
+    #[test]
+    fn test_explicit_default_on_command_line_wins() {
+        let args = merged(&["ccschedule", "--log-format", "json"], SAMPLE).unwrap();
+        assert_eq!(args.log_format, LogFormat::Json);
+    }
+
+    #[test]
+    fn test_config_rejects_unknown_keys() {
+        let err = toml::from_str::<FileConfig>("mesage = \"typo\"").unwrap_err();
+        assert!(err.to_string().contains("unknown field"));
+    }
+
+    #[test]
+    fn test_config_values_are_validated() {
+        let err = merged(&["ccschedule", "--interval", "3h"], "schedule = \"08:00\"").unwrap_err();
+        assert!(err.to_string().contains("cannot be used together"));
+
+        let err = merged(&["ccschedule"], "jitter = 259201").unwrap_err();
+        assert!(err.to_string().contains("jitter must be at most"));
+
+        let err = merged(&["ccschedule"], "grace-seconds = 86401").unwrap_err();
+        assert!(err.to_string().contains("grace-seconds must be at most"));
+
+        let err = merged(&["ccschedule"], "min-spacing = 604801").unwrap_err();
+        assert!(err.to_string().contains("min-spacing must be at most"));
+
+        let err = merged(&["ccschedule"], "rate-limit-cooldown = 9223372036854775807").unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("rate-limit-cooldown must be at most")
+        );
+
+        let err = merged(
+            &["ccschedule"],
+            "loop-mode = true\nstart-now = true\ncatch-up = true",
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("start-now and catch-up"));
+
+        let err = merged(&["ccschedule"], "start-now = true").unwrap_err();
+        assert!(err.to_string().contains("start-now requires loop-mode"));
+
+        let err = merged(&["ccschedule"], "reset-cycle = true").unwrap_err();
+        assert!(err.to_string().contains("reset-cycle requires loop-mode"));
+
+        let err = merged(&["ccschedule"], "unhealthy-after = 0").unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("unhealthy-after must be at least 1")
+        );
+
+        let err = merged(&["ccschedule"], "retries = 4294967295").unwrap_err();
+        assert!(err.to_string().contains("retries must be at most"));
+
+        let err = merged(&["ccschedule"], "network-retries = 4294967295").unwrap_err();
+        assert!(err.to_string().contains("network-retries must be at most"));
+
+        let err = merged(&["ccschedule"], "count = 0").unwrap_err();
+        assert!(err.to_string().contains("count must be at least 1"));
+
+        let err = merged(&["ccschedule"], "timezone = \"Mars/Olympus\"").unwrap_err();
+        assert!(err.to_string().contains("Invalid timezone"));
+    }
+
+    #[test]
+    fn test_redact_patterns_from_file() {
+        let args = merged(&["ccschedule"], r#"redact = ["sk-[a-z0-9]+"]"#).unwrap();
+        assert_eq!(args.redact.len(), 1);
+        assert!(args.redact[0].is_match("sk-abc"));
+
+        let err = merged(&["ccschedule"], r#"redact = ["("]"#).unwrap_err();
+        assert!(err.to_string().contains("Invalid redact pattern"));
+    }
+
+    #[test]
+    fn test_message_list_from_file() {
+        let args = merged(&["ccschedule"], r#"message = ["Plan", "Implement"]"#).unwrap();
+        assert_eq!(args.message, vec!["Plan", "Implement"]);
+    }
+}
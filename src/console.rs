@@ -0,0 +1,398 @@
+use chrono::{DateTime, Local, SecondsFormat};
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+use owo_colors::OwoColorize;
+use serde::Serialize;
+use std::io::{self, IsTerminal, Write};
+use std::sync::Mutex;
+
+/// Resolution of the `--progress-bar` bar, in steps.
+const PROGRESS_BAR_STEPS: u64 = 1000;
+
+/// Where the scheduler's progress output goes and what shape it takes. Prose
+/// and the countdown go to stderr, leaving stdout for results: the responses,
+/// or in JSON mode one JSON object per line with the prose suppressed. In quiet
+/// mode only output about executions that actually run is printed.
+#[derive(Clone, Copy, Debug)]
+pub struct Console {
+    json: bool,
+    quiet: bool,
+    progress_bar: bool,
+    color: bool,
+    banner: bool,
+    /// Where output goes instead of stdout, in tests.
+    capture: Option<&'static Capture>,
+}
+
+impl Console {
+    pub fn new(json: bool) -> Self {
+        Self {
+            json,
+            quiet: false,
+            progress_bar: false,
+            color: false,
+            banner: true,
+            capture: None,
+        }
+    }
+
+    pub fn with_quiet(mut self, quiet: bool) -> Self {
+        self.quiet = quiet;
+        self
+    }
+
+    /// Draws the countdown as a bar when stderr is a terminal.
+    pub fn with_progress_bar(mut self, progress_bar: bool) -> Self {
+        self.progress_bar = progress_bar;
+        self
+    }
+
+    /// Whether the [`Banner`] lines are shown; off with `--no-banner`.
+    pub fn with_banner(mut self, banner: bool) -> Self {
+        self.banner = banner;
+        self
+    }
+
+    /// Keeps everything the console prints in the returned [`Capture`] instead.
+    #[cfg(test)]
+    pub fn capturing(mut self) -> (Self, &'static Capture) {
+        let capture: &'static Capture = Box::leak(Box::default());
+        self.capture = Some(capture);
+        (self, capture)
+    }
+
+    /// Whether status lines are colored; see [`color_enabled`].
+    pub fn with_color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Colors `text` for `tone` when color is enabled.
+    pub fn paint(&self, text: &str, tone: Tone) -> String {
+        paint(text, tone, self.color)
+    }
+
+    /// Whether banners, waiting messages and the countdown are shown.
+    pub fn should_print_progress(&self) -> bool {
+        !self.json && !self.quiet
+    }
+
+    /// Prints a line of human-readable output about an execution.
+    pub fn line(&self, text: &str) {
+        if !self.json {
+            self.print(&format!("{text}\n"));
+        }
+    }
+
+    /// Prints a banner or waiting message.
+    pub fn progress(&self, text: &str) {
+        if self.should_print_progress() {
+            self.print(&format!("{text}\n"));
+        }
+    }
+
+    /// Writes a run's response to stdout as it is, for piping into other tools.
+    pub fn result(&self, response: &str) {
+        if !self.json {
+            self.write_stdout(response);
+        }
+    }
+
+    /// Announces the first fire time on stdout as `NEXT_RUN=<RFC 3339 time>`, for
+    /// wrapper scripts, or as a `{"next_run": ...}` line in JSON mode. Printed even
+    /// when quiet.
+    pub fn next_run(&self, fire_time: DateTime<Local>) {
+        let time = fire_time.to_rfc3339_opts(SecondsFormat::Secs, false);
+        let line = if self.json {
+            format!("{}\n", serde_json::json!({ "next_run": time }))
+        } else {
+            format!("NEXT_RUN={time}\n")
+        };
+        self.write_stdout(&line);
+    }
+
+    fn write_stdout(&self, text: &str) {
+        match self.capture {
+            Some(capture) => capture.stdout.lock().unwrap().push_str(text),
+            None => {
+                let mut stdout = io::stdout().lock();
+                if let Err(e) = stdout
+                    .write_all(text.as_bytes())
+                    .and_then(|()| stdout.flush())
+                {
+                    eprintln!("Warning: Failed to write to stdout: {e}");
+                }
+            }
+        }
+    }
+
+    /// Prints one of the branding lines, unless they are turned off.
+    pub fn banner(&self, banner: Banner) {
+        if self.banner {
+            self.progress(banner.text());
+        }
+    }
+
+    /// Writes prose to stderr.
+    fn print(&self, text: &str) {
+        match self.capture {
+            Some(capture) => capture.stderr.lock().unwrap().push_str(text),
+            None => eprint!("{text}"),
+        }
+    }
+
+    /// Starts a countdown to `target`, drawn as a bar with `--progress-bar`
+    /// or as a single line redrawn in place otherwise.
+    pub fn countdown(&self, label: &str, target: DateTime<Local>) -> Countdown {
+        let bar = (self.progress_bar && self.should_print_progress() && io::stderr().is_terminal())
+            .then(|| {
+                let bar = ProgressBar::with_draw_target(
+                    Some(PROGRESS_BAR_STEPS),
+                    ProgressDrawTarget::stderr(),
+                );
+                bar.set_style(
+                    ProgressStyle::with_template("{prefix}: [{wide_bar}] {msg}")
+                        .expect("progress bar template is valid")
+                        .progress_chars("=> "),
+                );
+                bar.set_prefix(label.to_string());
+                bar
+            });
+        Countdown {
+            console: *self,
+            label: label.to_string(),
+            start: Local::now(),
+            target,
+            bar,
+        }
+    }
+
+    /// Emits `record` as a JSON line on stdout.
+    pub fn record(&self, record: &impl Serialize) {
+        if !self.json {
+            return;
+        }
+        match serde_json::to_string(record) {
+            Ok(line) => self.write_stdout(&format!("{line}\n")),
+            Err(e) => eprintln!("Warning: Failed to write JSON output: {e}"),
+        }
+    }
+}
+
+/// The branding lines printed around a run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Banner {
+    /// Before a single-mode run
+    Single,
+    /// Before loop mode starts
+    Loop,
+    /// After the last single-mode run
+    Footer,
+}
+
+impl Banner {
+    pub fn text(self) -> &'static str {
+        match self {
+            Self::Single => "Claude Code Schedule by Ian Macalinao",
+            Self::Loop => "Claude Code Schedule by Ian Macalinao - Loop Mode",
+            Self::Footer => "Claude Code Schedule by Ian Macalinao - https://ianm.com",
+        }
+    }
+}
+
+/// Everything a capturing [`Console`] printed.
+#[derive(Debug, Default)]
+pub struct Capture {
+    stdout: Mutex<String>,
+    stderr: Mutex<String>,
+}
+
+#[cfg(test)]
+impl Capture {
+    pub fn stdout(&self) -> String {
+        self.stdout.lock().unwrap().clone()
+    }
+
+    pub fn stderr(&self) -> String {
+        self.stderr.lock().unwrap().clone()
+    }
+}
+
+/// What a console line reports, which decides its color.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Tone {
+    Success,
+    Error,
+    Warning,
+}
+
+impl Tone {
+    /// The tone for a log entry status, if it has one.
+    pub fn of_status(status: &str) -> Option<Self> {
+        match status {
+            "success" => Some(Self::Success),
+            "error" => Some(Self::Error),
+            "retry" | "warning" => Some(Self::Warning),
+            _ => None,
+        }
+    }
+}
+
+/// Whether console output should be colored: not with `--no-color`, not when
+/// `NO_COLOR` is set (see <https://no-color.org>), and not when stderr, where
+/// it goes, is redirected.
+pub fn color_enabled(no_color: bool) -> bool {
+    !no_color
+        && std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+        && io::stderr().is_terminal()
+}
+
+/// Wraps `text` in the ANSI color for `tone`, or returns it unchanged when
+/// `color` is off.
+pub fn paint(text: &str, tone: Tone, color: bool) -> String {
+    if !color {
+        return text.to_string();
+    }
+    match tone {
+        Tone::Success => text.green().to_string(),
+        Tone::Error => text.red().to_string(),
+        Tone::Warning => text.yellow().to_string(),
+    }
+}
+
+/// A countdown in progress; see [`Console::countdown`].
+pub struct Countdown {
+    console: Console,
+    label: String,
+    start: DateTime<Local>,
+    target: DateTime<Local>,
+    bar: Option<ProgressBar>,
+}
+
+impl Countdown {
+    /// Redraws the countdown as of `now`.
+    pub fn update(&self, now: DateTime<Local>) {
+        if !self.console.should_print_progress() {
+            return;
+        }
+        let eta = format_remaining(self.target.signed_duration_since(now));
+
+        match &self.bar {
+            Some(bar) => {
+                let fraction = fraction_complete(self.start, self.target, now);
+                bar.set_position((fraction * PROGRESS_BAR_STEPS as f64).round() as u64);
+                bar.set_message(eta);
+            }
+            None => self.console.print(&format!("\r{}: {eta}", self.label)),
+        }
+    }
+}
+
+impl Drop for Countdown {
+    /// Leaves the last frame on screen, like the plain countdown line.
+    fn drop(&mut self) {
+        if let Some(bar) = &self.bar {
+            bar.abandon();
+        }
+    }
+}
+
+/// Formats a countdown as `HH:MM:SS`, with a day count in front once it is a
+/// day or more (`2d 03:04:05`). Negative durations show as zero.
+pub fn format_remaining(remaining: chrono::Duration) -> String {
+    let total = remaining.num_seconds().max(0);
+    let days = total / 86_400;
+    let hours = total / 3600 % 24;
+    let minutes = total / 60 % 60;
+    let seconds = total % 60;
+    if days > 0 {
+        format!("{days}d {hours:02}:{minutes:02}:{seconds:02}")
+    } else {
+        format!("{hours:02}:{minutes:02}:{seconds:02}")
+    }
+}
+
+/// How much of the wait from `start` to `target` has elapsed at `now`, from
+/// 0.0 to 1.0. A wait with nothing to wait for counts as complete.
+pub fn fraction_complete(
+    start: DateTime<Local>,
+    target: DateTime<Local>,
+    now: DateTime<Local>,
+) -> f64 {
+    let total = (target - start).num_milliseconds();
+    if total <= 0 {
+        return 1.0;
+    }
+    let elapsed = (now - start).num_milliseconds();
+    (elapsed as f64 / total as f64).clamp(0.0, 1.0)
+}
+
+/// Writes `value` as a single line of JSON.
+pub fn write_json_line(out: &mut impl Write, value: &impl Serialize) -> io::Result<()> {
+    serde_json::to_writer(&mut *out, value)?;
+    writeln!(out)?;
+    out.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_should_print_progress() {
+        assert!(Console::new(false).should_print_progress());
+        assert!(!Console::new(false).with_quiet(true).should_print_progress());
+        assert!(!Console::new(true).should_print_progress());
+        assert!(!Console::new(true).with_quiet(true).should_print_progress());
+    }
+
+    #[test]
+    fn test_record_is_captured() {
+        let (console, capture) = Console::new(true).capturing();
+        console.record(&serde_json::json!({ "status": "success" }));
+        assert_eq!(capture.stdout(), "{\"status\":\"success\"}\n");
+
+        // Only JSON mode emits records
+        let (console, capture) = Console::new(false).capturing();
+        console.record(&serde_json::json!({ "status": "success" }));
+        assert_eq!(capture.stdout(), "");
+    }
+
+    #[test]
+    fn test_fraction_complete() {
+        let start = Local::now();
+        let target = start + chrono::Duration::seconds(100);
+        let at = |secs| start + chrono::Duration::seconds(secs);
+
+        assert_eq!(fraction_complete(start, target, start), 0.0);
+        assert_eq!(fraction_complete(start, target, at(25)), 0.25);
+        assert_eq!(fraction_complete(start, target, target), 1.0);
+        // The clock can move outside the wait
+        assert_eq!(fraction_complete(start, target, at(-10)), 0.0);
+        assert_eq!(fraction_complete(start, target, at(150)), 1.0);
+        // Nothing to wait for
+        assert_eq!(fraction_complete(start, start, start), 1.0);
+    }
+
+    #[test]
+    fn test_paint() {
+        assert_eq!(paint("ok", Tone::Success, false), "ok");
+        assert_eq!(paint("ok", Tone::Success, true), "\x1b[32mok\x1b[39m");
+        assert_eq!(paint("failed", Tone::Error, true), "\x1b[31mfailed\x1b[39m");
+        assert_eq!(Tone::of_status("retry"), Some(Tone::Warning));
+        assert_eq!(Tone::of_status("start"), None);
+    }
+
+    #[test]
+    fn test_format_remaining() {
+        let secs = chrono::Duration::seconds;
+        assert_eq!(format_remaining(secs(0)), "00:00:00");
+        assert_eq!(format_remaining(secs(59 * 60 + 7)), "00:59:07");
+        assert_eq!(format_remaining(secs(13 * 3600 + 4 * 60 + 5)), "13:04:05");
+        assert_eq!(format_remaining(secs(24 * 3600)), "1d 00:00:00");
+        assert_eq!(
+            format_remaining(secs(2 * 86_400 + 3 * 3600 + 4 * 60 + 5)),
+            "2d 03:04:05"
+        );
+        assert_eq!(format_remaining(secs(-5)), "00:00:00");
+    }
+}
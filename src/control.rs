@@ -0,0 +1,73 @@
+use anyhow::{Context, Result};
+use std::io::ErrorKind;
+
+/// What the `--control-file` asks loop mode to do at a fire point.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ControlState {
+    /// Fire as scheduled. Also what a missing or empty file means.
+    Run,
+    /// Skip the fire point but keep counting down to the next one.
+    Pause,
+}
+
+impl ControlState {
+    /// Reads the state from the file's contents: `pause` or `resume`, ignoring case
+    /// and surrounding whitespace.
+    pub fn parse(contents: &str) -> Result<Self> {
+        match contents.trim().to_ascii_lowercase().as_str() {
+            "pause" => Ok(Self::Pause),
+            "resume" | "" => Ok(Self::Run),
+            other => anyhow::bail!("Unknown control state '{other}'. Expected pause or resume"),
+        }
+    }
+
+    /// Reads the control file at `path`. A file that doesn't exist means run.
+    pub fn read(path: &str) -> Result<Self> {
+        match std::fs::read_to_string(path) {
+            Ok(contents) => {
+                Self::parse(&contents).with_context(|| format!("Invalid control file {path}"))
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Self::Run),
+            Err(e) => Err(e).with_context(|| format!("Failed to read control file {path}")),
+        }
+    }
+}
+
+/// Whether loop mode should fire now, going by the control file at `path`.
+pub fn should_fire(path: &str) -> Result<bool> {
+    Ok(ControlState::read(path)? == ControlState::Run)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_control_state() {
+        assert_eq!(ControlState::parse("pause\n").unwrap(), ControlState::Pause);
+        assert_eq!(ControlState::parse(" PAUSE ").unwrap(), ControlState::Pause);
+        assert_eq!(ControlState::parse("resume").unwrap(), ControlState::Run);
+        assert_eq!(ControlState::parse("").unwrap(), ControlState::Run);
+        assert!(ControlState::parse("stop").is_err());
+    }
+
+    #[test]
+    fn test_should_fire_follows_control_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("ccs.ctl");
+        let path = path.to_str().unwrap();
+
+        // Not created yet
+        assert!(should_fire(path).unwrap());
+
+        std::fs::write(path, "pause\n").unwrap();
+        assert!(!should_fire(path).unwrap());
+
+        std::fs::write(path, "resume\n").unwrap();
+        assert!(should_fire(path).unwrap());
+
+        std::fs::write(path, "sleep").unwrap();
+        let err = should_fire(path).unwrap_err();
+        assert!(format!("{err:#}").contains("Unknown control state 'sleep'"));
+    }
+}
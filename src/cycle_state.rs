@@ -0,0 +1,103 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Name of the state file, kept in the log directory so it survives a reboot
+/// along with the logs whose numbering it continues.
+const STATE_FILE_NAME: &str = "cycle-state";
+
+/// The number of the last loop cycle started, so a restarted loop carries on
+/// numbering from there instead of going back to 1.
+pub struct CycleState {
+    path: PathBuf,
+}
+
+impl CycleState {
+    pub fn in_dir(log_dir: &str) -> Self {
+        Self {
+            path: Path::new(log_dir).join(STATE_FILE_NAME),
+        }
+    }
+
+    /// The number the next cycle should get: one past the last recorded cycle,
+    /// or 1 when nothing has been recorded yet.
+    pub fn next_cycle(&self) -> Result<u32> {
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(1),
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to read {}", self.path.display()));
+            }
+        };
+        let last: u32 = contents.trim().parse().with_context(|| {
+            format!(
+                "Invalid cycle number '{}' in {}",
+                contents.trim(),
+                self.path.display()
+            )
+        })?;
+        Ok(last.saturating_add(1))
+    }
+
+    /// Records that `cycle_number` has started.
+    pub fn save(&self, cycle_number: u32) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        // Write then rename, so a crash mid-write can't leave a truncated number
+        let temp_path = self.path.with_extension("tmp");
+        fs::write(&temp_path, format!("{cycle_number}\n"))
+            .with_context(|| format!("Failed to write {}", temp_path.display()))?;
+        fs::rename(&temp_path, &self.path)
+            .with_context(|| format!("Failed to write {}", self.path.display()))
+    }
+
+    /// Forgets the recorded cycle, so numbering starts again at 1.
+    pub fn reset(&self) -> Result<()> {
+        match fs::remove_file(&self.path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => {
+                Err(e).with_context(|| format!("Failed to remove {}", self.path.display()))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cycle_state_round_trip() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let log_dir = temp_dir.path().join("logs");
+        let state = CycleState::in_dir(log_dir.to_str().unwrap());
+
+        // A fresh log directory starts at cycle 1
+        assert_eq!(state.next_cycle().unwrap(), 1);
+
+        state.save(7).unwrap();
+        assert_eq!(fs::read_to_string(&state.path).unwrap(), "7\n");
+        assert_eq!(state.next_cycle().unwrap(), 8);
+
+        fs::write(&state.path, "garbage").unwrap();
+        let err = state.next_cycle().unwrap_err();
+        assert!(err.to_string().contains("Invalid cycle number 'garbage'"));
+    }
+
+    #[test]
+    fn test_cycle_state_reset() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let state = CycleState::in_dir(temp_dir.path().to_str().unwrap());
+
+        // Nothing to reset yet
+        state.reset().unwrap();
+
+        state.save(41).unwrap();
+        state.reset().unwrap();
+        assert!(!state.path.exists());
+        assert_eq!(state.next_cycle().unwrap(), 1);
+    }
+}
@@ -0,0 +1,42 @@
+use std::io;
+
+/// Failures a caller may want to tell apart.
+///
+/// The library's functions return [`anyhow::Error`], which carries one of these
+/// wherever the failure is one of the kinds below; find it with
+/// `error.downcast_ref::<SchedulerError>()`. Anything else is a plain message.
+#[derive(Debug, thiserror::Error)]
+pub enum SchedulerError {
+    /// A time of day that couldn't be read.
+    #[error("{reason}")]
+    TimeParse { input: String, reason: String },
+
+    /// A command that ran to completion but exited unsuccessfully. `code` is
+    /// `None` when it was killed by a signal.
+    #[error("{program} command failed with exit code: {code:?}\nError: {stderr}")]
+    CommandFailed {
+        program: String,
+        code: Option<i32>,
+        stderr: String,
+    },
+
+    /// A command that ran past `--timeout` and was killed.
+    #[error("{program} command timed out after {seconds}s and was killed")]
+    Timeout { program: String, seconds: u64 },
+
+    /// A command that couldn't be started, e.g. because it isn't installed.
+    #[error("Failed to execute {program} command")]
+    Spawn {
+        program: String,
+        #[source]
+        source: io::Error,
+    },
+
+    /// A log entry that couldn't be written to its file.
+    #[error("Failed to write to log file {path}")]
+    LogIo {
+        path: String,
+        #[source]
+        source: io::Error,
+    },
+}
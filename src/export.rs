@@ -0,0 +1,171 @@
+use crate::logger::LogEntry;
+use anyhow::{Context, Result};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// File formats `export` can write.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// Comma-separated values with a header row
+    #[default]
+    Csv,
+}
+
+/// What an export wrote, and how much it had to leave out.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ExportSummary {
+    pub rows: usize,
+    /// Lines that were not JSON log entries, e.g. from `--log-format text`.
+    pub skipped: usize,
+}
+
+/// The `*.log` files under `log_dir`, oldest first.
+fn log_files(log_dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    let entries = fs::read_dir(log_dir)
+        .with_context(|| format!("Failed to read log directory {}", log_dir.display()))?;
+    for entry in entries {
+        let path = entry.context("Failed to read log directory entry")?.path();
+        if path.extension().is_some_and(|ext| ext == "log") {
+            paths.push(path);
+        }
+    }
+    // Dated names sort chronologically
+    paths.sort();
+    Ok(paths)
+}
+
+/// Calls `visit` with every entry in the `*.log` files under `log_dir`, oldest
+/// file first, and returns how many lines were not JSON log entries (e.g. from
+/// `--log-format text`).
+pub fn for_each_entry(
+    log_dir: &Path,
+    mut visit: impl FnMut(LogEntry) -> Result<()>,
+) -> Result<usize> {
+    let mut skipped = 0;
+    for path in log_files(log_dir)? {
+        let contents = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read log file {}", path.display()))?;
+        for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+            match serde_json::from_str::<LogEntry>(line) {
+                Ok(entry) => visit(entry)?,
+                Err(_) => skipped += 1,
+            }
+        }
+    }
+    Ok(skipped)
+}
+
+/// The length of an entry's response, in characters. Entries from before
+/// `response_length` was recorded only have the content.
+pub fn response_length(entry: &LogEntry) -> Option<usize> {
+    entry.response_length.or_else(|| {
+        entry
+            .response_content
+            .as_ref()
+            .map(|content| content.chars().count())
+    })
+}
+
+/// Writes every entry in the `*.log` files under `log_dir` to `out` as CSV,
+/// oldest file first.
+pub fn export_logs(log_dir: &Path, out: impl Write) -> Result<ExportSummary> {
+    let mut writer = csv::Writer::from_writer(out);
+    writer
+        .write_record([
+            "timestamp",
+            "action",
+            "status",
+            "cycle_number",
+            "response_length",
+        ])
+        .context("Failed to write CSV header")?;
+
+    let mut summary = ExportSummary::default();
+    summary.skipped = for_each_entry(log_dir, |entry| {
+        writer
+            .write_record(csv_row(&entry))
+            .context("Failed to write CSV row")?;
+        summary.rows += 1;
+        Ok(())
+    })?;
+
+    writer.flush().context("Failed to write CSV output")?;
+    Ok(summary)
+}
+
+fn csv_row(entry: &LogEntry) -> [String; 5] {
+    [
+        entry.timestamp.to_rfc3339(),
+        entry.action.clone(),
+        entry.status.clone(),
+        entry
+            .cycle_number
+            .map(|n| n.to_string())
+            .unwrap_or_default(),
+        response_length(entry)
+            .map(|n| n.to_string())
+            .unwrap_or_default(),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export_csv_rows() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        fs::write(
+            temp_dir.path().join("2024-01-02.log"),
+            concat!(
+                r#"{"timestamp":"2024-01-02T06:00:00+00:00","action":"claude","status":"success","message":null,"response_content":"hello, world","cycle_number":2}"#,
+                "\n",
+                "not json\n",
+            ),
+        )
+        .unwrap();
+        fs::write(
+            temp_dir.path().join("2024-01-01.log"),
+            concat!(
+                r#"{"timestamp":"2024-01-01T06:00:00+00:00","action":"cycle","status":"start","message":"Starting cycle 1","response_content":null,"cycle_number":1}"#,
+                "\n",
+                r#"{"timestamp":"2024-01-01T06:00:05+00:00","action":"claude","status":"success","message":null,"response_content":null,"response_length":42,"cycle_number":1}"#,
+                "\n",
+            ),
+        )
+        .unwrap();
+        // Not a log file
+        fs::write(temp_dir.path().join("notes.txt"), "ignored").unwrap();
+
+        let mut out = Vec::new();
+        let summary = export_logs(temp_dir.path(), &mut out).unwrap();
+        assert_eq!(
+            summary,
+            ExportSummary {
+                rows: 3,
+                skipped: 1
+            }
+        );
+
+        let csv = String::from_utf8(out).unwrap();
+        let rows: Vec<&str> = csv.lines().collect();
+        assert_eq!(rows.len(), 4);
+        assert_eq!(
+            rows[0],
+            "timestamp,action,status,cycle_number,response_length"
+        );
+        // Timestamps are rendered in local time, so only check the other columns
+        assert!(rows[1].ends_with(",cycle,start,1,"));
+        assert!(rows[2].ends_with(",claude,success,1,42"));
+        assert!(rows[3].ends_with(",claude,success,2,12"));
+    }
+
+    #[test]
+    fn test_export_missing_log_dir() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let err = export_logs(&temp_dir.path().join("missing"), Vec::new()).unwrap_err();
+        assert!(err.to_string().contains("Failed to read log directory"));
+    }
+}
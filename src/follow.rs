@@ -0,0 +1,136 @@
+use crate::logger::{LogEntry, daily_log_path, format_text_line};
+use anyhow::{Context, Result};
+use chrono::NaiveDate;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+
+/// Streams entries from the dated log files in a log directory, moving on to
+/// the next day's file at midnight.
+pub struct LogFollower {
+    log_dir: String,
+    date: NaiveDate,
+    /// Bytes of the current file already read, up to the last complete line.
+    offset: u64,
+}
+
+impl LogFollower {
+    /// Follows the log for `date`, starting from the beginning of its file.
+    pub fn new(log_dir: &str, date: NaiveDate) -> Self {
+        Self {
+            log_dir: log_dir.to_string(),
+            date,
+            offset: 0,
+        }
+    }
+
+    /// The file currently being followed.
+    pub fn path(&self) -> String {
+        daily_log_path(&self.log_dir, self.date)
+    }
+
+    /// Switches to `today`'s file if the date has changed. Returns whether it did.
+    pub fn roll_over(&mut self, today: NaiveDate) -> bool {
+        if today == self.date {
+            return false;
+        }
+        self.date = today;
+        self.offset = 0;
+        true
+    }
+
+    /// Reads the lines appended since the last poll, pretty-printing entries.
+    ///
+    /// A line still being written has no trailing newline yet; it is left for
+    /// the next poll rather than parsed half-finished. Complete lines that are
+    /// not JSON entries (e.g. `--log-format text`) are passed through as-is.
+    pub fn poll(&mut self) -> Result<Vec<String>> {
+        let path = self.path();
+        let mut file = match File::open(&path) {
+            Ok(file) => file,
+            // Nothing has been logged today yet
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e).with_context(|| format!("Failed to open log file {path}")),
+        };
+
+        let len = file
+            .metadata()
+            .with_context(|| format!("Failed to read log file {path}"))?
+            .len();
+        // The file was rotated for size and started afresh
+        if len < self.offset {
+            self.offset = 0;
+        }
+
+        file.seek(SeekFrom::Start(self.offset))
+            .with_context(|| format!("Failed to read log file {path}"))?;
+        let mut appended = String::new();
+        file.read_to_string(&mut appended)
+            .with_context(|| format!("Failed to read log file {path}"))?;
+
+        let Some(complete) = appended.rfind('\n').map(|end| &appended[..=end]) else {
+            return Ok(Vec::new());
+        };
+        self.offset += complete.len() as u64;
+
+        Ok(complete
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| match serde_json::from_str::<LogEntry>(line) {
+                Ok(entry) => format_text_line(&entry),
+                Err(_) => line.to_string(),
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::OpenOptions;
+    use std::io::Write;
+
+    #[test]
+    fn test_roll_over_switches_file() {
+        let day = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let mut follower = LogFollower::new("log", day);
+        assert_eq!(follower.path(), "log/2024-01-01.log");
+
+        assert!(!follower.roll_over(day));
+        assert_eq!(follower.path(), "log/2024-01-01.log");
+
+        assert!(follower.roll_over(day.succ_opt().unwrap()));
+        assert_eq!(follower.path(), "log/2024-01-02.log");
+    }
+
+    #[test]
+    fn test_poll_waits_for_complete_lines() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let log_dir = temp_dir.path().to_str().unwrap();
+        let day = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let mut follower = LogFollower::new(log_dir, day);
+
+        // No file yet
+        assert!(follower.poll().unwrap().is_empty());
+
+        let line = r#"{"timestamp":"2024-01-01T06:00:00+00:00","action":"claude","status":"success","message":null,"response_content":"hello","cycle_number":1}"#;
+        let (head, tail) = line.split_at(40);
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(follower.path())
+            .unwrap();
+
+        write!(file, "{head}").unwrap();
+        assert!(follower.poll().unwrap().is_empty());
+
+        writeln!(file, "{tail}").unwrap();
+        writeln!(file, "plain text line").unwrap();
+        let lines = follower.poll().unwrap();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].contains("[claude] success (cycle 1, 5 chars)"));
+        assert_eq!(lines[1], "plain text line");
+
+        // Already-read lines aren't repeated
+        assert!(follower.poll().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,173 @@
+use anyhow::{Context, Result};
+use std::future::Future;
+use std::pin::Pin;
+use tokio::process::Command;
+
+/// The future returned by [`CommandRunner::run`].
+pub type RunFuture<'a> = Pin<Box<dyn Future<Output = Result<String>> + Send + 'a>>;
+
+/// Runs a helper command and returns what it printed.
+///
+/// `--git-context` uses [`SystemRunner`]; tests can answer with canned output
+/// instead of needing a real repository.
+pub trait CommandRunner: Send + Sync {
+    /// Runs `program` with `args` in `working_dir`, failing unless it exits successfully.
+    fn run<'a>(
+        &'a self,
+        program: &'a str,
+        args: &'a [&'a str],
+        working_dir: Option<&'a str>,
+    ) -> RunFuture<'a>;
+}
+
+/// Runs commands as child processes.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemRunner;
+
+impl CommandRunner for SystemRunner {
+    fn run<'a>(
+        &'a self,
+        program: &'a str,
+        args: &'a [&'a str],
+        working_dir: Option<&'a str>,
+    ) -> RunFuture<'a> {
+        Box::pin(async move {
+            let mut command = Command::new(program);
+            command.args(args);
+            if let Some(dir) = working_dir {
+                command.current_dir(dir);
+            }
+            let output = command
+                .output()
+                .await
+                .with_context(|| format!("Failed to run {program}"))?;
+            if !output.status.success() {
+                anyhow::bail!(
+                    "{program} exited with {}: {}",
+                    output.status,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                );
+            }
+            Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+        })
+    }
+}
+
+/// How many recent commits `--git-context` includes.
+const COMMIT_COUNT: &str = "10";
+
+/// Builds the block of recent git activity in `working_dir` that `--git-context`
+/// puts before the message.
+pub async fn build_git_context(
+    runner: &dyn CommandRunner,
+    working_dir: Option<&str>,
+) -> Result<String> {
+    let log = runner
+        .run(
+            "git",
+            &["log", "--oneline", "-n", COMMIT_COUNT],
+            working_dir,
+        )
+        .await
+        .context("Failed to read recent git activity")?;
+    let log = log.trim_end();
+    if log.is_empty() {
+        anyhow::bail!("No git commits to include");
+    }
+    Ok(format!("Recent git activity:\n{log}\n\n"))
+}
+
+/// Puts the recent git activity before `message`. When there is none to read,
+/// e.g. outside a git repository, the message is kept as it is and a warning is
+/// returned alongside it for the caller to log.
+pub async fn prepend_git_context(
+    message: String,
+    runner: &dyn CommandRunner,
+    working_dir: Option<&str>,
+) -> (String, Option<String>) {
+    match build_git_context(runner, working_dir).await {
+        Ok(context) => (context + &message, None),
+        Err(e) => (
+            message,
+            Some(format!("{e:#}; sent the message without git context")),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Answers every command with `output` and remembers what it was asked to run.
+    struct FakeRunner {
+        output: Result<String, String>,
+        calls: Mutex<Vec<(String, Option<String>)>>,
+    }
+
+    impl FakeRunner {
+        fn new(output: Result<&str, &str>) -> Self {
+            Self {
+                output: output.map(str::to_string).map_err(str::to_string),
+                calls: Mutex::default(),
+            }
+        }
+    }
+
+    impl CommandRunner for FakeRunner {
+        fn run<'a>(
+            &'a self,
+            program: &'a str,
+            args: &'a [&'a str],
+            working_dir: Option<&'a str>,
+        ) -> RunFuture<'a> {
+            self.calls.lock().unwrap().push((
+                format!("{program} {}", args.join(" ")),
+                working_dir.map(str::to_string),
+            ));
+            let output = self.output.clone().map_err(anyhow::Error::msg);
+            Box::pin(async move { output })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_build_git_context() {
+        let runner = FakeRunner::new(Ok("abc1234 Fix parser\ndef5678 Add tests\n"));
+        let context = build_git_context(&runner, Some("/work/repo"))
+            .await
+            .unwrap();
+        assert_eq!(
+            context,
+            "Recent git activity:\nabc1234 Fix parser\ndef5678 Add tests\n\n"
+        );
+        assert_eq!(
+            *runner.calls.lock().unwrap(),
+            [(
+                "git log --oneline -n 10".to_string(),
+                Some("/work/repo".to_string())
+            )]
+        );
+
+        let (message, warning) =
+            prepend_git_context("Review these".to_string(), &runner, None).await;
+        assert_eq!(warning, None);
+        assert!(message.starts_with("Recent git activity:\n"));
+        assert!(message.ends_with("def5678 Add tests\n\nReview these"));
+    }
+
+    #[tokio::test]
+    async fn test_git_context_outside_a_repository() {
+        let runner = FakeRunner::new(Err("fatal: not a git repository"));
+        let err = build_git_context(&runner, None).await.unwrap_err();
+        assert!(format!("{err:#}").contains("not a git repository"));
+
+        // The plain message is still sent, with a warning to log
+        let (message, warning) =
+            prepend_git_context("Review these".to_string(), &runner, None).await;
+        assert_eq!(message, "Review these");
+        assert!(warning.unwrap().contains("without git context"));
+
+        let empty = FakeRunner::new(Ok(""));
+        assert!(build_git_context(&empty, None).await.is_err());
+    }
+}
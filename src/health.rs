@@ -0,0 +1,98 @@
+use crate::http::{self, Response};
+use crate::logger::LogEntry;
+use hyper::StatusCode;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+use tokio::net::TcpListener;
+
+/// Whether the scheduler is healthy, as reported by `--health-port`: it is
+/// until `unhealthy_after` runs in a row have failed.
+#[derive(Debug)]
+pub struct Health {
+    consecutive_failures: AtomicU32,
+    unhealthy_after: u32,
+}
+
+impl Health {
+    pub fn new(unhealthy_after: u32) -> Self {
+        Self {
+            consecutive_failures: AtomicU32::new(0),
+            unhealthy_after,
+        }
+    }
+
+    /// Updates the state from a finished run's log entry.
+    pub fn record(&self, entry: &LogEntry) {
+        match entry.status.as_str() {
+            "success" => self.consecutive_failures.store(0, Ordering::Relaxed),
+            "error" => {
+                self.consecutive_failures.fetch_add(1, Ordering::Relaxed);
+            }
+            _ => {}
+        }
+    }
+
+    pub fn is_healthy(&self) -> bool {
+        self.consecutive_failures.load(Ordering::Relaxed) < self.unhealthy_after
+    }
+
+    /// Answers a request for `path`: 200 on `/healthz` while healthy, 503 otherwise.
+    pub fn respond(&self, path: &str) -> Response {
+        if path != "/healthz" {
+            return Response::not_found();
+        }
+        let failures = self.consecutive_failures.load(Ordering::Relaxed);
+        if self.is_healthy() {
+            Response::text(StatusCode::OK, "ok\n")
+        } else {
+            Response::text(
+                StatusCode::SERVICE_UNAVAILABLE,
+                format!("unhealthy: last {failures} runs failed\n"),
+            )
+        }
+    }
+}
+
+/// Serves `/healthz` from `listener` in the background.
+pub fn serve_health(listener: TcpListener, health: Arc<Health>) {
+    http::spawn(listener, move |path| health.respond(path));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_health_status_follows_runs() {
+        let health = Health::new(1);
+        let success = LogEntry::success("claude", None);
+        let failure = LogEntry::error("claude", Some("failed".to_string()));
+
+        // Waiting for the first run
+        assert_eq!(health.respond("/healthz").status, StatusCode::OK);
+
+        health.record(&failure);
+        let response = health.respond("/healthz");
+        assert_eq!(response.status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.body, "unhealthy: last 1 runs failed\n");
+
+        health.record(&success);
+        assert_eq!(health.respond("/healthz").status, StatusCode::OK);
+
+        assert_eq!(health.respond("/other").status, StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn test_unhealthy_after_several_failures() {
+        let health = Health::new(2);
+        let failure = LogEntry::error("claude", Some("failed".to_string()));
+
+        health.record(&failure);
+        assert_eq!(health.respond("/healthz").status, StatusCode::OK);
+        health.record(&failure);
+        assert_eq!(
+            health.respond("/healthz").status,
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+    }
+}
@@ -0,0 +1,79 @@
+use anyhow::{Context, Result};
+use http_body_util::Full;
+use hyper::body::Bytes;
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Request, StatusCode};
+use hyper_util::rt::TokioIo;
+use std::convert::Infallible;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+
+/// What an endpoint returns for a request path.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Response {
+    pub status: StatusCode,
+    pub content_type: &'static str,
+    pub body: String,
+}
+
+impl Response {
+    pub fn text(status: StatusCode, body: impl Into<String>) -> Self {
+        Self {
+            status,
+            content_type: "text/plain; charset=utf-8",
+            body: body.into(),
+        }
+    }
+
+    pub fn not_found() -> Self {
+        Self::text(StatusCode::NOT_FOUND, "not found\n")
+    }
+}
+
+/// Listens on every interface, so probes and scrapers from outside a container
+/// can reach the endpoint.
+pub async fn bind(port: u16) -> Result<TcpListener> {
+    TcpListener::bind(("0.0.0.0", port))
+        .await
+        .with_context(|| format!("Failed to listen on port {port}"))
+}
+
+/// Answers each request on `listener` with `handler(path)`, on a background
+/// task that runs for the rest of the process.
+pub fn spawn<H>(listener: TcpListener, handler: H)
+where
+    H: Fn(&str) -> Response + Send + Sync + 'static,
+{
+    let handler = Arc::new(handler);
+    tokio::spawn(async move {
+        loop {
+            let stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    eprintln!("Warning: Failed to accept HTTP connection: {e}");
+                    continue;
+                }
+            };
+            let handler = Arc::clone(&handler);
+            tokio::spawn(async move {
+                let service = service_fn(move |request: Request<hyper::body::Incoming>| {
+                    let response = handler(request.uri().path());
+                    async move {
+                        Ok::<_, Infallible>(
+                            hyper::Response::builder()
+                                .status(response.status)
+                                .header("content-type", response.content_type)
+                                .body(Full::new(Bytes::from(response.body)))
+                                .expect("response parts are valid"),
+                        )
+                    }
+                });
+                // A client hanging up mid-request is its own problem
+                let _ = http1::Builder::new()
+                    .serve_connection(TokioIo::new(stream), service)
+                    .await;
+            });
+        }
+    });
+}
@@ -0,0 +1,84 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use std::fs::OpenOptions;
+use std::io::Write;
+
+/// Formats one `--journal` entry: a `##` heading with the time of the run and,
+/// in loop mode, its cycle and step, then the response itself.
+pub fn journal_entry(
+    timestamp: DateTime<Local>,
+    cycle_number: Option<u32>,
+    step: Option<u32>,
+    response: &str,
+) -> String {
+    let mut heading = format!("## {}", timestamp.format("%Y-%m-%d %H:%M:%S"));
+    let position = match (cycle_number, step) {
+        (Some(cycle), Some(step)) => Some(format!("Cycle {cycle}, step {step}")),
+        (Some(cycle), None) => Some(format!("Cycle {cycle}")),
+        (None, Some(step)) => Some(format!("Step {step}")),
+        (None, None) => None,
+    };
+    if let Some(position) = position {
+        heading.push_str(&format!(" - {position}"));
+    }
+    format!("{heading}\n\n{}\n\n", response.trim_end())
+}
+
+/// Appends `entry` to the journal at `path`, creating the file if needed.
+pub fn append_to_journal(path: &str, entry: &str) -> Result<()> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open journal {path}"))?;
+    file.write_all(entry.as_bytes())
+        .with_context(|| format!("Failed to write to journal {path}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_journal_entries_build_markdown() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("journal.md");
+        let path = path.to_str().unwrap();
+
+        let first = Local.with_ymd_and_hms(2024, 3, 12, 8, 0, 5).unwrap();
+        let second = Local.with_ymd_and_hms(2024, 3, 13, 14, 30, 0).unwrap();
+        append_to_journal(
+            path,
+            &journal_entry(first, Some(1), None, "Fixed the parser.\n"),
+        )
+        .unwrap();
+        append_to_journal(
+            path,
+            &journal_entry(second, Some(2), Some(3), "- Added tests\n- Updated docs"),
+        )
+        .unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(path).unwrap(),
+            "## 2024-03-12 08:00:05 - Cycle 1\n\
+             \n\
+             Fixed the parser.\n\
+             \n\
+             ## 2024-03-13 14:30:00 - Cycle 2, step 3\n\
+             \n\
+             - Added tests\n\
+             - Updated docs\n\
+             \n"
+        );
+    }
+
+    #[test]
+    fn test_journal_entry_outside_loop_mode() {
+        let timestamp = Local.with_ymd_and_hms(2024, 3, 12, 6, 0, 0).unwrap();
+        assert_eq!(
+            journal_entry(timestamp, None, None, "done"),
+            "## 2024-03-12 06:00:00\n\ndone\n\n"
+        );
+    }
+}
@@ -0,0 +1,6317 @@
+//! Schedules Claude Code to run at a chosen time, logging each run.
+//!
+//! The `ccschedule` binary is a thin wrapper around [`cli_main`]. To embed the
+//! scheduler instead, build a [`Config`] and hand it to a [`Scheduler`]:
+//!
+//! ```no_run
+//! # async fn example() -> anyhow::Result<()> {
+//! use claude_code_schedule::{Config, Scheduler};
+//!
+//! let config = Config {
+//!     time: vec!["+30m".to_string()],
+//!     message: vec!["Summarize yesterday's commits".to_string()],
+//!     ..Config::default()
+//! };
+//! Scheduler::new(config).run().await
+//! # }
+//! ```
+
+use action::{Action, ClaudeAction, ExecAction, ExtraAction, KeepaliveAction, PingAction};
+use anyhow::{Context, Result};
+use chrono::{
+    DateTime, Datelike, Local, LocalResult, NaiveDate, NaiveTime, Offset, TimeZone, Weekday,
+};
+use chrono_tz::Tz;
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::io::Read;
+use std::process::Output;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::process::Command;
+use tokio::time::sleep;
+
+pub mod action;
+mod blackout;
+pub mod clock;
+mod config;
+mod console;
+mod control;
+mod cycle_state;
+mod error;
+mod export;
+mod follow;
+mod git_context;
+mod health;
+mod http;
+mod journal;
+pub mod logger;
+mod metrics;
+mod notify;
+mod overlap;
+mod pid;
+mod print_config;
+mod reload;
+mod report;
+mod shutdown;
+#[cfg(feature = "sqlite")]
+mod sqlite_sink;
+mod stats;
+mod syslog_sink;
+mod systemd;
+mod template;
+use blackout::Blackout;
+use clock::{Clock, SystemClock};
+use config::ConfigFile;
+use console::{Banner, Console, Tone, color_enabled};
+use control::should_fire;
+use cycle_state::CycleState;
+pub use error::SchedulerError;
+use export::{ExportFormat, export_logs};
+use follow::LogFollower;
+use health::{Health, serve_health};
+use journal::{append_to_journal, journal_entry};
+pub use logger::{LogEntry, LogFailurePolicy, LogFormat, LogLevel, Logger};
+use metrics::{Metrics, serve_metrics};
+use notify::Notifier;
+pub use overlap::OverlapPolicy;
+use overlap::RunLock;
+pub use pid::PidFormat;
+use pid::{PidFile, RunMode, SystemProcesses, cleanup_pid_file, pid_status, stop_scheduler};
+use reload::Reload;
+use report::{ReportFormat, report_logs};
+use shutdown::Shutdown;
+use stats::LoopSummary;
+use syslog_sink::{SyslogSink, parse_facility};
+use systemd::Systemd;
+use template::TemplateVars;
+
+const DEFAULT_MESSAGE: &str = "Continue working on what you were working on previously. If you weren't working on something previously, then come up with a list of tasks to work on based on what is left in the codebase.";
+
+/// Ping mode's default prompt: a research-style query long enough to consume a meaningful
+/// number of tokens.
+const DEFAULT_PING_PROMPT: &str = "Search for today's global weather information and tell me: 1) the hottest place in the world today and its temperature; 2) the coldest place in the world today and its temperature; 3) the exact location and local time of each of these places; 4) a brief analysis of the meteorological causes of these extreme temperatures; 5) some interesting weather-related facts. Please provide detailed and accurate information, including your data sources.";
+
+/// Keepalive mode's prompt, kept as short as possible so each run is cheap.
+const KEEPALIVE_PROMPT: &str = "Reply with just OK.";
+
+/// How often `logs --follow` checks the log file for new entries.
+const FOLLOW_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How long `--dry-run` waits for `claude --version` before giving up.
+const BINARY_CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Largest `--jitter`, three days; more would push a run past the next ones.
+const MAX_JITTER_SECONDS: u64 = 3 * 24 * 60 * 60;
+
+/// Largest `--grace-seconds`, a day; past that every time of day would count as today.
+const MAX_GRACE_SECONDS: u64 = 24 * 60 * 60;
+
+/// Largest `--min-spacing`, a week.
+const MAX_SPACING_SECONDS: u64 = 7 * 24 * 60 * 60;
+
+/// Largest `--rate-limit-cooldown`, a week.
+const MAX_COOLDOWN_SECONDS: u64 = 7 * 24 * 60 * 60;
+
+/// Largest `--retries`; past that a failing run would effectively never give up.
+const MAX_RETRIES: u32 = 100;
+
+/// Longest single sleep while waiting for a fire time with no countdown on screen.
+const MAX_WAIT_CHUNK: Duration = Duration::from_secs(60);
+
+#[derive(Parser, Debug)]
+#[command(
+    author = "Ian Macalinao <ian@macalinao.com>",
+    version,
+    about = "Schedule Claude Code to run at a specific time - by Ian Macalinao",
+    long_about = "A CLI tool by Ian Macalinao that runs Claude Code at a scheduled time. \
+                  The tool will stay running in your terminal and execute the command when the time is reached.\
+                  \n\nCreated by Ian Macalinao - https://ianm.com",
+    args_conflicts_with_subcommands = true
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Commands>,
+
+    #[command(flatten)]
+    run: Config,
+}
+
+#[derive(Subcommand, Debug)]
+enum Commands {
+    /// Schedule and run Claude Code (the default when no subcommand is given)
+    Run(Box<Config>),
+
+    /// Report whether the scheduler recorded in a PID file is running
+    Status {
+        /// PID file written by a scheduler started with --pid-file
+        #[arg(long)]
+        pid_file: String,
+    },
+
+    /// Stop a backgrounded scheduler by sending SIGTERM to the PID in its PID file
+    Stop {
+        /// PID file written by a scheduler started with --pid-file
+        #[arg(long)]
+        pid_file: String,
+    },
+
+    /// Convert the JSON log files in a log directory into a spreadsheet-friendly file
+    Export {
+        /// Directory the scheduler wrote its logs to
+        #[arg(long, default_value = "log")]
+        log_dir: String,
+
+        /// Output format
+        #[arg(long, value_enum, default_value_t = ExportFormat::Csv)]
+        format: ExportFormat,
+
+        /// File to write (default: stdout)
+        #[arg(long, value_name = "PATH")]
+        out: Option<String>,
+    },
+
+    /// Summarize the runs in a log directory: success rate, failures per day,
+    /// response lengths and the busiest hour
+    Report {
+        /// Directory the scheduler wrote its logs to
+        #[arg(long, default_value = "log")]
+        log_dir: String,
+
+        /// Only count runs on or after this date (YYYY-MM-DD)
+        #[arg(long, value_name = "DATE")]
+        since: Option<NaiveDate>,
+
+        /// Output format
+        #[arg(long, value_enum, default_value_t = ReportFormat::Table)]
+        format: ReportFormat,
+    },
+
+    /// Print today's log entries
+    Logs {
+        /// Directory the scheduler writes its logs to
+        #[arg(long, default_value = "log")]
+        log_dir: String,
+
+        /// Keep watching for new entries, moving on to the next day's log at midnight
+        #[arg(short, long)]
+        follow: bool,
+    },
+}
+
+impl Cli {
+    /// The subcommand to run, treating a bare invocation as `run`.
+    fn into_command(self) -> Commands {
+        self.command
+            .unwrap_or_else(|| Commands::Run(Box::new(self.run)))
+    }
+}
+
+#[derive(clap::Args, Serialize, Debug)]
+pub struct Config {
+    /// Load settings from a TOML file; flags given on the command line take precedence
+    #[arg(long, value_name = "PATH")]
+    pub config: Option<String>,
+
+    /// Run Claude Code at a specific time (format: HH:MM[:SS], optionally with AM/PM, a full YYYY-MM-DD HH:MM timestamp, or a relative offset like +90m; default: 06:00).
+    /// Can be repeated to run at several times, e.g. --time 09:00 --time 13:30
+    #[arg(short, long, value_name = "HH:MM", env = "CCS_TIME")]
+    pub time: Vec<String>,
+
+    /// Message to pass to Claude Code (default: "Continue working on what you were working on previously. If you weren't working on something previously, then come up with a list of tasks to work on based on what is left in the codebase.").
+    /// Can be repeated to send several messages one after another at each scheduled time
+    #[arg(short, long, env = "CCS_MESSAGE")]
+    pub message: Vec<String>,
+
+    /// Read the message to pass to Claude Code from a file (takes precedence over --message and --message-stdin)
+    #[arg(long, value_name = "PATH")]
+    pub message_file: Option<String>,
+
+    /// Read the message to pass to Claude Code from standard input (takes precedence over --message)
+    #[arg(long)]
+    pub message_stdin: bool,
+
+    /// Fail on unknown {{...}} tokens in the message instead of sending them as written
+    #[arg(long)]
+    pub strict_template: bool,
+
+    /// Put the last 10 commits in the working directory (git log --oneline) before the
+    /// message each time it is sent
+    #[arg(long)]
+    pub git_context: bool,
+
+    /// Dry run - print what would happen without scheduling
+    #[arg(short, long)]
+    pub dry_run: bool,
+
+    /// Print the effective settings, from flags, environment variables and --config
+    /// combined, as JSON and exit. Secrets such as API keys are masked
+    #[arg(long)]
+    pub print_config: bool,
+
+    /// How many upcoming fire times a loop-mode dry run lists
+    #[arg(long, value_name = "N", default_value_t = 5, value_parser = clap::value_parser!(u32).range(1..))]
+    pub dry_run_count: u32,
+
+    /// Exit at startup if the claude binary fails its health check, instead of warning
+    #[arg(long)]
+    pub fail_fast: bool,
+
+    /// Print one JSON object per execution to stdout instead of the countdown and prose
+    #[arg(long)]
+    pub json: bool,
+
+    /// Suppress the countdown and banners, printing only when an execution runs
+    #[arg(short, long)]
+    pub quiet: bool,
+
+    /// Draw the countdown as a progress bar with an ETA when stdout is a terminal
+    #[arg(long)]
+    pub progress_bar: bool,
+
+    /// Leave out the "Claude Code Schedule by ..." branding lines
+    #[arg(long)]
+    pub no_banner: bool,
+
+    /// Disable colored output (also disabled by NO_COLOR or when stdout is not a terminal)
+    #[arg(long)]
+    pub no_color: bool,
+
+    /// Log the exact claude argv, working directory and environment before each run
+    #[arg(short, long)]
+    pub verbose: bool,
+
+    /// Query global weather information instead of running Claude Code
+    #[arg(short, long)]
+    pub ping_mode: bool,
+
+    /// Send a minimal prompt to keep the session warm, logging only the latency
+    #[arg(long, conflicts_with = "ping_mode")]
+    pub keepalive: bool,
+
+    /// Prompt to send in ping mode instead of the built-in weather query
+    #[arg(long, value_name = "PROMPT", requires = "ping_mode")]
+    pub ping_prompt: Option<String>,
+
+    /// Run this shell command instead of claude, with the same scheduling, retries and logging
+    #[arg(long, value_name = "COMMAND", conflicts_with_all = ["ping_mode", "keepalive"])]
+    pub exec: Option<String>,
+
+    /// Also run this action at each fire point, after the main one, logged separately
+    /// (repeatable)
+    #[arg(long, value_enum, value_name = "ACTION")]
+    pub also: Vec<ExtraAction>,
+
+    /// Shell command to run immediately before each execution
+    #[arg(long, value_name = "COMMAND")]
+    pub pre_hook: Option<String>,
+
+    /// Shell command to run immediately after each execution, with CCS_STATUS set to
+    /// success or error
+    #[arg(long, value_name = "COMMAND")]
+    pub post_hook: Option<String>,
+
+    /// Directory for storing logs (default: log)
+    #[arg(long, default_value = "log")]
+    pub log_dir: String,
+
+    /// Format of log file entries
+    #[arg(long, value_enum, default_value_t = LogFormat::Json)]
+    pub log_format: LogFormat,
+
+    /// Which log entries are echoed to the console: error, info or debug (everything).
+    /// The log file always records every entry
+    #[arg(long, value_enum, default_value_t = LogLevel::Debug)]
+    pub log_level: LogLevel,
+
+    /// Append all log entries to this one file instead of a dated file per day in --log-dir
+    #[arg(long, value_name = "PATH")]
+    pub single_log_file: Option<String>,
+
+    /// Also write each log entry to stdout as a JSON line (for container log collectors)
+    #[arg(long)]
+    pub log_stdout: bool,
+
+    /// Don't write log files at all
+    #[arg(long)]
+    pub no_log_file: bool,
+
+    /// What to do if the log directory can't be written at startup: abort, or warn once
+    /// and run without the log file
+    #[arg(long, value_enum, value_name = "POLICY", default_value_t)]
+    pub log_failure_policy: LogFailurePolicy,
+
+    /// Also send each log entry to the local syslog daemon
+    #[arg(long)]
+    pub syslog: bool,
+
+    /// Also record each log entry in the `runs` table of this SQLite database
+    /// (needs the `sqlite` feature)
+    #[arg(long, value_name = "PATH")]
+    pub sqlite: Option<String>,
+
+    /// Syslog facility to log under (e.g. daemon, user, local0)
+    #[arg(long, value_name = "FACILITY", default_value = "daemon", value_parser = parse_facility)]
+    #[serde(serialize_with = "print_config::serialize_facility")]
+    pub syslog_facility: syslog::Facility,
+
+    /// Store only the length of each response in the log, not its content
+    #[arg(long)]
+    pub no_response_log: bool,
+
+    /// Mask matches of this regex in logged responses (repeatable, e.g. --redact 'sk-ant-[A-Za-z0-9-]+')
+    #[arg(long, value_name = "PATTERN", value_parser = Regex::new)]
+    #[serde(serialize_with = "print_config::serialize_regexes")]
+    pub redact: Vec<Regex>,
+
+    /// Truncate each logged response to this many characters (the full length is still recorded)
+    #[arg(long, value_name = "N", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    pub max_response_log_chars: Option<usize>,
+
+    /// Roll the day's log file to a numbered file once it would exceed this many bytes
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub max_log_size: Option<u64>,
+
+    /// Delete dated log files older than this many days at startup
+    #[arg(long)]
+    pub log_retention_days: Option<u32>,
+
+    /// Gzip log files from previous days at startup
+    #[arg(long)]
+    pub compress_logs: bool,
+
+    /// Enable continuous loop mode (runs every 5 hours: 7:00, 12:00, 17:00, 22:00, 03:00)
+    #[arg(short, long)]
+    pub loop_mode: bool,
+
+    /// In loop mode, run immediately on startup if today's most recent scheduled time was missed
+    #[arg(long, requires = "loop_mode")]
+    pub catch_up: bool,
+
+    /// In loop mode, run the first cycle immediately on startup, then follow the schedule
+    #[arg(long, requires = "loop_mode", conflicts_with = "catch_up")]
+    pub start_now: bool,
+
+    /// In loop mode, number cycles from 1 again instead of continuing from the last run
+    #[arg(long, requires = "loop_mode")]
+    pub reset_cycle: bool,
+
+    /// Stop loop mode after this many cycles (default: run forever)
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    pub count: Option<u32>,
+
+    /// Exit gracefully once the scheduler has been running this long (e.g. 8h, 90m), in
+    /// either mode. A run in progress is allowed to finish
+    #[arg(long, value_name = "DURATION")]
+    pub max_runtime: Option<String>,
+
+    /// Skip fire points within this daily window (e.g. 02:00-04:00; may wrap past midnight)
+    #[arg(long, value_name = "HH:MM-HH:MM")]
+    pub blackout: Option<String>,
+
+    /// Stop loop mode once the next run would be after this time (HH:MM or "YYYY-MM-DD HH:MM")
+    #[arg(long, value_name = "TIME")]
+    pub until: Option<String>,
+
+    /// What to do when a fire point arrives while another execution in this process is
+    /// still running
+    #[arg(long, value_enum, value_name = "POLICY", default_value_t)]
+    pub on_overlap: OverlapPolicy,
+
+    /// In loop mode, check this file at each fire point: while it contains "pause" the
+    /// fire point is skipped, and "resume" (or no file) runs as usual
+    #[arg(long, value_name = "PATH", requires = "loop_mode")]
+    pub control_file: Option<String>,
+
+    /// Custom loop mode schedule as comma-separated times (e.g. 08:00,14:00,20:00)
+    #[arg(long, value_name = "HH:MM,...")]
+    pub schedule: Option<String>,
+
+    /// Only run on these days of the week (e.g. mon,wed,fri)
+    #[arg(long, value_name = "DAYS")]
+    pub days: Option<String>,
+
+    /// Run loop mode at a fixed interval instead of a schedule (e.g. 3h, 90m)
+    #[arg(long, value_name = "DURATION", conflicts_with = "schedule")]
+    pub interval: Option<String>,
+
+    /// Anchor time the loop interval steps forward from each day (default: 00:00)
+    #[arg(long, value_name = "HH:MM", requires = "interval")]
+    pub interval_start: Option<String>,
+
+    /// Write PID file for daemon management
+    #[arg(long)]
+    pub pid_file: Option<String>,
+
+    /// How to write --pid-file
+    #[arg(
+        long,
+        value_enum,
+        value_name = "FORMAT",
+        default_value_t,
+        requires = "pid_file"
+    )]
+    pub pid_format: PidFormat,
+
+    /// Write only the bare PID to --pid-file; the same as --pid-format bare
+    #[arg(long, requires = "pid_file", conflicts_with = "pid_format")]
+    pub legacy_pid_format: bool,
+
+    /// Add a random delay of up to this many seconds (at most three days) to each scheduled run
+    #[arg(long, value_name = "SECONDS", default_value_t = 0, value_parser = clap::value_parser!(u64).range(..=MAX_JITTER_SECONDS))]
+    pub jitter: u64,
+
+    /// Leave at least this many seconds between the end of one execution and the start
+    /// of the next (up to a week), delaying the later one if needed
+    #[arg(long, value_name = "SECONDS", value_parser = clap::value_parser!(u64).range(..=MAX_SPACING_SECONDS))]
+    pub min_spacing: Option<u64>,
+
+    /// Record in each log entry how many milliseconds after its scheduled time the run
+    /// started (drift_ms)
+    #[arg(long)]
+    pub verbose_timing: bool,
+
+    /// Serve Prometheus metrics at /metrics on this port
+    #[arg(long, value_name = "PORT")]
+    pub metrics_port: Option<u16>,
+
+    /// Serve a liveness check at /healthz on this port
+    #[arg(long, value_name = "PORT")]
+    pub health_port: Option<u16>,
+
+    /// Report unhealthy (503) once this many runs in a row have failed
+    #[arg(long, value_name = "RUNS", default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    pub unhealthy_after: u32,
+
+    /// Seed for the jitter random number generator, for reproducible fire times
+    #[arg(long, value_name = "SEED")]
+    pub jitter_seed: Option<u64>,
+
+    /// Number of times to retry a failed run before giving up (up to 100)
+    #[arg(long, value_name = "N", default_value_t = 0, value_parser = clap::value_parser!(u32).range(..=MAX_RETRIES as i64))]
+    pub retries: u32,
+
+    /// Keep going after a failure: later messages of the same run, later --time runs in
+    /// single mode and later cycles in loop mode. Without it, loop mode stops at the first
+    /// failed cycle
+    #[arg(long)]
+    pub continue_on_error: bool,
+
+    /// Further retries for network errors and other transient failures only, with jittered
+    /// backoff (up to 100). Failures like a bad flag are not retried by these
+    #[arg(long, value_name = "N", default_value_t = 0, value_parser = clap::value_parser!(u32).range(..=MAX_RETRIES as i64))]
+    pub network_retries: u32,
+
+    /// Base delay in seconds before the first retry, doubling after each failed attempt
+    #[arg(long, value_name = "SECONDS", default_value_t = 30)]
+    pub retry_delay: u64,
+
+    /// After a run fails because claude is rate limited, wait at least this many seconds
+    /// before the next loop cycle (up to a week)
+    #[arg(long, value_name = "SECONDS", default_value_t = 1800, value_parser = clap::value_parser!(u64).range(..=MAX_COOLDOWN_SECONDS))]
+    pub rate_limit_cooldown: u64,
+
+    /// Kill the command and treat the run as failed if it takes longer than this many seconds
+    #[arg(long, value_name = "SECONDS")]
+    pub timeout: Option<u64>,
+
+    /// Directory to run claude in (default: the directory the scheduler was started from)
+    #[arg(long, value_name = "PATH")]
+    pub working_dir: Option<String>,
+
+    /// Set an environment variable for the claude process (repeatable, e.g. --env HTTPS_PROXY=http://proxy:8080)
+    #[arg(long, value_name = "KEY=VALUE", value_parser = parse_env_var)]
+    #[serde(serialize_with = "print_config::serialize_env")]
+    pub env: Vec<(String, String)>,
+
+    /// Overwrite this file with the raw output of each successful run, so it always holds
+    /// the latest response
+    #[arg(long, value_name = "PATH")]
+    pub output_file: Option<String>,
+
+    /// Append each successful response to this markdown file under a heading with the
+    /// time and cycle, building a readable journal
+    #[arg(long, value_name = "PATH")]
+    pub journal: Option<String>,
+
+    /// Read the API key from this file before each claude run and pass it to claude as
+    /// ANTHROPIC_API_KEY, so a rotated key is picked up without a restart
+    #[arg(long, value_name = "PATH")]
+    pub api_key_file: Option<String>,
+
+    /// Path or name of the claude executable to run
+    #[arg(long, value_name = "PATH", default_value = "claude")]
+    pub claude_bin: String,
+
+    /// Extra argument to pass through to claude before the message (repeatable, e.g. --claude-arg --model --claude-arg opus)
+    #[arg(long = "claude-arg", value_name = "ARG", allow_hyphen_values = true)]
+    pub claude_args: Vec<String>,
+
+    /// POST a JSON summary of each run result to this URL
+    #[arg(long, value_name = "URL")]
+    #[serde(serialize_with = "print_config::serialize_webhook")]
+    pub webhook: Option<String>,
+
+    /// Post a summary of each run to this Slack incoming webhook URL
+    #[arg(long, value_name = "URL")]
+    #[serde(serialize_with = "print_config::serialize_webhook")]
+    pub slack_webhook: Option<String>,
+
+    /// Show a desktop notification after each run
+    #[arg(long)]
+    pub notify: bool,
+
+    /// In loop mode, send an alert to the notification targets once this many cycles in a
+    /// row have failed, and again each time that streak doubles
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    pub alert_after: Option<u32>,
+
+    /// IANA timezone to interpret scheduled times in (e.g. America/New_York, default: local time)
+    #[arg(long, value_name = "ZONE")]
+    #[serde(serialize_with = "print_config::serialize_timezone")]
+    pub timezone: Option<Tz>,
+
+    /// Show scheduled times on both the --timezone clock and the local clock
+    #[arg(long, requires = "timezone")]
+    pub tz_display: bool,
+
+    /// Run today rather than tomorrow when a --time has passed by at most this many seconds
+    /// (up to a day)
+    #[arg(long, default_value_t = 0, value_name = "SECONDS", value_parser = clap::value_parser!(u64).range(..=MAX_GRACE_SECONDS))]
+    pub grace_seconds: u64,
+}
+
+impl Config {
+    /// The messages sent at each scheduled time, in order.
+    fn messages(&self) -> Vec<&str> {
+        if self.message.is_empty() {
+            return vec![DEFAULT_MESSAGE];
+        }
+        self.message.iter().map(String::as_str).collect()
+    }
+
+    /// Where log entries are written, for banners and dry runs.
+    fn log_destination(&self) -> String {
+        if self.no_log_file {
+            return "Log file: disabled".to_string();
+        }
+        match &self.single_log_file {
+            Some(path) => format!("Log file: {path}"),
+            None => format!("Log directory: {}", self.log_dir),
+        }
+    }
+
+    fn ping_prompt(&self) -> &str {
+        self.ping_prompt.as_deref().unwrap_or(DEFAULT_PING_PROMPT)
+    }
+
+    /// How long ago a `--time` can have passed and still run today.
+    fn grace(&self) -> chrono::Duration {
+        i64::try_from(self.grace_seconds)
+            .ok()
+            .and_then(chrono::Duration::try_seconds)
+            .unwrap_or(chrono::Duration::MAX)
+    }
+
+    /// The action's log name, banner label and a description of each step, for banners
+    /// and dry runs.
+    fn action_summary(&self) -> (&'static str, &'static str, Vec<String>) {
+        if let Some(command) = &self.exec {
+            ("exec", "Command", vec![build_exec_command(command)])
+        } else if self.keepalive {
+            (
+                "keepalive",
+                "Action",
+                vec!["Send a minimal keepalive prompt".to_string()],
+            )
+        } else if self.ping_mode {
+            ("ping", "Action", vec![self.ping_description().to_string()])
+        } else {
+            let commands = self
+                .messages()
+                .into_iter()
+                .map(|message| build_claude_command(message, &self.claude_args))
+                .collect();
+            ("claude", "Command", commands)
+        }
+    }
+
+    /// A description of each `--also` action, in the order they run.
+    fn extra_descriptions(&self) -> Vec<String> {
+        self.also
+            .iter()
+            .map(|extra| match extra {
+                ExtraAction::Ping => self.ping_description().to_string(),
+                ExtraAction::Keepalive => "Send a minimal keepalive prompt".to_string(),
+            })
+            .collect()
+    }
+
+    /// A description of every step run at each scheduled time, `--also` actions last.
+    fn step_descriptions(&self) -> Vec<String> {
+        let (_, _, mut descriptions) = self.action_summary();
+        descriptions.extend(self.extra_descriptions());
+        descriptions
+    }
+
+    /// The banner describing what will run at each scheduled time, one line per step.
+    fn action_banner(&self) -> String {
+        let (_, label, descriptions) = self.action_summary();
+        let mut lines = match descriptions.as_slice() {
+            [description] => vec![format!("{label}: {description}")],
+            _ => descriptions
+                .iter()
+                .enumerate()
+                .map(|(index, description)| format!("{label} {}: {description}", index + 1))
+                .collect(),
+        };
+        lines.extend(
+            self.extra_descriptions()
+                .into_iter()
+                .map(|description| format!("Also: {description}")),
+        );
+        lines.join("\n")
+    }
+
+    /// What ping mode does, for banners and dry runs.
+    fn ping_description(&self) -> &str {
+        match self.ping_prompt {
+            Some(_) => "Send a custom ping prompt",
+            None => "Query global weather information",
+        }
+    }
+
+    /// Days of the week runs are restricted to, or `None` to run every day.
+    fn allowed_days(&self) -> Result<Option<Vec<Weekday>>> {
+        self.days.as_deref().map(parse_days).transpose()
+    }
+
+    /// How banners show a scheduled time: on the local clock, or with `--tz-display`
+    /// on both the `--timezone` clock and the local one.
+    fn display_time(&self, time: DateTime<Local>) -> String {
+        match self.timezone {
+            Some(tz) if self.tz_display => format_dual_clock(&time, tz),
+            _ => time.format("%Y-%m-%d %H:%M:%S").to_string(),
+        }
+    }
+
+    /// The daily window fire points are skipped in, if any.
+    fn blackout(&self) -> Result<Option<Blackout>> {
+        self.blackout.as_deref().map(Blackout::parse).transpose()
+    }
+
+    pub fn claude_options(&self) -> ClaudeOptions {
+        ClaudeOptions {
+            program: self.claude_bin.clone(),
+            extra_args: self.claude_args.clone(),
+            timeout: self.timeout.map(Duration::from_secs),
+            working_dir: self.working_dir.clone(),
+            env: self.env.clone(),
+            api_key_file: self.api_key_file.clone(),
+        }
+    }
+}
+
+impl Default for Config {
+    /// The settings a bare `ccschedule` invocation runs with, leaving out the
+    /// `CCS_*` environment variables the command line falls back to.
+    fn default() -> Self {
+        let matches = default_command().get_matches_from(["ccschedule"]);
+        Cli::from_arg_matches(&matches)
+            .expect("the defaults are valid")
+            .run
+    }
+}
+
+/// The command line with its environment variable fallbacks removed.
+fn default_command() -> clap::Command {
+    Cli::command().mut_args(|arg| arg.env(None::<&str>))
+}
+
+/// Runs the schedule described by a [`Config`], as `ccschedule run` does.
+pub struct Scheduler {
+    config: Config,
+    clock: Arc<dyn Clock>,
+}
+
+impl Scheduler {
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Waits on `clock` instead of the system clock, e.g. a
+    /// [`MockClock`](clock::MockClock) in tests.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Waits for each scheduled time and runs the action, returning once the schedule is
+    /// finished or cancelled with Ctrl+C.
+    ///
+    /// Only the command line reads a `--config` file, so a [`Config`] naming one is
+    /// rejected rather than having the file silently ignored.
+    pub async fn run(self) -> Result<()> {
+        if let Some(path) = &self.config.config {
+            anyhow::bail!(
+                "Scheduler doesn't read config files ({path}); set the settings on the Config instead"
+            );
+        }
+        run(self.config, self.clock.as_ref(), None).await
+    }
+}
+
+/// Parses the command line and runs the requested subcommand.
+pub async fn cli_main() -> Result<()> {
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+
+    match cli.into_command() {
+        Commands::Run(args) => match args.config.clone() {
+            Some(path) => {
+                // Flags may come before or after an explicit `run`
+                let run_matches = matches.subcommand_matches("run").unwrap_or(&matches);
+                let config_file = ConfigFile::new(&path, run_matches.clone());
+                run(config_file.load()?, &SystemClock, Some(&config_file)).await
+            }
+            None => run(*args, &SystemClock, None).await,
+        },
+        Commands::Status { pid_file } => {
+            println!("{}", pid_status(&pid_file)?.describe());
+            Ok(())
+        }
+        Commands::Stop { pid_file } => {
+            let pid = stop_scheduler(&pid_file, &SystemProcesses)?;
+            println!("Stopped scheduler (PID {pid}) and removed {pid_file}");
+            Ok(())
+        }
+        Commands::Export {
+            log_dir,
+            format: ExportFormat::Csv,
+            out,
+        } => {
+            let summary = match &out {
+                Some(path) => {
+                    let file = std::fs::File::create(path)
+                        .with_context(|| format!("Failed to create {path}"))?;
+                    export_logs(std::path::Path::new(&log_dir), file)?
+                }
+                None => export_logs(std::path::Path::new(&log_dir), std::io::stdout().lock())?,
+            };
+            // Report on stderr so it doesn't mix with CSV written to stdout
+            eprintln!(
+                "Exported {} log entries{}",
+                summary.rows,
+                out.map(|path| format!(" to {path}")).unwrap_or_default()
+            );
+            if summary.skipped > 0 {
+                eprintln!("Warning: Skipped {} malformed lines", summary.skipped);
+            }
+            Ok(())
+        }
+        Commands::Report {
+            log_dir,
+            since,
+            format,
+        } => {
+            let report = report_logs(std::path::Path::new(&log_dir), since)?;
+            print!("{}", report.render(format)?);
+            Ok(())
+        }
+        Commands::Logs { log_dir, follow } => show_logs(&log_dir, follow).await,
+    }
+}
+
+/// Prints today's log, then with `follow` keeps printing entries as they are appended
+/// until Ctrl+C.
+async fn show_logs(log_dir: &str, follow: bool) -> Result<()> {
+    let mut follower = LogFollower::new(log_dir, Local::now().date_naive());
+    for line in follower.poll()? {
+        println!("{line}");
+    }
+    if !follow {
+        return Ok(());
+    }
+
+    loop {
+        tokio::select! {
+            _ = sleep(FOLLOW_POLL_INTERVAL) => {}
+            _ = tokio::signal::ctrl_c() => return Ok(()),
+        }
+        for line in follower.poll()? {
+            println!("{line}");
+        }
+        if follower.roll_over(Local::now().date_naive()) {
+            eprintln!("Following {}", follower.path());
+            for line in follower.poll()? {
+                println!("{line}");
+            }
+        }
+    }
+}
+
+/// Adds the `--sqlite` sink to `logger`, if one was asked for.
+#[cfg(feature = "sqlite")]
+fn with_sqlite(logger: Logger, path: Option<&str>) -> Result<Logger> {
+    let sink = path.map(sqlite_sink::SqliteSink::open).transpose()?;
+    Ok(logger.with_sqlite(sink))
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn with_sqlite(logger: Logger, path: Option<&str>) -> Result<Logger> {
+    if path.is_some() {
+        anyhow::bail!(
+            "--sqlite needs a build with the `sqlite` feature (cargo install --features sqlite)"
+        );
+    }
+    Ok(logger)
+}
+
+/// Runs the schedule in `args`. In loop mode, `config_file` is re-read on SIGHUP.
+async fn run(mut args: Config, clock: &dyn Clock, config_file: Option<&ConfigFile>) -> Result<()> {
+    if args.print_config {
+        println!("{}", print_config::effective_config(&args)?);
+        return Ok(());
+    }
+
+    let color = color_enabled(args.no_color);
+    let console = Console::new(args.json)
+        .with_quiet(args.quiet)
+        .with_progress_bar(args.progress_bar)
+        .with_banner(!args.no_banner)
+        .with_color(color);
+
+    if let Some(dir) = &args.working_dir {
+        validate_working_dir(dir)?;
+    }
+
+    let syslog = if args.syslog {
+        Some(SyslogSink::connect(args.syslog_facility)?)
+    } else {
+        None
+    };
+
+    // Initialize logger
+    let logger = Logger::new(&args.log_dir)
+        // The JSON output modes leave out the human summaries, like the rest of the prose
+        .with_echo(!args.json && !args.log_stdout)
+        .with_echo_level(args.log_level)
+        .with_color(color)
+        .with_format(args.log_format)
+        .with_max_log_size(args.max_log_size)
+        .with_retention_days(args.log_retention_days)
+        .with_compression(args.compress_logs)
+        .with_response_content(!args.no_response_log)
+        .with_redactions(args.redact.clone())
+        .with_max_response_chars(args.max_response_log_chars)
+        .with_single_file(args.single_log_file.clone())
+        .with_stdout(args.log_stdout)
+        .with_file(!args.no_log_file)
+        .with_failure_policy(args.log_failure_policy)
+        .with_syslog(syslog);
+    let logger = with_sqlite(logger, args.sqlite.as_deref())?;
+    logger.init().context("Failed to initialize logger")?;
+
+    if let Some(warning) = message_source_warning(&args)
+        && let Err(e) = logger.log_warning(&warning)
+    {
+        eprintln!("Warning: Failed to log warning: {e}");
+    }
+    args.message = resolve_messages(&args, std::io::stdin())?;
+    if args.strict_template {
+        // Catch a typo now rather than at the first run, possibly hours away
+        let vars = TemplateVars {
+            now: clock.now(),
+            cycle_number: None,
+        };
+        for message in args.messages() {
+            template::render(message, &vars, true)?;
+        }
+    }
+
+    // Dry runs report the binary check themselves
+    if !args.dry_run {
+        startup_health_check(&args, &logger, console).await?;
+    }
+
+    // Write PID file if requested
+    let mode = if args.loop_mode {
+        RunMode::Loop
+    } else {
+        RunMode::Single
+    };
+    let pid_file = match args.pid_file {
+        Some(ref path) => {
+            let format = if args.legacy_pid_format {
+                PidFormat::Bare
+            } else {
+                args.pid_format
+            };
+            let pid_file = PidFile::create(path, mode, format)?;
+            console.progress(&format!(
+                "PID file written: {path} (PID: {})",
+                pid_file.pid()
+            ));
+            Some(pid_file)
+        }
+        None => None,
+    };
+
+    if args.loop_mode {
+        // Loop mode: ignore time parameter and use predefined schedule
+        let actions = actions_from_args(&args);
+        run_loop_mode(
+            &args,
+            &logger,
+            console,
+            clock,
+            pid_file.as_ref(),
+            actions,
+            config_file,
+        )
+        .await?;
+    } else {
+        // Single execution mode
+        let days = args.allowed_days()?;
+        let target_times =
+            resolve_target_times(&args.time, args.timezone, days.as_deref(), args.grace())?;
+        if let Some(blackout) = args.blackout()?
+            && let Some(time) = target_times
+                .iter()
+                .find(|time| blackout.covers(**time, args.timezone))
+        {
+            anyhow::bail!(
+                "Scheduled time {} falls in the --blackout window {blackout}",
+                time.format("%Y-%m-%d %H:%M:%S")
+            );
+        }
+
+        let actions = actions_from_args(&args);
+        run_single_mode(
+            &args,
+            &logger,
+            console,
+            clock,
+            pid_file.as_ref(),
+            &target_times,
+            actions,
+        )
+        .await?;
+    }
+
+    // Cleanup PID file
+    if cleanup_pid_file(&args.pid_file)
+        && let Some(ref pid_file) = args.pid_file
+    {
+        console.progress(&format!("PID file removed: {pid_file}"));
+    }
+    Ok(())
+}
+
+async fn run_single_mode(
+    args: &Config,
+    logger: &Logger,
+    console: Console,
+    clock: &dyn Clock,
+    pid_file: Option<&PidFile>,
+    target_times: &[DateTime<Local>],
+    actions: Vec<Box<dyn Action>>,
+) -> Result<()> {
+    let max_runtime = MaxRuntime::from_args(args, clock.now())?;
+    if args.dry_run {
+        for record in dry_run_records(args, target_times) {
+            console.record(&record);
+        }
+        for target_time in target_times {
+            console.line(&format!(
+                "Would run at: {}",
+                args.display_time(*target_time)
+            ));
+        }
+        console.line(&args.action_banner());
+        print_timezone(console, args.timezone);
+        if let Some(dir) = &args.working_dir {
+            console.line(&format!("Working directory: {dir}"));
+        }
+        console.line(&args.log_destination());
+        return report_dry_run_binary(args, console).await;
+    }
+
+    console.banner(Banner::Single);
+    for target_time in target_times {
+        console.progress(&format!(
+            "Scheduled to run at: {}",
+            args.display_time(*target_time)
+        ));
+    }
+    console.progress(&args.action_banner());
+    if let Some(tz) = args.timezone {
+        console.progress(&format!("Timezone: {tz}"));
+    }
+    if let Some(dir) = &args.working_dir {
+        console.progress(&format!("Working directory: {dir}"));
+    }
+    console.progress(&args.log_destination());
+    console.progress("Press Ctrl+C to cancel...\n");
+
+    let shutdown = Shutdown::new();
+    shutdown.listen_for_ctrl_c(console, "\nCancelled by user");
+    let systemd = Systemd::from_env();
+    systemd.ready();
+
+    // Only number the runs when there is more than one to tell apart
+    let numbered = target_times.len() > 1;
+    let policy = FailurePolicy::from_args(args);
+    let mut failed_runs = 0;
+    let notifier = Notifier::new(args.webhook.clone())
+        .with_slack_webhook(args.slack_webhook.clone())
+        .with_desktop(args.notify);
+    let metrics = start_metrics(args.metrics_port, console).await?;
+    let health = start_health(args, console).await?;
+    let context = RunContext {
+        args,
+        logger,
+        notifier: &notifier,
+        metrics: &metrics,
+        health: &health,
+        console,
+    };
+    let mut jitter = Jitter::new(args.jitter, args.jitter_seed);
+    let mut spacing = Spacing::new(args.min_spacing);
+
+    for (index, &target_time) in target_times.iter().enumerate() {
+        let run_number = numbered.then_some(index as u32 + 1);
+        let target_time = jitter.apply_and_log(target_time, logger, run_number);
+        let target_time = spacing.apply_and_log(target_time, logger, run_number);
+        record_next_fire(pid_file, target_time);
+        if index == 0 {
+            console.next_run(target_time);
+        }
+
+        // Wait until the target time
+        if !wait_until(
+            max_runtime.cap(target_time),
+            "Time remaining",
+            console,
+            clock,
+            &shutdown,
+            systemd,
+        )
+        .await
+        {
+            return Ok(());
+        }
+        if max_runtime.has_elapsed(clock.now()) {
+            max_runtime.report(logger, console);
+            return Ok(());
+        }
+
+        match run_number {
+            Some(n) => console.line(&format!(
+                "\nRunning scheduled action {n} of {}...",
+                target_times.len()
+            )),
+            None => console.line("\nRunning scheduled action..."),
+        }
+
+        let Some(running) = RunLock::global().acquire(args.on_overlap).await else {
+            report_overlap_skip(logger, console, run_number);
+            continue;
+        };
+        let outcome = run_fire(context, &actions, run_number, target_time, "").await;
+        drop(running);
+        spacing.record_run(clock.now());
+        if !outcome.is_success() {
+            let error = outcome.into_error();
+            if !policy.proceeds_after(false) {
+                return Err(error);
+            }
+            failed_runs += 1;
+            eprintln!(
+                "{}",
+                console.paint(
+                    &format!("Run failed, continuing with the next scheduled time: {error}"),
+                    Tone::Error
+                )
+            );
+        }
+
+        if shutdown.is_requested() {
+            return Ok(());
+        }
+    }
+
+    if failed_runs > 0 {
+        anyhow::bail!(
+            "{failed_runs} of {} scheduled runs failed",
+            target_times.len()
+        );
+    }
+    console.banner(Banner::Footer);
+    Ok(())
+}
+
+/// What every run needs besides the action itself: settings, log and notification targets.
+#[derive(Clone, Copy)]
+struct RunContext<'a> {
+    args: &'a Config,
+    logger: &'a Logger,
+    notifier: &'a Notifier,
+    metrics: &'a Metrics,
+    health: &'a Health,
+    console: Console,
+}
+
+/// What a successful action run reports back to the run mode.
+#[derive(Debug)]
+struct ActionSuccess {
+    /// Console summary, e.g. "ping completed successfully!".
+    line: String,
+    /// The response, for actions that keep one.
+    response: Option<String>,
+}
+
+/// Runs `action` with retries, then logs the result and sends notifications whether it
+/// succeeded or not.
+async fn execute_action(
+    context: RunContext<'_>,
+    action: &dyn Action,
+    cycle_number: Option<u32>,
+    step: Option<u32>,
+    fire_time: DateTime<Local>,
+) -> Result<ActionSuccess> {
+    let RunContext { args, logger, .. } = context;
+    let drift_ms = args
+        .verbose_timing
+        .then(|| timing_drift_ms(fire_time, Local::now()));
+    if let Some(drift_ms) = drift_ms {
+        context
+            .console
+            .line(&format!("Started {drift_ms} ms after the scheduled time"));
+    }
+    run_hook(args, logger, Hook::Pre, cycle_number).await;
+    log_invocation(args, logger, action, cycle_number);
+    let result = match run_with_retries(args, logger, action.name(), cycle_number, || {
+        action.execute(cycle_number)
+    })
+    .await
+    {
+        Ok(output) => {
+            for warning in &output.warnings {
+                if let Err(e) = logger.log_warning(warning) {
+                    eprintln!("Warning: {warning} (and failed to log it: {e})");
+                }
+            }
+            save_response(args, logger, &output.response, cycle_number, step);
+            let entry = action
+                .success_entry(&output, cycle_number)
+                .with_step(step)
+                .with_drift(drift_ms);
+            let response = entry.response_content.clone();
+            record_result(context, entry, fire_time).await;
+            Ok(ActionSuccess {
+                line: action.success_line(&output),
+                response,
+            })
+        }
+        Err(e) => {
+            let entry = run_error_entry(action.name(), &e, cycle_number)
+                .with_step(step)
+                .with_drift(drift_ms);
+            record_result(context, entry, fire_time).await;
+            Err(e)
+        }
+    };
+    let status = if result.is_ok() { "success" } else { "error" };
+    run_hook(args, logger, Hook::Post { status }, cycle_number).await;
+    result
+}
+
+/// Writes a successful run's response to `--output-file` and `--journal`, if given. A
+/// failed write is logged as a warning and doesn't fail the run.
+fn save_response(
+    args: &Config,
+    logger: &Logger,
+    response: &str,
+    cycle_number: Option<u32>,
+    step: Option<u32>,
+) {
+    let warn = |warning: String| {
+        if let Err(log_err) = logger.log_warning(&warning) {
+            eprintln!("Warning: {warning} (and failed to log it: {log_err})");
+        }
+    };
+    if let Some(path) = &args.output_file
+        && let Err(e) = write_output_file(path, response)
+    {
+        warn(format!("Failed to write --output-file: {e:#}"));
+    }
+    if let Some(path) = &args.journal {
+        let entry = journal_entry(Local::now(), cycle_number, step, response);
+        if let Err(e) = append_to_journal(path, &entry) {
+            warn(format!("Failed to write --journal: {e:#}"));
+        }
+    }
+}
+
+/// Replaces the contents of `path` with `response` in one rename, so a reader never sees
+/// a partly written response.
+fn write_output_file(path: &str, response: &str) -> Result<()> {
+    let temp_path = format!("{path}.tmp");
+    std::fs::write(&temp_path, response).with_context(|| format!("Failed to write {temp_path}"))?;
+    std::fs::rename(&temp_path, path).with_context(|| format!("Failed to replace {path}"))
+}
+
+/// How many milliseconds after `fire_time` a run started at `now`; negative if it
+/// started early.
+fn timing_drift_ms(fire_time: DateTime<Local>, now: DateTime<Local>) -> i64 {
+    (now - fire_time).num_milliseconds()
+}
+
+/// A `--pre-hook` or `--post-hook` run around each execution.
+#[derive(Clone, Copy, Debug)]
+enum Hook {
+    Pre,
+    /// `status` is how the execution went, passed to the hook as `CCS_STATUS`.
+    Post {
+        status: &'static str,
+    },
+}
+
+impl Hook {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Pre => "pre-hook",
+            Self::Post { .. } => "post-hook",
+        }
+    }
+
+    fn command(self, args: &Config) -> Option<&str> {
+        match self {
+            Self::Pre => args.pre_hook.as_deref(),
+            Self::Post { .. } => args.post_hook.as_deref(),
+        }
+    }
+}
+
+/// Builds the shell invocation for a hook, run like an `--exec` command.
+fn hook_command(hook: Hook, shell_command: &str, options: &ClaudeOptions) -> Command {
+    let mut command = exec_command(shell_command, options);
+    if let Hook::Post { status } = hook {
+        command.env("CCS_STATUS", status);
+    }
+    command
+}
+
+/// Runs `hook` if one is configured. A failing hook is logged but doesn't
+/// affect the execution it surrounds.
+async fn run_hook(args: &Config, logger: &Logger, hook: Hook, cycle_number: Option<u32>) {
+    let Some(shell_command) = hook.command(args) else {
+        return;
+    };
+    let options = args.claude_options();
+    let result = run_command(hook_command(hook, shell_command, &options), options.timeout).await;
+    if let Err(e) = result {
+        let message = format!("{shell_command}: {e:#}");
+        let entry =
+            LogEntry::new_with_response(hook.name(), "error", Some(message), None, cycle_number);
+        if let Err(log_err) = logger.log(entry) {
+            eprintln!("Warning: {} failed: {e:#}", hook.name());
+            eprintln!("Warning: Failed to log {} failure: {log_err}", hook.name());
+        }
+    }
+}
+
+/// The steps run at each scheduled time, as chosen on the command line: one per
+/// `--message`, or the single exec, keepalive or ping action.
+fn actions_from_args(args: &Config) -> Vec<Box<dyn Action>> {
+    let options = args.claude_options();
+    let mut actions = main_actions(args, &options);
+    actions.extend(args.also.iter().map(|extra| match extra {
+        ExtraAction::Ping => {
+            Box::new(PingAction::new(args.ping_prompt(), options.clone())) as Box<dyn Action>
+        }
+        ExtraAction::Keepalive => Box::new(KeepaliveAction::new(options.clone())),
+    }));
+    actions
+}
+
+/// The actions the mode flags choose: one per message for claude, otherwise just one.
+fn main_actions(args: &Config, options: &ClaudeOptions) -> Vec<Box<dyn Action>> {
+    let options = options.clone();
+    if let Some(command) = &args.exec {
+        vec![Box::new(ExecAction::new(command, options))]
+    } else if args.keepalive {
+        vec![Box::new(KeepaliveAction::new(options))]
+    } else if args.ping_mode {
+        vec![Box::new(PingAction::new(args.ping_prompt(), options))]
+    } else {
+        args.messages()
+            .into_iter()
+            .map(|message| {
+                let action = ClaudeAction::new(message, options.clone())
+                    .with_strict_template(args.strict_template)
+                    .with_git_context(args.git_context);
+                Box::new(action) as Box<dyn Action>
+            })
+            .collect()
+    }
+}
+
+/// What happens after a failed step or run, chosen with `--continue-on-error`. The same
+/// policy applies to the steps within a run and to the runs of a schedule.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum FailurePolicy {
+    Stop,
+    Continue,
+}
+
+impl FailurePolicy {
+    fn from_args(args: &Config) -> Self {
+        if args.continue_on_error {
+            Self::Continue
+        } else {
+            Self::Stop
+        }
+    }
+
+    /// Whether to go on to the next step or run after one that did or didn't succeed.
+    fn proceeds_after(self, succeeded: bool) -> bool {
+        succeeded || self == Self::Continue
+    }
+}
+
+/// How the steps of one scheduled run went.
+#[derive(Debug, Default)]
+struct StepsOutcome {
+    steps: usize,
+    succeeded: usize,
+    /// Total response length of the successful steps.
+    response_len: usize,
+    errors: Vec<anyhow::Error>,
+}
+
+impl StepsOutcome {
+    fn is_success(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    fn describe(&self) -> String {
+        let result = if self.is_success() {
+            "success"
+        } else if self.succeeded > 0 {
+            "partial"
+        } else {
+            "failed"
+        };
+        format!(
+            "{result} ({} of {} steps succeeded)",
+            self.succeeded, self.steps
+        )
+    }
+
+    /// The error a failed run is reported with: the step's own error when there is only
+    /// one step, otherwise the overall result.
+    fn into_error(mut self) -> anyhow::Error {
+        if self.steps == 1 && self.errors.len() == 1 {
+            return self.errors.remove(0);
+        }
+        anyhow::anyhow!("Run {}", self.describe())
+    }
+}
+
+/// Runs steps `0..steps` in order, stopping after a failure if `policy` says so. Each step
+/// yields its response length.
+async fn run_steps<F, Fut>(steps: usize, policy: FailurePolicy, mut run_step: F) -> StepsOutcome
+where
+    F: FnMut(usize) -> Fut,
+    Fut: Future<Output = Result<usize>>,
+{
+    let mut outcome = StepsOutcome {
+        steps,
+        ..StepsOutcome::default()
+    };
+    for index in 0..steps {
+        match run_step(index).await {
+            Ok(response_len) => {
+                outcome.succeeded += 1;
+                outcome.response_len += response_len;
+            }
+            Err(e) => {
+                outcome.errors.push(e);
+                if !policy.proceeds_after(false) {
+                    break;
+                }
+            }
+        }
+    }
+    outcome
+}
+
+/// Runs every step due at `fire_time`, printing each result with `prefix` (e.g. "Cycle 3 ")
+/// and, when there is more than one step, the overall result.
+async fn run_fire(
+    context: RunContext<'_>,
+    actions: &[Box<dyn Action>],
+    cycle_number: Option<u32>,
+    fire_time: DateTime<Local>,
+    prefix: &str,
+) -> StepsOutcome {
+    let console = context.console;
+    let steps = actions.len();
+    let policy = FailurePolicy::from_args(context.args);
+    let outcome = run_steps(steps, policy, |index| {
+        let action = actions[index].as_ref();
+        let step = (steps > 1).then_some(index as u32 + 1);
+        async move {
+            if let Some(step) = step {
+                console.line(&capitalize(&format!("{prefix}step {step} of {steps}...")));
+            }
+            let result = execute_action(context, action, cycle_number, step, fire_time).await;
+            match &result {
+                Ok(success) => {
+                    console.line(&console.paint(
+                        &capitalize(&format!("{prefix}{}", success.line)),
+                        Tone::Success,
+                    ));
+                    if let Some(response) = &success.response {
+                        console.line(&format!("Response length: {} characters", response.len()));
+                        console.result(response);
+                    }
+                }
+                // A lone failure is reported by the caller
+                Err(e) if steps > 1 => {
+                    eprintln!(
+                        "{}",
+                        console.paint(
+                            &format!("Step {} {} failed: {e}", index + 1, action.noun()),
+                            Tone::Error
+                        )
+                    );
+                }
+                Err(_) => {}
+            }
+            result.map(|success| success.response.map_or(0, |response| response.len()))
+        }
+    })
+    .await;
+
+    if steps > 1 {
+        let tone = if outcome.is_success() {
+            Tone::Success
+        } else if outcome.succeeded > 0 {
+            Tone::Warning
+        } else {
+            Tone::Error
+        };
+        console.line(&console.paint(
+            &capitalize(&format!("{prefix}result: {}", outcome.describe())),
+            tone,
+        ));
+    }
+    outcome
+}
+
+/// Upper-cases the first letter of a console line.
+fn capitalize(line: &str) -> String {
+    let mut chars = line.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Logs the outcome of a run, counts it in the metrics and health state and forwards it to any configured
+/// notification targets.
+async fn record_result(context: RunContext<'_>, entry: LogEntry, fire_time: DateTime<Local>) {
+    let logger = context.logger;
+    if let Err(e) = logger.log(entry.clone()) {
+        eprintln!(
+            "Warning: Failed to log {} {}: {e}",
+            entry.action, entry.status
+        );
+    }
+    context.metrics.record(&entry);
+    context.health.record(&entry);
+    context.notifier.notify(&entry, logger).await;
+    context.console.record(&RunRecord { entry, fire_time });
+}
+
+/// Escalates `failures` failed cycles in a row, the latest being `cycle_number`, to the
+/// log and the notification targets.
+async fn send_alert(context: RunContext<'_>, cycle_number: u32, failures: u32) {
+    let RunContext {
+        logger,
+        notifier,
+        console,
+        ..
+    } = context;
+    let message =
+        format!("{failures} cycles in a row have failed, most recently cycle {cycle_number}");
+    console.line(&console.paint(&format!("Alert: {message}"), Tone::Error));
+    let entry = LogEntry::new_with_response(
+        "scheduler",
+        "alert",
+        Some(message),
+        None,
+        Some(cycle_number),
+    );
+    if let Err(e) = logger.log(entry.clone()) {
+        eprintln!("Warning: Failed to log alert: {e}");
+    }
+    notifier.notify(&entry, logger).await;
+}
+
+/// Starts serving `/healthz` on `--health-port`, if given.
+async fn start_health(args: &Config, console: Console) -> Result<Arc<Health>> {
+    let health = Arc::new(Health::new(args.unhealthy_after));
+    if let Some(port) = args.health_port {
+        serve_health(http::bind(port).await?, Arc::clone(&health));
+        console.progress(&format!("Serving health checks on port {port} at /healthz"));
+    }
+    Ok(health)
+}
+
+/// Starts serving `/metrics` on `port`, if given. The counters are kept either way.
+async fn start_metrics(port: Option<u16>, console: Console) -> Result<Arc<Metrics>> {
+    let metrics = Arc::new(Metrics::default());
+    if let Some(port) = port {
+        serve_metrics(http::bind(port).await?, Arc::clone(&metrics));
+        console.progress(&format!("Serving metrics on port {port} at /metrics"));
+    }
+    Ok(metrics)
+}
+
+/// One execution as reported by `--json`: the log entry plus the time it was scheduled to fire.
+#[derive(Serialize, Deserialize, Debug)]
+struct RunRecord {
+    #[serde(flatten)]
+    entry: LogEntry,
+    fire_time: DateTime<Local>,
+}
+
+impl RunRecord {
+    /// The record for a run that a dry run would have fired at `fire_time`.
+    fn dry_run(args: &Config, fire_time: DateTime<Local>, cycle_number: Option<u32>) -> Self {
+        let (action, _, _) = args.action_summary();
+        let description = args.step_descriptions().join("\n");
+        let entry =
+            LogEntry::new_with_response(action, "dry_run", Some(description), None, cycle_number);
+        Self { entry, fire_time }
+    }
+}
+
+/// How many times a failed run is retried, and how long to back off between attempts.
+struct RetryPolicy {
+    retries: u32,
+    /// Further retries only transient failures get, on top of `retries`.
+    network_retries: u32,
+    base_delay: Duration,
+}
+
+impl RetryPolicy {
+    fn from_args(args: &Config) -> Self {
+        Self {
+            retries: args.retries,
+            network_retries: args.network_retries,
+            base_delay: Duration::from_secs(args.retry_delay),
+        }
+    }
+
+    /// How many attempts a run failing this way gets in all.
+    fn attempts_for(&self, kind: FailureKind) -> u32 {
+        match kind {
+            FailureKind::RateLimited => 1,
+            FailureKind::Transient => self
+                .retries
+                .saturating_add(self.network_retries)
+                .saturating_add(1),
+            FailureKind::Permanent => self.retries.saturating_add(1),
+        }
+    }
+
+    /// Exponential backoff: the base delay doubles after each failed attempt.
+    fn delay_for(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.base_delay.saturating_mul(factor)
+    }
+
+    /// The backoff for `attempt`, cut to a random 50-100% of it for transient
+    /// failures so clients that lost the network together don't retry together.
+    fn jittered_delay_for(&self, attempt: u32, kind: FailureKind) -> Duration {
+        let delay = self.delay_for(attempt);
+        match kind {
+            FailureKind::Transient => delay.mul_f64(rand::rng().random_range(0.5..=1.0)),
+            _ => delay,
+        }
+    }
+}
+
+/// Runs `operation`, retrying failures according to `policy`.
+/// `on_retry` is called with the failed attempt number, its error, and the upcoming delay.
+async fn retry_with_backoff<F, Fut, R, T>(
+    policy: &RetryPolicy,
+    mut operation: F,
+    mut on_retry: R,
+) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+    R: FnMut(u32, &anyhow::Error, Duration),
+{
+    let mut attempt = 1;
+    loop {
+        match operation().await {
+            Ok(output) => return Ok(output),
+            Err(e) => {
+                let kind = failure_kind(&e);
+                if attempt >= policy.attempts_for(kind) {
+                    return Err(e);
+                }
+                let delay = policy.jittered_delay_for(attempt, kind);
+                on_retry(attempt, &e, delay);
+                sleep(delay).await;
+                attempt += 1;
+            }
+        }
+    }
+}
+
+/// Runs an action with the retry policy from `args`, logging each failed attempt.
+async fn run_with_retries<F, Fut, T>(
+    args: &Config,
+    logger: &Logger,
+    action: &str,
+    cycle_number: Option<u32>,
+    operation: F,
+) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let policy = RetryPolicy::from_args(args);
+
+    retry_with_backoff(&policy, operation, |attempt, e, delay| {
+        let total_attempts = policy.attempts_for(failure_kind(e));
+        eprintln!("Attempt {attempt} of {total_attempts} failed, retrying in {delay:?}...");
+        if let Err(log_err) = logger.log_retry(
+            action,
+            attempt,
+            total_attempts,
+            &e.to_string(),
+            cycle_number,
+        ) {
+            eprintln!("Warning: Failed to log retry: {log_err}");
+        }
+    })
+    .await
+}
+
+/// Random delay added to fire times so many machines don't hit the API at the same second.
+struct Jitter {
+    max_seconds: u64,
+    rng: StdRng,
+}
+
+impl Jitter {
+    fn new(max_seconds: u64, seed: Option<u64>) -> Self {
+        let rng = match seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_os_rng(),
+        };
+        Self { max_seconds, rng }
+    }
+
+    /// Returns `target_time` shifted by a fresh random offset, along with the offset in seconds.
+    fn apply(&mut self, target_time: DateTime<Local>) -> (DateTime<Local>, u64) {
+        if self.max_seconds == 0 {
+            return (target_time, 0);
+        }
+        let offset = self.rng.random_range(0..=self.max_seconds);
+        i64::try_from(offset)
+            .ok()
+            .and_then(chrono::Duration::try_seconds)
+            .and_then(|delay| target_time.checked_add_signed(delay))
+            .map_or((target_time, 0), |jittered| (jittered, offset))
+    }
+
+    /// Applies jitter and records the chosen offset so the actual fire time can be traced.
+    fn apply_and_log(
+        &mut self,
+        target_time: DateTime<Local>,
+        logger: &Logger,
+        cycle_number: Option<u32>,
+    ) -> DateTime<Local> {
+        let (jittered, offset) = self.apply(target_time);
+        if self.max_seconds > 0
+            && let Err(e) = logger.log_jitter(offset, jittered, cycle_number)
+        {
+            eprintln!("Warning: Failed to log jitter: {e}");
+        }
+        jittered
+    }
+}
+
+/// Holds loop mode back for `--rate-limit-cooldown` after a rate-limited run.
+struct Cooldown {
+    length: chrono::Duration,
+    /// When the cooldown in progress ends.
+    until: Option<DateTime<Local>>,
+}
+
+impl Cooldown {
+    fn new(seconds: u64) -> Result<Self> {
+        let length = i64::try_from(seconds)
+            .ok()
+            .and_then(chrono::Duration::try_seconds)
+            .context("rate-limit-cooldown is too large")?;
+        Ok(Self {
+            length,
+            until: None,
+        })
+    }
+
+    /// Starts a cooldown at `now`, returning when it ends.
+    fn start(&mut self, now: DateTime<Local>) -> DateTime<Local> {
+        let end = now + self.length;
+        self.until = Some(end);
+        end
+    }
+
+    /// Returns `next_time`, pushed back to the end of the cooldown in progress. The
+    /// cooldown lasts until [`finish`](Self::finish), so a fire time computed again
+    /// after a reload still respects it.
+    fn apply(&self, next_time: DateTime<Local>) -> DateTime<Local> {
+        match self.until {
+            Some(end) if end > next_time => end,
+            _ => next_time,
+        }
+    }
+
+    /// Ends the cooldown once the wait for a fire time has completed.
+    fn finish(&mut self) {
+        self.until = None;
+    }
+}
+
+/// Keeps consecutive executions `--min-spacing` apart, so fire points that land close
+/// together don't hit the API back to back.
+struct Spacing {
+    min: chrono::Duration,
+    /// When the previous execution finished.
+    last_run: Option<DateTime<Local>>,
+}
+
+impl Spacing {
+    fn new(min_seconds: Option<u64>) -> Self {
+        let min = i64::try_from(min_seconds.unwrap_or(0))
+            .ok()
+            .and_then(chrono::Duration::try_seconds)
+            .unwrap_or(chrono::Duration::MAX);
+        Self {
+            min,
+            last_run: None,
+        }
+    }
+
+    /// Returns `fire_time`, pushed back if it is too soon after the last execution.
+    fn apply(&self, fire_time: DateTime<Local>) -> DateTime<Local> {
+        match self
+            .last_run
+            .and_then(|last_run| last_run.checked_add_signed(self.min))
+        {
+            Some(earliest) if fire_time < earliest => earliest,
+            _ => fire_time,
+        }
+    }
+
+    /// Applies the spacing and logs any delay it enforced.
+    fn apply_and_log(
+        &self,
+        fire_time: DateTime<Local>,
+        logger: &Logger,
+        cycle_number: Option<u32>,
+    ) -> DateTime<Local> {
+        let spaced = self.apply(fire_time);
+        if spaced > fire_time
+            && let Err(e) = logger.log_spacing(spaced - fire_time, spaced, cycle_number)
+        {
+            eprintln!("Warning: Failed to log spacing delay: {e}");
+        }
+        spaced
+    }
+
+    fn record_run(&mut self, finished_at: DateTime<Local>) {
+        self.last_run = Some(finished_at);
+    }
+}
+
+/// The `--max-runtime` limit on how long the scheduler keeps going, counted from startup.
+struct MaxRuntime {
+    started: DateTime<Local>,
+    limit: Option<chrono::Duration>,
+    /// The limit as given, for messages.
+    spec: String,
+}
+
+impl MaxRuntime {
+    fn from_args(args: &Config, started: DateTime<Local>) -> Result<Self> {
+        let spec = args.max_runtime.clone().unwrap_or_default();
+        let limit = args
+            .max_runtime
+            .as_deref()
+            .map(parse_duration)
+            .transpose()?;
+        Ok(Self {
+            started,
+            limit,
+            spec,
+        })
+    }
+
+    fn deadline(&self) -> Option<DateTime<Local>> {
+        self.limit.map(|limit| self.started + limit)
+    }
+
+    fn has_elapsed(&self, now: DateTime<Local>) -> bool {
+        self.deadline().is_some_and(|deadline| now >= deadline)
+    }
+
+    /// `fire_time`, or the deadline if that comes first, so a wait never outlasts the limit.
+    fn cap(&self, fire_time: DateTime<Local>) -> DateTime<Local> {
+        self.deadline()
+            .map_or(fire_time, |deadline| fire_time.min(deadline))
+    }
+
+    /// Announces and logs that the scheduler is stopping because the limit was reached.
+    fn report(&self, logger: &Logger, console: Console) {
+        let message = format!("Stopping after reaching --max-runtime {}", self.spec);
+        console.line(&format!("\n{message}"));
+        if let Err(e) = logger.log_termination(&message) {
+            eprintln!("Warning: Failed to log termination: {e}");
+        }
+    }
+}
+
+/// The records `--json` reports for a single-mode dry run, numbered when there are several.
+fn dry_run_records(args: &Config, target_times: &[DateTime<Local>]) -> Vec<RunRecord> {
+    let numbered = target_times.len() > 1;
+    target_times
+        .iter()
+        .enumerate()
+        .map(|(index, &fire_time)| {
+            RunRecord::dry_run(args, fire_time, numbered.then_some(index as u32 + 1))
+        })
+        .collect()
+}
+
+/// Shows a countdown until `target_time`, returning once it has been reached.
+/// Updates the PID file, if any, with when the next run is due.
+fn record_next_fire(pid_file: Option<&PidFile>, next_fire: DateTime<Local>) {
+    if let Some(pid_file) = pid_file
+        && let Err(e) = pid_file.record_next_fire(next_fire)
+    {
+        eprintln!("Warning: Failed to update PID file: {e}");
+    }
+}
+
+/// Counts down to `target_time`. Returns `false` if shutdown was requested before it arrived.
+///
+/// The remaining time is recomputed from the wall clock after every chunk, so a suspend or
+/// a clock adjustment is picked up within one chunk. Each chunk also pings the systemd
+/// watchdog, so chunks never outlast its deadline.
+async fn wait_until(
+    target_time: DateTime<Local>,
+    label: &str,
+    console: Console,
+    clock: &dyn Clock,
+    shutdown: &Shutdown,
+    systemd: Systemd,
+) -> bool {
+    // Only wake every second when there is a countdown to redraw
+    let mut cap = if console.should_print_progress() {
+        Duration::from_secs(1)
+    } else {
+        MAX_WAIT_CHUNK
+    };
+    if let Some(deadline) = systemd.watchdog_deadline() {
+        cap = cap.min(deadline);
+    }
+
+    let countdown = console.countdown(label, target_time);
+    let mut last_now = clock.now();
+    loop {
+        let now = clock.now();
+        if now >= target_time {
+            return true;
+        }
+        if now < last_now {
+            console.progress(&format!(
+                "\nClock moved back by {}s, recalculating wait",
+                (last_now - now).num_seconds()
+            ));
+        }
+        last_now = now;
+
+        let remaining = target_time.signed_duration_since(now);
+        countdown.update(now);
+        systemd.watchdog();
+
+        tokio::select! {
+            _ = clock.sleep(sleep_chunk(remaining, cap)) => {}
+            _ = shutdown.requested() => return false,
+        }
+    }
+}
+
+/// How long to sleep before checking the clock again: the time remaining, capped at `cap`.
+/// A remaining duration that is already zero or negative gives no sleep at all.
+fn sleep_chunk(remaining: chrono::Duration, cap: Duration) -> Duration {
+    remaining
+        .to_std()
+        .map_or(Duration::ZERO, |remaining| remaining.min(cap))
+}
+
+async fn run_loop_mode(
+    args: &Config,
+    logger: &Logger,
+    console: Console,
+    clock: &dyn Clock,
+    pid_file: Option<&PidFile>,
+    actions: Vec<Box<dyn Action>>,
+    config_file: Option<&ConfigFile>,
+) -> Result<()> {
+    let max_runtime = MaxRuntime::from_args(args, clock.now())?;
+    let mut plan = LoopPlan::new(args, actions)?;
+    let until = match args.until {
+        Some(ref until_str) => Some(resolve_until(until_str, args.timezone)?),
+        None => None,
+    };
+    // An immediate first run happens whatever the schedule says
+    if !args.start_now {
+        check_first_fire_point(
+            clock.now(),
+            &plan.schedule,
+            args.timezone,
+            plan.days.as_deref(),
+            plan.blackout.as_ref(),
+            until,
+        )?;
+    }
+
+    if args.dry_run {
+        let fire_times = simulate_fire_times(
+            clock.now(),
+            &plan.schedule,
+            args.timezone,
+            plan.days.as_deref(),
+            plan.blackout.as_ref(),
+            until,
+            args.dry_run_count as usize,
+        );
+        for (index, &fire_time) in fire_times.iter().enumerate() {
+            console.record(&RunRecord::dry_run(args, fire_time, Some(index as u32 + 1)));
+        }
+        console.line("Loop mode dry run:");
+        console.line(&format!("Schedule: {}", plan.schedule.describe()));
+        if let Some(days) = &plan.days {
+            console.line(&format!("Days: {}", format_days(days)));
+        }
+        if let Some(blackout) = &plan.blackout {
+            console.line(&format!("Blackout: {blackout}"));
+        }
+        console.line(&format!("Next {} fire times:", fire_times.len()));
+        for fire_time in &fire_times {
+            console.line(&format!("  {}", fire_time.format("%Y-%m-%d %H:%M:%S %a")));
+        }
+        console.line(&args.action_banner());
+        print_timezone(console, args.timezone);
+        if let Some(dir) = &args.working_dir {
+            console.line(&format!("Working directory: {dir}"));
+        }
+        console.line(&args.log_destination());
+        return report_dry_run_binary(args, console).await;
+    }
+
+    console.banner(Banner::Loop);
+    console.progress(&format!("Schedule: {}", plan.schedule.describe()));
+    if let Some(days) = &plan.days {
+        console.progress(&format!("Days: {}", format_days(days)));
+    }
+    if let Some(blackout) = &plan.blackout {
+        console.progress(&format!("Blackout: {blackout}"));
+    }
+    console.progress(&args.action_banner());
+    if let Some(tz) = args.timezone {
+        console.progress(&format!("Timezone: {tz}"));
+    }
+    if let Some(until) = until {
+        console.progress(&format!(
+            "Running until: {}",
+            until.format("%Y-%m-%d %H:%M:%S")
+        ));
+    }
+    if let Some(dir) = &args.working_dir {
+        console.progress(&format!("Working directory: {dir}"));
+    }
+    console.progress(&args.log_destination());
+    console.progress("Press Ctrl+C to stop...\n");
+
+    let shutdown = Shutdown::new();
+    shutdown.listen_for_ctrl_c(console, "\nStopping loop mode...");
+    let reload = Reload::new();
+    if let Some(config_file) = config_file {
+        reload
+            .listen_for_hangup()
+            .context("Failed to listen for SIGHUP")?;
+        console.progress(&format!("Send SIGHUP to reload {}", config_file.path()));
+    }
+    let systemd = Systemd::from_env();
+    systemd.ready();
+
+    // Cycle numbers carry on from the previous run unless --reset-cycle. An
+    // unwritable log directory has already been warned about, so numbering just
+    // starts from 1 rather than failing to be recorded every cycle.
+    let cycle_state = (!logger.file_unwritable()).then(|| CycleState::in_dir(&args.log_dir));
+    if args.reset_cycle
+        && let Some(cycle_state) = &cycle_state
+    {
+        cycle_state.reset()?;
+    }
+    let mut cycle_number = cycle_state.as_ref().map_or(1, |cycle_state| {
+        cycle_state.next_cycle().unwrap_or_else(|e| {
+            eprintln!("Warning: {e:#}; numbering cycles from 1");
+            1
+        })
+    });
+    if cycle_number > 1 {
+        console.progress(&format!("Resuming at cycle {cycle_number}"));
+    }
+    let mut summary = LoopSummary::default();
+    let policy = FailurePolicy::from_args(args);
+    let mut aborted_cycle = None;
+    let notifier = Notifier::new(args.webhook.clone())
+        .with_slack_webhook(args.slack_webhook.clone())
+        .with_desktop(args.notify);
+    let metrics = start_metrics(args.metrics_port, console).await?;
+    let health = start_health(args, console).await?;
+    let context = RunContext {
+        args,
+        logger,
+        notifier: &notifier,
+        metrics: &metrics,
+        health: &health,
+        console,
+    };
+    let mut jitter = Jitter::new(args.jitter, args.jitter_seed);
+    let mut spacing = Spacing::new(args.min_spacing);
+
+    let mut catch_up = None;
+    if args.catch_up {
+        let now = clock.now();
+        let last_run = logger.last_run_on(now.date_naive())?;
+        catch_up = find_missed_fire_point(
+            now,
+            &plan.schedule,
+            args.timezone,
+            plan.days.as_deref(),
+            last_run,
+        )
+        // A missed run isn't made up inside the blackout window
+        .filter(|missed| {
+            !plan
+                .blackout
+                .as_ref()
+                .is_some_and(|blackout| blackout.covers(*missed, args.timezone))
+        });
+    }
+
+    let mut start_now = args.start_now;
+    let mut cooldown = Cooldown::new(args.rate_limit_cooldown)?;
+    let mut announced_first_fire = false;
+
+    loop {
+        let next_time = if std::mem::take(&mut start_now) {
+            console.progress("Starting with an immediate run");
+            clock.now()
+        } else if let Some(missed) = catch_up.take() {
+            console.progress(&format!(
+                "Catching up on missed run scheduled for {}",
+                missed.format("%Y-%m-%d %H:%M:%S")
+            ));
+            missed
+        } else {
+            let now = clock.now();
+            let mut next_time = next_loop_time(
+                now,
+                &plan.schedule,
+                args.timezone,
+                plan.days.as_deref(),
+                plan.blackout.as_ref(),
+            );
+            next_time = cooldown.apply(next_time);
+            if is_past_until(next_time, until) {
+                console.line(&format!(
+                    "Next execution at {} is past the --until bound, stopping",
+                    next_time.format("%Y-%m-%d %H:%M:%S")
+                ));
+                break;
+            }
+            jitter.apply_and_log(next_time, logger, Some(cycle_number))
+        };
+        let next_time = spacing.apply_and_log(next_time, logger, Some(cycle_number));
+        record_next_fire(pid_file, next_time);
+        if !std::mem::replace(&mut announced_first_fire, true) {
+            console.next_run(next_time);
+        }
+
+        console.progress(&format!(
+            "Cycle {cycle_number} - Next execution: {}",
+            args.display_time(next_time)
+        ));
+
+        // Wait until the next scheduled time, starting over with the new settings if
+        // they are reloaded meanwhile
+        let wait = wait_until(
+            max_runtime.cap(next_time),
+            "Time until next execution",
+            console,
+            clock,
+            &shutdown,
+            systemd,
+        );
+        tokio::select! {
+            arrived = wait => if !arrived {
+                break;
+            },
+            // Only ever requested when there is a file to reload
+            _ = reload.requested() => {
+                if let Some(config_file) = config_file {
+                    reload_plan(&mut plan, args, config_file, logger, console);
+                }
+                continue;
+            }
+        }
+        cooldown.finish();
+        if max_runtime.has_elapsed(clock.now()) {
+            max_runtime.report(logger, console);
+            break;
+        }
+        if let Some(control_file) = &args.control_file
+            && !control_allows_fire(control_file, logger)
+        {
+            console.line(&console.paint(
+                &format!("Paused by {control_file}, skipping cycle {cycle_number}"),
+                Tone::Warning,
+            ));
+            if let Err(e) = logger.log_paused(control_file, cycle_number) {
+                eprintln!("Warning: Failed to log pause: {e}");
+            }
+            continue;
+        }
+        let Some(running) = RunLock::global().acquire(args.on_overlap).await else {
+            report_overlap_skip(logger, console, Some(cycle_number));
+            continue;
+        };
+
+        // Log cycle start
+        if let Err(e) = logger.log_cycle_start(cycle_number) {
+            eprintln!("Warning: Failed to log cycle start: {e}");
+        }
+        if let Some(cycle_state) = &cycle_state
+            && let Err(e) = cycle_state.save(cycle_number)
+        {
+            eprintln!("Warning: Failed to record cycle number: {e:#}");
+        }
+
+        console.line(&format!("\nExecuting cycle {cycle_number}..."));
+
+        // Execute the action
+        let outcome = run_fire(
+            context,
+            &plan.actions,
+            Some(cycle_number),
+            next_time,
+            &format!("Cycle {cycle_number} "),
+        )
+        .await;
+        drop(running);
+        spacing.record_run(clock.now());
+        if outcome
+            .errors
+            .iter()
+            .any(|e| failure_kind(e) == FailureKind::RateLimited)
+        {
+            let cooldown_end = cooldown.start(clock.now());
+            console.line(&console.paint(
+                &format!(
+                    "Rate limited, cooling down until {}",
+                    cooldown_end.format("%Y-%m-%d %H:%M:%S")
+                ),
+                Tone::Warning,
+            ));
+        }
+        let succeeded = outcome.is_success();
+        if succeeded {
+            summary.record_success(outcome.response_len);
+        } else {
+            summary.record_failure();
+            if summary.should_alert(args.alert_after) {
+                send_alert(context, cycle_number, summary.consecutive_failures).await;
+            }
+            if let [e] = outcome.errors.as_slice()
+                && outcome.steps == 1
+            {
+                eprintln!(
+                    "{}",
+                    console.paint(
+                        &format!(
+                            "Cycle {cycle_number} {} failed: {e}",
+                            plan.actions[0].noun()
+                        ),
+                        Tone::Error
+                    )
+                );
+            }
+        }
+
+        // Log cycle end
+        if let Err(e) = logger.log_cycle_end(cycle_number) {
+            eprintln!("Warning: Failed to log cycle end: {e}");
+        }
+
+        if !policy.proceeds_after(succeeded) {
+            console.line(&console.paint(
+                &format!(
+                    "Stopping loop mode after cycle {cycle_number} failed \
+                     (use --continue-on-error to keep going)"
+                ),
+                Tone::Error,
+            ));
+            aborted_cycle = Some(cycle_number);
+            break;
+        }
+
+        // A Ctrl+C during the run stops the loop once the cycle is fully logged
+        if shutdown.is_requested() || !should_continue_loop(summary.cycles(), args.count) {
+            break;
+        }
+
+        cycle_number += 1;
+        systemd.watchdog();
+        console.progress("Cycle completed. Waiting for next scheduled time...\n");
+    }
+
+    console.line(&format!("Loop finished after {}", summary.describe()));
+    if let Err(e) = logger.log_loop_summary(&summary) {
+        eprintln!("Warning: Failed to log loop summary: {e}");
+    }
+
+    if let Some(cycle_number) = aborted_cycle {
+        anyhow::bail!("Loop mode stopped after cycle {cycle_number} failed");
+    }
+    Ok(())
+}
+
+/// Reports a fire point dropped by `--on-overlap skip`.
+fn report_overlap_skip(logger: &Logger, console: Console, cycle_number: Option<u32>) {
+    console.line(&console.paint(
+        "Skipping this run, a previous execution is still running",
+        Tone::Warning,
+    ));
+    if let Err(e) = logger.log_overlap_skip(cycle_number) {
+        eprintln!("Warning: Failed to log skipped run: {e}");
+    }
+}
+
+/// Whether the `--control-file` lets loop mode fire. A file that can't be read or
+/// understood is logged as a warning and doesn't hold up the schedule.
+fn control_allows_fire(control_file: &str, logger: &Logger) -> bool {
+    should_fire(control_file).unwrap_or_else(|e| {
+        let warning = format!("{e:#}; firing anyway");
+        if let Err(log_err) = logger.log_warning(&warning) {
+            eprintln!("Warning: {warning} (and failed to log it: {log_err})");
+        }
+        true
+    })
+}
+
+/// What loop mode fires on and what it runs at each fire point: the settings a
+/// SIGHUP reload of `--config` can change. Everything else keeps its startup value.
+struct LoopPlan {
+    schedule: LoopSchedule,
+    days: Option<Vec<Weekday>>,
+    blackout: Option<Blackout>,
+    actions: Vec<Box<dyn Action>>,
+}
+
+impl LoopPlan {
+    fn new(args: &Config, actions: Vec<Box<dyn Action>>) -> Result<Self> {
+        let schedule = loop_schedule_from_args(args)?;
+        let blackout = args.blackout()?;
+        if let Some(blackout) = &blackout
+            && schedule.daily_points().iter().all(|&(hour, minute)| {
+                blackout.contains(NaiveTime::from_hms_opt(hour, minute, 0).expect("time is valid"))
+            })
+        {
+            anyhow::bail!(
+                "Every fire point of the schedule ({}) falls in the --blackout window {blackout}",
+                schedule.describe()
+            );
+        }
+        Ok(Self {
+            schedule,
+            days: args.allowed_days()?,
+            blackout,
+            actions,
+        })
+    }
+}
+
+/// Replaces `plan` with the one the `reloaded` settings describe, leaving it as it was
+/// if they are invalid. Returns the log message describing the new plan.
+fn apply_reload(plan: &mut LoopPlan, reloaded: &Config) -> Result<String> {
+    let new_plan = LoopPlan::new(reloaded, actions_from_args(reloaded))?;
+    let message = format!(
+        "Reloaded configuration: schedule {}, running {}",
+        new_plan.schedule.describe(),
+        reloaded.step_descriptions().join("; ")
+    );
+    *plan = new_plan;
+    Ok(message)
+}
+
+/// Re-reads `config_file` on SIGHUP and applies it to `plan`, logging the outcome. A
+/// file that fails to load keeps the current plan.
+fn reload_plan(
+    plan: &mut LoopPlan,
+    args: &Config,
+    config_file: &ConfigFile,
+    logger: &Logger,
+    console: Console,
+) {
+    let result = config_file.load().and_then(|mut reloaded| {
+        // Standard input was used up at startup, so its message stays
+        reloaded.message = if reloaded.message_stdin {
+            args.message.clone()
+        } else {
+            resolve_messages(&reloaded, std::io::empty())?
+        };
+        apply_reload(plan, &reloaded)
+    });
+    match result {
+        Ok(message) => {
+            console.line(&format!("\n{message}"));
+            if let Err(e) = logger.log_reload(&message) {
+                eprintln!("Warning: Failed to log reload: {e}");
+            }
+        }
+        Err(e) => {
+            let warning = format!(
+                "Failed to reload {}, keeping the current settings: {e:#}",
+                config_file.path()
+            );
+            eprintln!(
+                "\n{}",
+                console.paint(&format!("Warning: {warning}"), Tone::Warning)
+            );
+            if let Err(e) = logger.log_warning(&warning) {
+                eprintln!("Warning: Failed to log warning: {e}");
+            }
+        }
+    }
+}
+
+/// How far ahead startup looks for the first fire point before deciding the schedule
+/// will never fire.
+const FIRE_POINT_HORIZON_DAYS: i64 = 8;
+
+/// Checks that the loop schedule fires at least once within [`FIRE_POINT_HORIZON_DAYS`]
+/// and before `--until`, so a contradictory configuration fails at startup instead of
+/// waiting forever or stopping without a run.
+fn check_first_fire_point(
+    now: DateTime<Local>,
+    schedule: &LoopSchedule,
+    timezone: Option<Tz>,
+    days: Option<&[Weekday]>,
+    blackout: Option<&Blackout>,
+    until: Option<DateTime<Local>>,
+) -> Result<()> {
+    let first = next_loop_time(now, schedule, timezone, days, blackout);
+    let on_days = days
+        .map(|days| format!(" on {}", format_days(days)))
+        .unwrap_or_default();
+    if first - now > chrono::Duration::days(FIRE_POINT_HORIZON_DAYS) {
+        anyhow::bail!(
+            "The schedule ({}{on_days}) has no fire point in the next {FIRE_POINT_HORIZON_DAYS} days",
+            schedule.describe()
+        );
+    }
+    if let Some(until) = until
+        && is_past_until(first, Some(until))
+    {
+        anyhow::bail!(
+            "The schedule ({}{on_days}) never fires before --until {}: \
+             its first fire point is {}",
+            schedule.describe(),
+            until.format("%Y-%m-%d %H:%M:%S"),
+            first.format("%Y-%m-%d %H:%M:%S")
+        );
+    }
+    Ok(())
+}
+
+/// Whether `next_time` falls after the `--until` bound, meaning loop mode should stop.
+fn is_past_until(next_time: DateTime<Local>, until: Option<DateTime<Local>>) -> bool {
+    until.is_some_and(|until| next_time > until)
+}
+
+/// Resolves an `--until` value: either a full `YYYY-MM-DD HH:MM[:SS]` timestamp, or a time
+/// of day / relative offset resolved the same way as `--time`.
+fn resolve_until(until_str: &str, timezone: Option<Tz>) -> Result<DateTime<Local>> {
+    if is_absolute_time(until_str) {
+        parse_datetime(until_str, timezone)
+    } else {
+        resolve_target_time(until_str, timezone, chrono::Duration::zero())
+    }
+}
+
+/// Parses a full `YYYY-MM-DD HH:MM[:SS]` timestamp, interpreted in `timezone` when given.
+fn parse_datetime(datetime_str: &str, timezone: Option<Tz>) -> Result<DateTime<Local>> {
+    let datetime_str = datetime_str.trim();
+    let naive = chrono::NaiveDateTime::parse_from_str(datetime_str, "%Y-%m-%d %H:%M:%S")
+        .or_else(|_| chrono::NaiveDateTime::parse_from_str(datetime_str, "%Y-%m-%d %H:%M"))
+        .with_context(|| {
+            format!("Invalid timestamp '{datetime_str}'. Expected YYYY-MM-DD HH:MM[:SS]")
+        })?;
+
+    let resolved = match timezone {
+        Some(tz) => tz
+            .from_local_datetime(&naive)
+            .earliest()
+            .map(|t| t.with_timezone(&Local)),
+        None => Local.from_local_datetime(&naive).earliest(),
+    };
+    resolved.with_context(|| format!("Timestamp '{datetime_str}' does not exist in this timezone"))
+}
+
+/// Whether loop mode should schedule another cycle after `completed_cycles` have run.
+/// Without a `--count` limit the loop runs forever.
+fn should_continue_loop(completed_cycles: u32, count: Option<u32>) -> bool {
+    match count {
+        Some(limit) => completed_cycles < limit,
+        None => true,
+    }
+}
+
+/// Picks the messages to send: `--message-file` wins over `--message-stdin`, which wins over
+/// `--message`, then the default.
+fn resolve_messages(args: &Config, stdin: impl Read) -> Result<Vec<String>> {
+    match args.message_file {
+        Some(ref path) => Ok(vec![read_message_file(path)?]),
+        None if args.message_stdin => Ok(vec![read_message_stdin(stdin)?]),
+        None => Ok(args.messages().into_iter().map(str::to_string).collect()),
+    }
+}
+
+/// Describes which message sources are being ignored when more than one was given.
+fn message_source_warning(args: &Config) -> Option<String> {
+    let mut sources = Vec::new();
+    if args.message_file.is_some() {
+        sources.push("--message-file");
+    }
+    if args.message_stdin {
+        sources.push("--message-stdin");
+    }
+    if !args.message.is_empty() {
+        sources.push("--message");
+    }
+
+    (sources.len() > 1).then(|| {
+        format!(
+            "Multiple message sources given ({}); using {}",
+            sources.join(", "),
+            sources[0]
+        )
+    })
+}
+
+/// Reads the whole of standard input as the message.
+fn read_message_stdin(mut stdin: impl Read) -> Result<String> {
+    let mut message = String::new();
+    stdin
+        .read_to_string(&mut message)
+        .context("Failed to read message from stdin")?;
+    if message.trim().is_empty() {
+        anyhow::bail!("No message received on stdin");
+    }
+    Ok(message)
+}
+
+/// Reads a message file verbatim, including newlines.
+fn read_message_file(path: &str) -> Result<String> {
+    let message = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read message file {path}"))?;
+    if message.trim().is_empty() {
+        anyhow::bail!("Message file {path} is empty");
+    }
+    Ok(message)
+}
+
+/// Splits an optional trailing AM/PM suffix off a time string.
+/// Returns the remaining time portion and `Some(true)` for PM, `Some(false)` for AM.
+fn split_meridiem(time_str: &str) -> (&str, Option<bool>) {
+    let trimmed = time_str.trim();
+    if trimmed.len() > 2 && trimmed.is_char_boundary(trimmed.len() - 2) {
+        let (time, suffix) = trimmed.split_at(trimmed.len() - 2);
+        if suffix.eq_ignore_ascii_case("am") {
+            return (time.trim_end(), Some(false));
+        }
+        if suffix.eq_ignore_ascii_case("pm") {
+            return (time.trim_end(), Some(true));
+        }
+    }
+    (trimmed, None)
+}
+
+/// Parses a time of day (`HH:MM[:SS]`, optionally with AM/PM) as a time today.
+pub fn parse_time(time_str: &str) -> Result<DateTime<Local>> {
+    parse_time_in(time_str, Local::now())
+}
+
+/// Parses a time of day and places it on the same date as `now`, in `now`'s timezone.
+fn parse_time_in<Z: TimeZone>(time_str: &str, now: DateTime<Z>) -> Result<DateTime<Z>> {
+    let (hour, minute, second) = parse_clock_time(time_str)?;
+    let time =
+        NaiveTime::from_hms_opt(hour, minute, second).context("Failed to create target time")?;
+    Ok(at_local_time(&now.timezone(), now.date_naive(), time))
+}
+
+/// The instant the clocks in `tz` read `time` on `date`, even when a DST change
+/// means they never do or do twice. A time skipped by spring-forward is moved on
+/// by the length of the gap (02:30 in a 02:00-03:00 gap becomes 03:30), and a
+/// time repeated by fall-back resolves to its first occurrence.
+fn at_local_time<Z: TimeZone>(tz: &Z, date: NaiveDate, time: NaiveTime) -> DateTime<Z> {
+    let naive = date.and_time(time);
+    match tz.from_local_datetime(&naive) {
+        LocalResult::Single(t) | LocalResult::Ambiguous(t, _) => t,
+        LocalResult::None => {
+            // Read the wall time with the offset in effect before the gap
+            let before = tz.offset_from_utc_datetime(&(naive - chrono::Duration::days(1)));
+            let utc = naive - chrono::Duration::seconds(before.fix().local_minus_utc().into());
+            tz.from_utc_datetime(&utc)
+        }
+    }
+}
+
+/// `at_local_time` for an `(hour, minute)` schedule entry, which was validated when parsed.
+fn at_schedule_time<Z: TimeZone>(
+    tz: &Z,
+    date: NaiveDate,
+    (hour, minute): (u32, u32),
+) -> DateTime<Z> {
+    let time = NaiveTime::from_hms_opt(hour, minute, 0).expect("schedule times are validated");
+    at_local_time(tz, date, time)
+}
+
+/// Parses a time of day into 24-hour `(hour, minute, second)` components.
+fn parse_clock_time(time_str: &str) -> Result<(u32, u32, u32), SchedulerError> {
+    let invalid = |reason: String| SchedulerError::TimeParse {
+        input: time_str.to_string(),
+        reason,
+    };
+    let (time_part, meridiem) = split_meridiem(time_str);
+
+    let parts: Vec<&str> = time_part.split(':').collect();
+    if parts.len() != 2 && parts.len() != 3 {
+        return Err(invalid(
+            "Invalid time format. Expected HH:MM, HH:MM:SS, or either with AM/PM".to_string(),
+        ));
+    }
+
+    let component = |value: &str, name: &str| {
+        value
+            .parse::<u32>()
+            .map_err(|e| invalid(format!("Invalid {name} '{value}': {e}")))
+    };
+    let hour = component(parts[0], "hour")?;
+    let minute = component(parts[1], "minute")?;
+    let second = match parts.get(2) {
+        Some(second) => component(second, "second")?,
+        None => 0,
+    };
+
+    if minute >= 60 || second >= 60 {
+        return Err(invalid(
+            "Invalid time. Minute and second must be 0-59".to_string(),
+        ));
+    }
+
+    let hour = match meridiem {
+        Some(is_pm) => {
+            if !(1..=12).contains(&hour) {
+                return Err(invalid(
+                    "Invalid time. Hour must be 1-12 with AM/PM".to_string(),
+                ));
+            }
+            // 12 AM is midnight, 12 PM is noon
+            match (hour, is_pm) {
+                (12, false) => 0,
+                (12, true) => 12,
+                (h, false) => h,
+                (h, true) => h + 12,
+            }
+        }
+        None => {
+            if hour >= 24 {
+                return Err(invalid("Invalid time. Hour must be 0-23".to_string()));
+            }
+            hour
+        }
+    };
+
+    Ok((hour, minute, second))
+}
+
+/// Parses a relative offset such as `+90m`, `+2h`, or `+1h30m` into a time from now.
+fn parse_relative_time(offset_str: &str) -> Result<DateTime<Local>> {
+    let spec = offset_str
+        .trim()
+        .strip_prefix('+')
+        .context("Relative time must start with '+'")?;
+    Local::now()
+        .checked_add_signed(parse_duration(spec)?)
+        .with_context(|| format!("Invalid relative time '{offset_str}'. Offset too large"))
+}
+
+/// Parses a duration made of `<number><unit>` components (units: h, m, s), e.g. `1h30m`.
+fn parse_duration(spec: &str) -> Result<chrono::Duration> {
+    let mut total = chrono::Duration::zero();
+    let mut digits = String::new();
+
+    for c in spec.trim().chars() {
+        if c.is_ascii_digit() {
+            digits.push(c);
+            continue;
+        }
+
+        if digits.is_empty() {
+            anyhow::bail!("Invalid duration '{spec}'. Expected a number before unit '{c}'");
+        }
+        let too_large = || format!("Invalid duration '{spec}'. Offset too large");
+        let value: i64 = digits.parse().with_context(too_large)?;
+        digits.clear();
+
+        let component = match c.to_ascii_lowercase() {
+            'h' => chrono::Duration::try_hours(value),
+            'm' => chrono::Duration::try_minutes(value),
+            's' => chrono::Duration::try_seconds(value),
+            _ => anyhow::bail!("Invalid duration unit '{c}'. Expected h, m, or s"),
+        };
+        total = component
+            .and_then(|component| total.checked_add(&component))
+            .with_context(too_large)?;
+    }
+
+    if !digits.is_empty() {
+        anyhow::bail!("Invalid duration '{spec}'. Every number needs a unit (h, m, or s)");
+    }
+    if total <= chrono::Duration::zero() {
+        anyhow::bail!("Invalid duration '{spec}'. Duration must be greater than zero");
+    }
+
+    Ok(total)
+}
+
+/// Whether a `--time` value is a full `YYYY-MM-DD HH:MM` timestamp rather than a time of day.
+fn is_absolute_time(time_str: &str) -> bool {
+    time_str.contains('-')
+}
+
+/// Resolves a `--time` value to the next matching local instant.
+/// Clock times are interpreted in `timezone` when given, otherwise in local time.
+/// A time of day up to `grace` in the past is kept for today rather than rolled to tomorrow.
+fn resolve_target_time(
+    time_str: &str,
+    timezone: Option<Tz>,
+    grace: chrono::Duration,
+) -> Result<DateTime<Local>> {
+    // A full timestamp is an absolute instant, so it is never rolled to tomorrow
+    if is_absolute_time(time_str) {
+        let target_time = parse_datetime(time_str, timezone)?;
+        if target_time <= Local::now() {
+            anyhow::bail!(
+                "Scheduled time '{}' is in the past",
+                target_time.format("%Y-%m-%d %H:%M:%S")
+            );
+        }
+        return Ok(target_time);
+    }
+
+    // Relative offsets are always in the future, so no rollover is needed
+    if time_str.starts_with('+') {
+        return parse_relative_time(time_str);
+    }
+
+    let target_time = match timezone {
+        Some(tz) => {
+            let target = parse_time_in(time_str, Local::now().with_timezone(&tz))?;
+            roll_to_future(target, grace).with_timezone(&Local)
+        }
+        None => roll_to_future(parse_time(time_str)?, grace),
+    };
+    Ok(target_time)
+}
+
+/// Resolves every `--time` value (defaulting to 06:00) and returns them in chronological order.
+/// With `days`, each time of day is pushed forward to the next allowed weekday.
+fn resolve_target_times(
+    time_strs: &[String],
+    timezone: Option<Tz>,
+    days: Option<&[Weekday]>,
+    grace: chrono::Duration,
+) -> Result<Vec<DateTime<Local>>> {
+    // Default to 6:00 AM
+    let default = ["06:00".to_string()];
+    let time_strs = if time_strs.is_empty() {
+        &default[..]
+    } else {
+        time_strs
+    };
+
+    let mut target_times = Vec::with_capacity(time_strs.len());
+    for time_str in time_strs {
+        let mut target_time = resolve_target_time(time_str, timezone, grace)?;
+        // An explicit date is used as given
+        if let Some(days) = days
+            && !is_absolute_time(time_str)
+        {
+            target_time = match timezone {
+                Some(tz) => {
+                    roll_to_allowed_day(target_time.with_timezone(&tz), days).with_timezone(&Local)
+                }
+                None => roll_to_allowed_day(target_time, days),
+            };
+        }
+        target_times.push(target_time);
+    }
+    target_times.sort();
+    Ok(target_times)
+}
+
+/// Moves a time that has already passed today to the same time tomorrow.
+fn roll_to_future<Z: TimeZone>(target_time: DateTime<Z>, grace: chrono::Duration) -> DateTime<Z> {
+    if has_passed(&target_time, Local::now(), grace) {
+        same_time_next_day(&target_time)
+    } else {
+        target_time
+    }
+}
+
+/// The same wall-clock time on the following day in `time`'s zone, which is 23 or 25
+/// hours later when a DST change falls in between.
+fn same_time_next_day<Z: TimeZone>(time: &DateTime<Z>) -> DateTime<Z> {
+    let next_day = time.date_naive().succ_opt().expect("date is in range");
+    at_local_time(&time.timezone(), next_day, time.time())
+}
+
+/// Whether `target_time` is more than `grace` before `now`, comparing whole seconds so
+/// that a time in the current second still counts as upcoming.
+fn has_passed<Z: TimeZone>(
+    target_time: &DateTime<Z>,
+    now: DateTime<Local>,
+    grace: chrono::Duration,
+) -> bool {
+    target_time.timestamp() + grace.num_seconds() < now.timestamp()
+}
+
+/// Moves a time forward one day at a time until it falls on one of `days`.
+fn roll_to_allowed_day<Z: TimeZone>(mut time: DateTime<Z>, days: &[Weekday]) -> DateTime<Z> {
+    while !days.contains(&time.weekday()) {
+        time = same_time_next_day(&time);
+    }
+    time
+}
+
+/// Parses a comma-separated list of three-letter day names (e.g. `mon,wed,fri`),
+/// case-insensitively, into the set of weekdays runs are allowed on.
+fn parse_days(days_str: &str) -> Result<Vec<Weekday>> {
+    let mut days = Vec::new();
+
+    for entry in days_str.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        let day = match entry.to_ascii_lowercase().as_str() {
+            "mon" => Weekday::Mon,
+            "tue" => Weekday::Tue,
+            "wed" => Weekday::Wed,
+            "thu" => Weekday::Thu,
+            "fri" => Weekday::Fri,
+            "sat" => Weekday::Sat,
+            "sun" => Weekday::Sun,
+            _ => anyhow::bail!(
+                "Invalid day '{entry}'. Expected mon, tue, wed, thu, fri, sat, or sun"
+            ),
+        };
+        if !days.contains(&day) {
+            days.push(day);
+        }
+    }
+
+    if days.is_empty() {
+        anyhow::bail!("--days must list at least one day");
+    }
+    days.sort_by_key(Weekday::num_days_from_monday);
+    Ok(days)
+}
+
+fn format_days(days: &[Weekday]) -> String {
+    days.iter()
+        .map(Weekday::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn get_loop_schedule() -> Vec<(u32, u32)> {
+    // (hour, minute) pairs for the 5-hour cycle, in chronological order
+    vec![(3, 0), (7, 0), (12, 0), (17, 0), (22, 0)]
+}
+
+/// Parses a comma-separated list of `HH:MM` entries (e.g. `08:00,14:00,20:00`) into a
+/// chronologically sorted loop schedule.
+fn parse_schedule(schedule_str: &str) -> Result<Vec<(u32, u32)>> {
+    let mut schedule = Vec::new();
+
+    for entry in schedule_str.split(',') {
+        let entry = entry.trim();
+        let (hour, minute, second) =
+            parse_clock_time(entry).with_context(|| format!("Invalid schedule entry '{entry}'"))?;
+        if second != 0 {
+            anyhow::bail!("Invalid schedule entry '{entry}'. Expected HH:MM");
+        }
+        if schedule.contains(&(hour, minute)) {
+            anyhow::bail!("Duplicate schedule entry '{entry}'");
+        }
+        schedule.push((hour, minute));
+    }
+
+    schedule.sort();
+    Ok(schedule)
+}
+
+/// When loop mode fires: either fixed times of day or a repeating interval.
+#[derive(Debug, Clone, PartialEq)]
+enum LoopSchedule {
+    Times(Vec<(u32, u32)>),
+    Interval {
+        anchor: (u32, u32),
+        interval: chrono::Duration,
+    },
+}
+
+impl LoopSchedule {
+    fn next_after<Z: TimeZone>(&self, now: DateTime<Z>) -> DateTime<Z> {
+        match self {
+            LoopSchedule::Times(times) => get_next_loop_time(now, times),
+            LoopSchedule::Interval { anchor, interval } => {
+                get_next_interval_time(now, *anchor, *interval)
+            }
+        }
+    }
+
+    /// Like [`LoopSchedule::next_after`], but skips fire points that fall outside `days`.
+    fn next_allowed_after<Z: TimeZone>(
+        &self,
+        now: DateTime<Z>,
+        days: Option<&[Weekday]>,
+    ) -> DateTime<Z> {
+        let mut next = self.next_after(now);
+        if let Some(days) = days {
+            while !days.contains(&next.weekday()) {
+                // Search again from the last instant of the disallowed day
+                let next_day = next.date_naive().succ_opt().expect("date is in range");
+                let end_of_day = at_local_time(&next.timezone(), next_day, NaiveTime::MIN)
+                    - chrono::Duration::nanoseconds(1);
+                next = self.next_after(end_of_day);
+            }
+        }
+        next
+    }
+
+    /// The times of day the schedule fires at.
+    fn daily_points(&self) -> Vec<(u32, u32)> {
+        match self {
+            LoopSchedule::Times(times) => times.clone(),
+            LoopSchedule::Interval { anchor, interval } => {
+                generate_interval_schedule(*anchor, *interval)
+            }
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            LoopSchedule::Times(times) => format_schedule(times),
+            LoopSchedule::Interval { anchor, interval } => format!(
+                "every {} minutes ({})",
+                interval.num_minutes(),
+                format_schedule(&generate_interval_schedule(*anchor, *interval))
+            ),
+        }
+    }
+}
+
+fn loop_schedule_from_args(args: &Config) -> Result<LoopSchedule> {
+    if let Some(ref interval_str) = args.interval {
+        let interval = parse_interval(interval_str)?;
+        let anchor = match args.interval_start {
+            Some(ref start) => {
+                let (hour, minute, _) =
+                    parse_clock_time(start).context("Invalid interval start")?;
+                (hour, minute)
+            }
+            None => (0, 0),
+        };
+        return Ok(LoopSchedule::Interval { anchor, interval });
+    }
+
+    let times = match args.schedule {
+        Some(ref schedule_str) => parse_schedule(schedule_str)?,
+        None => get_loop_schedule(),
+    };
+    Ok(LoopSchedule::Times(times))
+}
+
+/// Parses a loop interval, which must be a whole number of minutes no longer than a day.
+fn parse_interval(interval_str: &str) -> Result<chrono::Duration> {
+    let interval = parse_duration(interval_str)?;
+    if interval.num_seconds() % 60 != 0 {
+        anyhow::bail!("Invalid interval '{interval_str}'. Must be a whole number of minutes");
+    }
+    if interval > chrono::Duration::days(1) {
+        anyhow::bail!("Invalid interval '{interval_str}'. Must be at most 24h");
+    }
+    Ok(interval)
+}
+
+/// Lists the fire points produced by stepping `interval` forward from `anchor` within one day.
+fn generate_interval_schedule(anchor: (u32, u32), interval: chrono::Duration) -> Vec<(u32, u32)> {
+    let anchor_minutes = i64::from(anchor.0 * 60 + anchor.1);
+    let step = interval.num_minutes();
+
+    let mut schedule: Vec<(u32, u32)> = (0..)
+        .map(|k| k * step)
+        .take_while(|offset| *offset < 24 * 60)
+        .map(|offset| {
+            let minutes = (anchor_minutes + offset) % (24 * 60);
+            ((minutes / 60) as u32, (minutes % 60) as u32)
+        })
+        .collect();
+    schedule.sort();
+    schedule
+}
+
+/// Returns the first multiple of `interval` after `now`, counted from the most recent
+/// `anchor` time of day. The steps restart from the anchor every day.
+fn get_next_interval_time<Z: TimeZone>(
+    now: DateTime<Z>,
+    anchor: (u32, u32),
+    interval: chrono::Duration,
+) -> DateTime<Z> {
+    let tz = now.timezone();
+    let mut day = now.date_naive();
+    let mut start = at_schedule_time(&tz, day, anchor);
+    if start > now {
+        day = day.pred_opt().expect("date is in range");
+        start = at_schedule_time(&tz, day, anchor);
+    }
+
+    let elapsed = now.clone() - start.clone();
+    let steps = elapsed.num_seconds() / interval.num_seconds() + 1;
+    let offset = interval * steps as i32;
+
+    // Steps that leave the 24-hour window wrap around to the next anchor
+    if offset >= chrono::Duration::days(1) {
+        at_schedule_time(&tz, day.succ_opt().expect("date is in range"), anchor)
+    } else {
+        start + offset
+    }
+}
+
+fn format_schedule(schedule: &[(u32, u32)]) -> String {
+    schedule
+        .iter()
+        .map(|(hour, minute)| format!("{hour:02}:{minute:02}"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Computes the next loop fire time, interpreting the schedule in `timezone` when given
+/// and skipping days outside `days` and fire points inside the `blackout` window.
+///
+/// The schedule must have a fire point outside the window.
+fn next_loop_time(
+    now: DateTime<Local>,
+    schedule: &LoopSchedule,
+    timezone: Option<Tz>,
+    days: Option<&[Weekday]>,
+    blackout: Option<&Blackout>,
+) -> DateTime<Local> {
+    let next_after = |now: DateTime<Local>| match timezone {
+        Some(tz) => schedule
+            .next_allowed_after(now.with_timezone(&tz), days)
+            .with_timezone(&Local),
+        None => schedule.next_allowed_after(now, days),
+    };
+    let mut next = next_after(now);
+    while let Some(blackout) = blackout
+        && blackout.covers(next, timezone)
+    {
+        next = next_after(next);
+    }
+    next
+}
+
+/// The next `count` fire times after `now`, as loop mode would compute them one after
+/// another, stopping early at `until`.
+fn simulate_fire_times(
+    now: DateTime<Local>,
+    schedule: &LoopSchedule,
+    timezone: Option<Tz>,
+    days: Option<&[Weekday]>,
+    blackout: Option<&Blackout>,
+    until: Option<DateTime<Local>>,
+    count: usize,
+) -> Vec<DateTime<Local>> {
+    let mut fire_times = Vec::with_capacity(count);
+    let mut after = now;
+    while fire_times.len() < count {
+        let next = next_loop_time(after, schedule, timezone, days, blackout);
+        if until.is_some_and(|until| next > until) {
+            break;
+        }
+        fire_times.push(next);
+        after = next;
+    }
+    fire_times
+}
+
+/// Today's most recent fire point at or before `now`, in `now`'s timezone.
+fn last_fire_point_today<Z: TimeZone>(
+    now: DateTime<Z>,
+    schedule: &LoopSchedule,
+    days: Option<&[Weekday]>,
+) -> Option<DateTime<Z>> {
+    let start_of_day = at_local_time(&now.timezone(), now.date_naive(), NaiveTime::MIN);
+
+    let mut last = None;
+    let mut point =
+        schedule.next_allowed_after(start_of_day - chrono::Duration::nanoseconds(1), days);
+    while point <= now {
+        let next = schedule.next_allowed_after(point.clone(), days);
+        last = Some(point);
+        point = next;
+    }
+    last
+}
+
+/// The fire point `--catch-up` should run on startup: today's most recent scheduled time,
+/// unless a run was logged at or after it.
+fn find_missed_fire_point(
+    now: DateTime<Local>,
+    schedule: &LoopSchedule,
+    timezone: Option<Tz>,
+    days: Option<&[Weekday]>,
+    last_run: Option<DateTime<Local>>,
+) -> Option<DateTime<Local>> {
+    let last_fire = match timezone {
+        Some(tz) => last_fire_point_today(now.with_timezone(&tz), schedule, days)
+            .map(|t| t.with_timezone(&Local)),
+        None => last_fire_point_today(now, schedule, days),
+    }?;
+    last_run
+        .is_none_or(|run| run < last_fire)
+        .then_some(last_fire)
+}
+
+/// Returns the first entry of the sorted `schedule` after `now`, rolling over to tomorrow.
+fn get_next_loop_time<Z: TimeZone>(now: DateTime<Z>, schedule: &[(u32, u32)]) -> DateTime<Z> {
+    let tz = now.timezone();
+    let today = now.date_naive();
+
+    // Find the next scheduled time
+    for &entry in schedule {
+        let target = at_schedule_time(&tz, today, entry);
+        if target > now {
+            return target;
+        }
+    }
+
+    // If no time today, get the first time tomorrow
+    let tomorrow = today.succ_opt().expect("date is in range");
+    at_schedule_time(&tz, tomorrow, schedule[0])
+}
+
+/// Shows the instant `time` on the clock of `timezone` and on its own clock, labelled
+/// local, e.g. `2024-03-12 09:00:00 America/New_York = 2024-03-12 14:00:00 local`.
+fn format_dual_clock<Z: TimeZone>(time: &DateTime<Z>, timezone: Tz) -> String
+where
+    Z::Offset: std::fmt::Display,
+{
+    format!(
+        "{} {timezone} = {} local",
+        time.with_timezone(&timezone).format("%Y-%m-%d %H:%M:%S"),
+        time.format("%Y-%m-%d %H:%M:%S")
+    )
+}
+
+fn print_timezone(console: Console, timezone: Option<Tz>) {
+    if let Some(tz) = timezone {
+        console.line(&format!("Timezone: {tz}"));
+    }
+}
+
+/// Settings for how the claude subprocess is invoked.
+#[derive(Clone)]
+pub struct ClaudeOptions {
+    pub program: String,
+    pub extra_args: Vec<String>,
+    pub timeout: Option<Duration>,
+    pub working_dir: Option<String>,
+    /// Variables set for the child on top of the inherited environment.
+    pub env: Vec<(String, String)>,
+    /// File the API key is read from at each run, for claude only.
+    pub api_key_file: Option<String>,
+}
+
+/// Arguments passed to claude: fixed flags, then any extra args, with the message always last.
+fn claude_args(message: &str, extra_args: &[String]) -> Vec<String> {
+    let mut args = vec!["--dangerously-skip-permissions".to_string()];
+    args.extend(extra_args.iter().cloned());
+    args.push(message.to_string());
+    args
+}
+
+/// Environment variables that commonly change how claude behaves.
+const RELEVANT_ENV_VARS: &[&str] = &["PATH", "HOME", "SHELL", "USER", "HTTP_PROXY", "HTTPS_PROXY"];
+
+/// Environment variable prefixes whose variables are all relevant to claude.
+const RELEVANT_ENV_PREFIXES: &[&str] = &["ANTHROPIC_", "CLAUDE_"];
+
+fn is_relevant_env_var(name: &str) -> bool {
+    RELEVANT_ENV_VARS.contains(&name)
+        || RELEVANT_ENV_PREFIXES
+            .iter()
+            .any(|prefix| name.starts_with(prefix))
+}
+
+/// Hides the values of variables that look like secrets (`*_KEY`, `*_TOKEN`).
+fn redact_env_value(name: &str, value: &str) -> String {
+    let name = name.to_ascii_uppercase();
+    if name.ends_with("_KEY") || name.ends_with("_TOKEN") {
+        "***".to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+/// Describes the exact argv, working directory and relevant environment a
+/// claude invocation will see, with secrets redacted. Variables set with
+/// `--env` are always shown and override inherited ones.
+fn describe_invocation(
+    argv: &[String],
+    cwd: &str,
+    inherited_env: impl IntoIterator<Item = (String, String)>,
+    extra_env: &[(String, String)],
+) -> String {
+    let mut env: std::collections::BTreeMap<String, String> = inherited_env
+        .into_iter()
+        .filter(|(name, _)| is_relevant_env_var(name))
+        .collect();
+    env.extend(extra_env.iter().cloned());
+
+    let env: Vec<String> = env
+        .iter()
+        .map(|(name, value)| format!("{name}={}", redact_env_value(name, value)))
+        .collect();
+
+    format!("argv: {argv:?}\ncwd: {cwd}\nenv: {}", env.join(" "))
+}
+
+/// Logs how the action is about to be invoked when `--verbose` is set.
+fn log_invocation(args: &Config, logger: &Logger, action: &dyn Action, cycle_number: Option<u32>) {
+    if !args.verbose {
+        return;
+    }
+    let Some(argv) = action.argv() else {
+        return;
+    };
+
+    let cwd = match &args.working_dir {
+        Some(dir) => dir.clone(),
+        None => std::env::current_dir()
+            .map(|dir| dir.display().to_string())
+            .unwrap_or_else(|e| format!("<unknown: {e}>")),
+    };
+
+    let mut env = args.env.clone();
+    if args.api_key_file.is_some() && args.exec.is_none() {
+        // The key itself is only read when claude starts, and redacted anyway
+        env.push((API_KEY_VAR.to_string(), String::new()));
+    }
+    let report = describe_invocation(&argv, &cwd, std::env::vars(), &env);
+    if let Err(e) = logger.log_debug(action.name(), &report, cycle_number) {
+        eprintln!("Warning: Failed to log command details: {e}");
+    }
+}
+
+fn build_claude_command(message: &str, extra_args: &[String]) -> String {
+    let mut command = "claude --dangerously-skip-permissions".to_string();
+    for arg in extra_args {
+        command.push(' ');
+        if arg.is_empty() || arg.contains(|c: char| c.is_whitespace() || c == '"') {
+            command.push_str(&quote_arg(arg));
+        } else {
+            command.push_str(arg);
+        }
+    }
+    format!("{command} {}", quote_arg(message))
+}
+
+fn quote_arg(arg: &str) -> String {
+    format!("\"{}\"", arg.replace("\"", "\\\""))
+}
+
+/// The shell `--exec` commands are run through, and the flag that passes it a command.
+#[cfg(not(windows))]
+const SHELL: [&str; 2] = ["sh", "-c"];
+#[cfg(windows)]
+const SHELL: [&str; 2] = ["cmd", "/C"];
+
+/// The `--exec` invocation as it would be typed, for banners and dry runs.
+fn build_exec_command(command: &str) -> String {
+    format!("{} {} {}", SHELL[0], SHELL[1], quote_arg(command))
+}
+
+/// Builds the shell invocation that runs an `--exec` command, without running it. The
+/// working directory and environment are applied as they are for claude.
+fn exec_command(shell_command: &str, options: &ClaudeOptions) -> Command {
+    let mut command = Command::new(SHELL[0]);
+    command.args([SHELL[1], shell_command]);
+    if let Some(dir) = &options.working_dir {
+        command.current_dir(dir);
+    }
+    command.envs(options.env.iter().cloned());
+    command
+}
+
+/// Runs an `--exec` command, treating its output like a claude response.
+async fn run_exec_command(shell_command: &str, options: &ClaudeOptions) -> Result<ClaudeOutput> {
+    run_command(exec_command(shell_command, options), options.timeout).await
+}
+
+/// The result of a successful claude run.
+#[derive(Debug)]
+pub struct ClaudeOutput {
+    pub response: String,
+    /// Diagnostics the process wrote to stderr, even though it succeeded.
+    pub stderr: String,
+    pub exit_code: Option<i32>,
+    /// Wall-clock time the claude process took to complete.
+    pub duration: Duration,
+    /// Problems that didn't stop the run, such as missing `--git-context`, logged
+    /// as warnings once it has finished.
+    pub warnings: Vec<String>,
+}
+
+/// Builds the claude invocation for `message`, without running it.
+fn claude_command(message: &str, options: &ClaudeOptions) -> Command {
+    let mut command = Command::new(&options.program);
+    command.args(claude_args(message, &options.extra_args));
+    if let Some(dir) = &options.working_dir {
+        command.current_dir(dir);
+    }
+    command.envs(options.env.iter().cloned());
+    command
+}
+
+/// Parses a `KEY=VALUE` pair for `--env`, splitting at the first `=` so the
+/// value may itself contain `=`.
+fn parse_env_var(pair: &str) -> Result<(String, String)> {
+    let (key, value) = pair
+        .split_once('=')
+        .with_context(|| format!("Invalid environment variable '{pair}'. Expected KEY=VALUE"))?;
+    if key.is_empty() {
+        anyhow::bail!("Invalid environment variable '{pair}'. The name must not be empty");
+    }
+    Ok((key.to_string(), value.to_string()))
+}
+
+/// Checks that `--working-dir` names an existing directory.
+fn validate_working_dir(dir: &str) -> Result<()> {
+    let metadata = std::fs::metadata(dir)
+        .with_context(|| format!("Working directory {dir} does not exist"))?;
+    if !metadata.is_dir() {
+        anyhow::bail!("Working directory {dir} is not a directory");
+    }
+    Ok(())
+}
+
+/// Phrases claude and the API use when a request is turned away for going
+/// over a rate or usage limit, lower-cased.
+const RATE_LIMIT_SIGNATURES: [&str; 6] = [
+    "rate limit",
+    "rate_limit",
+    "rate-limited",
+    "too many requests",
+    "usage limit reached",
+    "status 429",
+];
+
+/// Whether a failed command's stderr says it was rate limited.
+fn is_rate_limit_error(stderr: &str) -> bool {
+    let stderr = stderr.to_lowercase();
+    RATE_LIMIT_SIGNATURES
+        .iter()
+        .any(|signature| stderr.contains(signature))
+}
+
+/// Phrases in stderr that point to a network problem or a briefly unavailable
+/// service rather than anything wrong with the invocation, lower-cased.
+const TRANSIENT_SIGNATURES: [&str; 14] = [
+    "connection reset",
+    "connection refused",
+    "connection closed",
+    "econnreset",
+    "econnrefused",
+    "etimedout",
+    "eai_again",
+    "timed out",
+    "socket hang up",
+    "network error",
+    "temporarily unavailable",
+    "bad gateway",
+    "service unavailable",
+    "overloaded",
+];
+
+/// Why a command failed, as far as deciding whether to try it again goes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum FailureKind {
+    /// Over a rate or usage limit: retrying straight away only prolongs it.
+    RateLimited,
+    /// A network blip or overloaded service that may well pass.
+    Transient,
+    /// Anything else, e.g. a bad flag, which will fail the same way again.
+    Permanent,
+}
+
+/// Classifies a failed command from its stderr.
+fn classify_failure(stderr: &str) -> FailureKind {
+    if is_rate_limit_error(stderr) {
+        return FailureKind::RateLimited;
+    }
+    let stderr = stderr.to_lowercase();
+    if TRANSIENT_SIGNATURES
+        .iter()
+        .any(|signature| stderr.contains(signature))
+    {
+        FailureKind::Transient
+    } else {
+        FailureKind::Permanent
+    }
+}
+
+/// Classifies a failed run. Only a command that ran and exited unsuccessfully
+/// has stderr to go on; anything else counts as permanent.
+fn failure_kind(error: &anyhow::Error) -> FailureKind {
+    match error.downcast_ref::<SchedulerError>() {
+        Some(SchedulerError::CommandFailed { stderr, .. }) => classify_failure(stderr),
+        _ => FailureKind::Permanent,
+    }
+}
+
+/// Builds the log entry for a failed run, keeping the command's stderr and
+/// exit code when it got that far.
+fn run_error_entry(action: &str, error: &anyhow::Error, cycle_number: Option<u32>) -> LogEntry {
+    let entry = LogEntry::run_error(action, &error.to_string(), cycle_number);
+    match error.downcast_ref::<SchedulerError>() {
+        Some(SchedulerError::CommandFailed { code, stderr, .. }) => {
+            entry.with_stderr(stderr).with_exit_code(*code)
+        }
+        _ => entry,
+    }
+}
+
+pub async fn run_claude_command(message: &str, options: &ClaudeOptions) -> Result<ClaudeOutput> {
+    let mut command = claude_command(message, options);
+    let Some(path) = &options.api_key_file else {
+        return run_command(command, options.timeout).await;
+    };
+    let api_key = read_api_key(path)?;
+    command.env(API_KEY_VAR, &api_key);
+    match run_command(command, options.timeout).await {
+        Ok(mut output) => {
+            output.response = redact_api_key(&output.response, &api_key);
+            output.stderr = redact_api_key(&output.stderr, &api_key);
+            Ok(output)
+        }
+        Err(mut e) => {
+            if let Some(SchedulerError::CommandFailed { stderr, .. }) =
+                e.downcast_mut::<SchedulerError>()
+            {
+                *stderr = redact_api_key(stderr, &api_key);
+            }
+            Err(e)
+        }
+    }
+}
+
+/// The variable `--api-key-file` passes the key to claude in.
+const API_KEY_VAR: &str = "ANTHROPIC_API_KEY";
+
+/// Reads the `--api-key-file` key, ignoring surrounding whitespace. Errors never include
+/// the file's contents.
+fn read_api_key(path: &str) -> Result<String> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read API key file {path}"))?;
+    let api_key = contents.trim();
+    if api_key.is_empty() {
+        anyhow::bail!("API key file {path} is empty");
+    }
+    Ok(api_key.to_string())
+}
+
+/// Replaces the API key wherever it appears in claude's output, so it never reaches the
+/// console, the log or a notification.
+fn redact_api_key(text: &str, api_key: &str) -> String {
+    text.replace(api_key, "***")
+}
+
+/// Runs `command`, capturing stdout and stderr separately. A non-zero exit
+/// becomes a [`SchedulerError::CommandFailed`].
+async fn run_command(command: Command, timeout: Option<Duration>) -> Result<ClaudeOutput> {
+    let program = command.as_std().get_program().to_string_lossy().to_string();
+
+    let started = Instant::now();
+    let output = run_with_timeout(command, timeout).await?;
+    let duration = started.elapsed();
+
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+    if !output.status.success() {
+        return Err(SchedulerError::CommandFailed {
+            program,
+            code: output.status.code(),
+            stderr,
+        }
+        .into());
+    }
+
+    Ok(ClaudeOutput {
+        response: String::from_utf8_lossy(&output.stdout).to_string(),
+        stderr,
+        exit_code: output.status.code(),
+        duration,
+        warnings: Vec::new(),
+    })
+}
+
+/// Runs `command` to completion, killing it if it runs longer than `timeout`.
+async fn run_with_timeout(mut command: Command, timeout: Option<Duration>) -> Result<Output> {
+    let program = command.as_std().get_program().to_string_lossy().to_string();
+    command.kill_on_drop(true);
+
+    let output = match timeout {
+        Some(limit) => match tokio::time::timeout(limit, command.output()).await {
+            Ok(output) => output,
+            // Dropping the timed-out future kills the child process
+            Err(_) => {
+                return Err(SchedulerError::Timeout {
+                    program,
+                    seconds: limit.as_secs(),
+                }
+                .into());
+            }
+        },
+        None => command.output().await,
+    };
+
+    output.map_err(|source| SchedulerError::Spawn { program, source }.into())
+}
+
+/// Runs `<program> --version` to confirm the binary resolves and starts,
+/// returning the first line it prints.
+async fn check_claude_binary(program: &str) -> Result<String> {
+    let mut command = Command::new(program);
+    command.arg("--version");
+    let output = run_command(command, Some(BINARY_CHECK_TIMEOUT)).await?;
+    Ok(output
+        .response
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_string())
+}
+
+/// Checks the claude binary for a dry run, unless `--exec` means claude won't be run.
+async fn report_dry_run_binary(args: &Config, console: Console) -> Result<()> {
+    if args.exec.is_some() {
+        return Ok(());
+    }
+    report_binary_check(&args.claude_bin, console).await
+}
+
+/// Checks the claude binary before waiting, so a misconfiguration shows up now
+/// rather than hours later at the first run. A failure aborts startup with
+/// `--fail-fast` and is logged as a warning otherwise.
+async fn startup_health_check(args: &Config, logger: &Logger, console: Console) -> Result<()> {
+    if args.exec.is_some() {
+        return Ok(());
+    }
+    let program = &args.claude_bin;
+    let error = match check_claude_binary(program).await {
+        Ok(version) => {
+            console.progress(&format!("Claude binary: {program} ({version})"));
+            return Ok(());
+        }
+        Err(e) => e.context(format!(
+            "Health check failed: '{program} --version' did not run successfully \
+             (check --claude-bin and that claude is installed and on PATH)"
+        )),
+    };
+    if args.fail_fast {
+        return Err(error);
+    }
+
+    let warning = format!("{error:#}; continuing anyway (use --fail-fast to abort)");
+    if let Err(e) = logger.log_warning(&warning) {
+        eprintln!("Warning: {warning}");
+        eprintln!("Warning: Failed to log warning: {e}");
+    }
+    Ok(())
+}
+
+/// Reports the result of [`check_claude_binary`] for a dry run, failing the
+/// run if the binary is not usable so CI notices.
+async fn report_binary_check(program: &str, console: Console) -> Result<()> {
+    match check_claude_binary(program).await {
+        Ok(version) => {
+            console.line(&format!("Claude binary: {program} ({version})"));
+            Ok(())
+        }
+        Err(e) => {
+            console.line(&format!("Claude binary: {program} (not runnable)"));
+            Err(e.context(format!("Claude binary '{program}' failed validation")))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Timelike;
+
+    /// A JSON-mode console that captures its records instead of printing them
+    /// among the test output.
+    fn silent_console() -> Console {
+        Console::new(true).capturing().0
+    }
+
+    fn parse_args(argv: &[&str]) -> Config {
+        match Cli::parse_from(argv).into_command() {
+            Commands::Run(args) => *args,
+            command => panic!("expected the run command, got {command:?}"),
+        }
+    }
+
+    #[test]
+    fn test_run_is_the_default_command() {
+        let args = parse_args(&["ccschedule", "--time", "07:00"]);
+        assert_eq!(args.time, vec!["07:00"]);
+
+        let args = parse_args(&["ccschedule", "run", "--time", "07:00"]);
+        assert_eq!(args.time, vec!["07:00"]);
+    }
+
+    #[test]
+    fn test_default_config_ignores_environment() {
+        assert!(
+            Cli::command()
+                .get_arguments()
+                .any(|arg| arg.get_env().is_some())
+        );
+        assert!(
+            default_command()
+                .get_arguments()
+                .all(|arg| arg.get_env().is_none())
+        );
+        let config = Config::default();
+        assert!(config.time.is_empty());
+        assert!(config.message.is_empty());
+    }
+
+    #[test]
+    fn test_parse_status_command() {
+        let cli = Cli::parse_from(["ccschedule", "status", "--pid-file", "run.pid"]);
+        match cli.into_command() {
+            Commands::Status { pid_file } => assert_eq!(pid_file, "run.pid"),
+            command => panic!("expected the status command, got {command:?}"),
+        }
+    }
+
+    #[test]
+    fn test_build_claude_command() {
+        assert_eq!(
+            build_claude_command("Hello, world!", &[]),
+            "claude --dangerously-skip-permissions \"Hello, world!\""
+        );
+        assert_eq!(
+            build_claude_command("Hello \"world\"", &[]),
+            "claude --dangerously-skip-permissions \"Hello \\\"world\\\"\""
+        );
+    }
+
+    #[test]
+    fn test_parse_time() {
+        let time = parse_time("14:30").unwrap();
+        assert_eq!(time.hour(), 14);
+        assert_eq!(time.minute(), 30);
+    }
+
+    #[test]
+    fn test_parse_invalid_time() {
+        assert!(parse_time("25:00").is_err());
+        assert!(parse_time("12:60").is_err());
+        assert!(parse_time("12").is_err());
+        assert!(parse_time("12:30:60").is_err());
+        assert!(parse_time("12:30:45:10").is_err());
+    }
+
+    #[test]
+    fn test_parse_time_with_seconds() {
+        let time = parse_time("06:00:30").unwrap();
+        assert_eq!(time.hour(), 6);
+        assert_eq!(time.minute(), 0);
+        assert_eq!(time.second(), 30);
+
+        let time = parse_time("14:30").unwrap();
+        assert_eq!(time.second(), 0);
+
+        let time = parse_time("6:15:45 PM").unwrap();
+        assert_eq!(time.hour(), 18);
+        assert_eq!(time.second(), 45);
+    }
+
+    #[test]
+    fn test_parse_relative_time() {
+        let cases = [
+            ("+45m", chrono::Duration::minutes(45)),
+            ("+2h", chrono::Duration::hours(2)),
+            ("+1h15m", chrono::Duration::minutes(75)),
+        ];
+        for (input, expected) in cases {
+            let before = Local::now();
+            let time = parse_relative_time(input).unwrap();
+            let after = Local::now();
+            assert!(time >= before + expected && time <= after + expected);
+        }
+    }
+
+    #[test]
+    fn test_parse_invalid_relative_time() {
+        assert!(parse_relative_time("+90").is_err());
+        assert!(parse_relative_time("+").is_err());
+        assert!(parse_relative_time("+h").is_err());
+        assert!(parse_relative_time("+5x").is_err());
+        assert!(parse_relative_time("90m").is_err());
+    }
+
+    #[test]
+    fn test_parse_relative_time_too_large() {
+        for input in [
+            "+99999999999h",
+            "+9999999999999999h",
+            "+99999999999999999999s",
+            "+2562047788015h2562047788015h",
+        ] {
+            let err = parse_relative_time(input).unwrap_err();
+            assert!(
+                format!("{err:#}").contains("Offset too large"),
+                "{input}: {err:#}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_time_in_timezone() {
+        let tz: Tz = "America/New_York".parse().unwrap();
+        let now = tz.with_ymd_and_hms(2024, 7, 1, 0, 0, 0).unwrap();
+
+        let target = parse_time_in("09:00", now).unwrap();
+        assert_eq!(target.hour(), 9);
+        // 09:00 EDT is 13:00 UTC
+        assert_eq!(target.with_timezone(&chrono::Utc).hour(), 13);
+    }
+
+    #[test]
+    fn test_resolve_target_time_in_timezone() {
+        let tz: Tz = "Asia/Tokyo".parse().unwrap();
+        let target = resolve_target_time("09:00", Some(tz), chrono::Duration::zero()).unwrap();
+
+        // The resolved local instant is 09:00 in Tokyo, within the next day
+        let in_zone = target.with_timezone(&tz);
+        assert_eq!((in_zone.hour(), in_zone.minute()), (9, 0));
+        assert!(target > Local::now());
+        assert!(target <= Local::now() + chrono::Duration::days(1));
+    }
+
+    #[test]
+    fn test_get_next_loop_time_in_timezone() {
+        let tz: Tz = "Europe/London".parse().unwrap();
+        let now = tz.with_ymd_and_hms(2024, 1, 15, 8, 30, 0).unwrap();
+
+        let next = get_next_loop_time(now, &get_loop_schedule());
+        assert_eq!(next, tz.with_ymd_and_hms(2024, 1, 15, 12, 0, 0).unwrap());
+    }
+
+    #[test]
+    fn test_rollover_keeps_wall_clock_time_across_dst() {
+        let tz: Tz = "America/New_York".parse().unwrap();
+
+        // Clocks spring forward overnight, so tomorrow's 06:00 is only 23 hours away
+        let passed = tz.with_ymd_and_hms(2024, 3, 9, 6, 0, 0).unwrap();
+        let rolled = roll_to_future(passed, chrono::Duration::zero());
+        assert_eq!(
+            rolled.format("%Y-%m-%d %H:%M %Z").to_string(),
+            "2024-03-10 06:00 EDT"
+        );
+        assert_eq!(rolled - passed, chrono::Duration::hours(23));
+
+        // And fall back on Sunday 3 November, making that day 25 hours long
+        let saturday = tz.with_ymd_and_hms(2024, 11, 2, 6, 0, 0).unwrap();
+        let monday = roll_to_allowed_day(saturday, &[Weekday::Mon]);
+        assert_eq!(
+            monday.format("%Y-%m-%d %H:%M %Z").to_string(),
+            "2024-11-04 06:00 EST"
+        );
+        assert_eq!(monday - saturday, chrono::Duration::hours(49));
+    }
+
+    #[test]
+    fn test_loop_times_across_dst_changes() {
+        let tz: Tz = "America/New_York".parse().unwrap();
+        let utc = |h, m| chrono::Utc.with_ymd_and_hms(2024, 3, 10, h, m, 0).unwrap();
+
+        // Clocks jump from 02:00 EST to 03:00 EDT, so 02:30 never happens
+        let now = tz.with_ymd_and_hms(2024, 3, 10, 0, 0, 0).unwrap();
+        let next = get_next_loop_time(now, &[(2, 30), (9, 0)]);
+        assert_eq!(next, utc(7, 30));
+        assert_eq!(next.format("%H:%M %Z").to_string(), "03:30 EDT");
+        assert_eq!(parse_time_in("02:30", now).unwrap(), utc(7, 30));
+
+        // An interval anchored in the gap keeps stepping from the adjusted anchor
+        let hourly = parse_interval("1h").unwrap();
+        let now = tz.with_ymd_and_hms(2024, 3, 10, 4, 10, 0).unwrap();
+        assert_eq!(get_next_interval_time(now, (2, 30), hourly), utc(8, 30));
+
+        // Clocks fall back from 02:00 EDT to 01:00 EST, so 01:30 happens twice
+        let now = tz.with_ymd_and_hms(2024, 11, 3, 0, 0, 0).unwrap();
+        let next = get_next_loop_time(now, &[(1, 30)]);
+        assert_eq!(
+            next,
+            chrono::Utc.with_ymd_and_hms(2024, 11, 3, 5, 30, 0).unwrap()
+        );
+        assert_eq!(next.format("%H:%M %Z").to_string(), "01:30 EDT");
+    }
+
+    #[test]
+    fn test_roll_decision_near_now() {
+        let now = Local::now();
+        let secs = chrono::Duration::seconds;
+        let no_grace = chrono::Duration::zero();
+
+        assert!(!has_passed(&(now + secs(5)), now, no_grace));
+        assert!(has_passed(&(now - secs(5)), now, no_grace));
+        // parse_time zeroes the sub-second part, which alone doesn't make a time past
+        let this_second = now.with_nanosecond(0).unwrap();
+        assert!(!has_passed(&this_second, now, no_grace));
+
+        // Within the grace window a time just gone by still runs today
+        assert!(!has_passed(&(now - secs(5)), now, secs(10)));
+        assert!(has_passed(&(now - secs(15)), now, secs(10)));
+
+        let args = parse_args(&["ccschedule", "--grace-seconds", "30"]);
+        assert_eq!(args.grace(), secs(30));
+        assert_eq!(parse_args(&["ccschedule"]).grace(), no_grace);
+
+        assert!(Cli::try_parse_from(["ccschedule", "--grace-seconds", "86401"]).is_err());
+        let huge = Config {
+            grace_seconds: u64::MAX,
+            ..parse_args(&["ccschedule"])
+        };
+        assert_eq!(huge.grace(), chrono::Duration::MAX);
+    }
+
+    #[test]
+    fn test_resolve_target_times_sorted() {
+        let times = vec!["+3h".to_string(), "+1h".to_string(), "+2h".to_string()];
+        let resolved = resolve_target_times(&times, None, None, chrono::Duration::zero()).unwrap();
+
+        assert_eq!(resolved.len(), 3);
+        assert!(resolved.windows(2).all(|pair| pair[0] <= pair[1]));
+        let first_offset = resolved[0] - Local::now();
+        assert!(first_offset <= chrono::Duration::hours(1));
+        assert!(first_offset > chrono::Duration::minutes(59));
+    }
+
+    #[test]
+    fn test_resolve_target_times_rolls_and_defaults() {
+        let times = vec![
+            "09:00".to_string(),
+            "13:30".to_string(),
+            "18:00".to_string(),
+        ];
+        let resolved = resolve_target_times(&times, None, None, chrono::Duration::zero()).unwrap();
+        assert_eq!(resolved.len(), 3);
+        let now = Local::now();
+        let tomorrow = now + chrono::Duration::days(1);
+        assert!(resolved.iter().all(|t| *t > now && *t <= tomorrow));
+
+        let default = resolve_target_times(&[], None, None, chrono::Duration::zero()).unwrap();
+        assert_eq!(default.len(), 1);
+        assert_eq!((default[0].hour(), default[0].minute()), (6, 0));
+
+        assert!(
+            resolve_target_times(
+                &["09:00".to_string(), "25:00".to_string()],
+                None,
+                None,
+                chrono::Duration::zero()
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_parse_schedule() {
+        assert_eq!(
+            parse_schedule("20:00, 08:00,14:30").unwrap(),
+            vec![(8, 0), (14, 30), (20, 0)]
+        );
+        assert!(parse_schedule("08:00,08:00").is_err());
+        assert!(parse_schedule("08:00,24:00").is_err());
+        assert!(parse_schedule("08:00,").is_err());
+        assert!(parse_schedule("08:00:30").is_err());
+    }
+
+    #[test]
+    fn test_get_next_loop_time_custom_schedule() {
+        let schedule = parse_schedule("08:00,14:00,20:00").unwrap();
+
+        let now = Local.with_ymd_and_hms(2024, 3, 12, 9, 15, 0).unwrap();
+        assert_eq!(
+            get_next_loop_time(now, &schedule),
+            Local.with_ymd_and_hms(2024, 3, 12, 14, 0, 0).unwrap()
+        );
+
+        // After the last entry, the schedule rolls over to the first entry tomorrow
+        let now = Local.with_ymd_and_hms(2024, 3, 12, 21, 0, 0).unwrap();
+        assert_eq!(
+            get_next_loop_time(now, &schedule),
+            Local.with_ymd_and_hms(2024, 3, 13, 8, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_get_next_loop_time_default_schedule_early_morning() {
+        let now = Local.with_ymd_and_hms(2024, 3, 12, 1, 0, 0).unwrap();
+        assert_eq!(
+            get_next_loop_time(now, &get_loop_schedule()),
+            Local.with_ymd_and_hms(2024, 3, 12, 3, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_generate_interval_schedule() {
+        let three_hours = parse_interval("3h").unwrap();
+        assert_eq!(
+            generate_interval_schedule((0, 0), three_hours),
+            vec![
+                (0, 0),
+                (3, 0),
+                (6, 0),
+                (9, 0),
+                (12, 0),
+                (15, 0),
+                (18, 0),
+                (21, 0)
+            ]
+        );
+
+        let ninety_minutes = parse_interval("90m").unwrap();
+        let schedule = generate_interval_schedule((7, 0), ninety_minutes);
+        assert_eq!(schedule.len(), 16);
+        assert_eq!(schedule[0], (1, 0));
+        assert!(schedule.contains(&(7, 0)));
+        assert!(schedule.contains(&(8, 30)));
+        assert!(schedule.contains(&(23, 30)));
+    }
+
+    #[test]
+    fn test_get_next_interval_time() {
+        let ninety_minutes = parse_interval("90m").unwrap();
+        let now = Local.with_ymd_and_hms(2024, 3, 12, 10, 10, 0).unwrap();
+        assert_eq!(
+            get_next_interval_time(now, (0, 0), ninety_minutes),
+            Local.with_ymd_and_hms(2024, 3, 12, 10, 30, 0).unwrap()
+        );
+
+        // Before today's anchor, steps continue from yesterday's anchor
+        let three_hours = parse_interval("3h").unwrap();
+        let now = Local.with_ymd_and_hms(2024, 3, 12, 5, 0, 0).unwrap();
+        assert_eq!(
+            get_next_interval_time(now, (8, 0), three_hours),
+            Local.with_ymd_and_hms(2024, 3, 12, 8, 0, 0).unwrap()
+        );
+
+        // Intervals that don't divide 24h wrap to the next day's anchor
+        let five_hours = parse_interval("5h").unwrap();
+        let now = Local.with_ymd_and_hms(2024, 3, 12, 21, 0, 0).unwrap();
+        assert_eq!(
+            get_next_interval_time(now, (0, 0), five_hours),
+            Local.with_ymd_and_hms(2024, 3, 13, 0, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_interval() {
+        assert!(parse_interval("90").is_err());
+        assert!(parse_interval("90s").is_err());
+        assert!(parse_interval("25h").is_err());
+    }
+
+    #[test]
+    fn test_jitter_disabled() {
+        let target = Local.with_ymd_and_hms(2024, 3, 12, 6, 0, 0).unwrap();
+        let mut jitter = Jitter::new(0, Some(42));
+        assert_eq!(jitter.apply(target), (target, 0));
+    }
+
+    #[test]
+    fn test_jitter_offset_applied() {
+        let target = Local.with_ymd_and_hms(2024, 3, 12, 6, 0, 0).unwrap();
+        let mut jitter = Jitter::new(300, Some(42));
+
+        for _ in 0..20 {
+            let (jittered, offset) = jitter.apply(target);
+            assert!(offset <= 300);
+            assert_eq!(jittered - target, chrono::Duration::seconds(offset as i64));
+        }
+    }
+
+    #[test]
+    fn test_jitter_is_bounded() {
+        let args = parse_args(&["ccschedule", "--jitter", "259200"]);
+        assert_eq!(args.jitter, MAX_JITTER_SECONDS);
+        assert!(Cli::try_parse_from(["ccschedule", "--jitter", "259201"]).is_err());
+        assert!(Cli::try_parse_from(["ccschedule", "--jitter", "18446744073709551615"]).is_err());
+    }
+
+    #[test]
+    fn test_jitter_seed_is_deterministic() {
+        let target = Local.with_ymd_and_hms(2024, 3, 12, 6, 0, 0).unwrap();
+        let mut first = Jitter::new(300, Some(7));
+        let mut second = Jitter::new(300, Some(7));
+
+        let first_offsets: Vec<u64> = (0..5).map(|_| first.apply(target).1).collect();
+        let second_offsets: Vec<u64> = (0..5).map(|_| second.apply(target).1).collect();
+        assert_eq!(first_offsets, second_offsets);
+    }
+
+    #[test]
+    fn test_min_spacing_delays_close_fire_points() {
+        let first = Local.with_ymd_and_hms(2024, 3, 12, 6, 0, 0).unwrap();
+        let second = first + chrono::Duration::seconds(10);
+        let mut spacing = Spacing::new(Some(60));
+
+        // Nothing has run yet
+        assert_eq!(spacing.apply(first), first);
+
+        // The first run finished 5s after it started; the second is pushed to 60s after that
+        spacing.record_run(first + chrono::Duration::seconds(5));
+        assert_eq!(spacing.apply(second), first + chrono::Duration::seconds(65));
+
+        // A fire point far enough away is left alone
+        let later = first + chrono::Duration::minutes(5);
+        assert_eq!(spacing.apply(later), later);
+
+        // Without --min-spacing nothing is delayed
+        let mut unspaced = Spacing::new(None);
+        unspaced.record_run(first);
+        assert_eq!(unspaced.apply(second), second);
+
+        // Out-of-range spacing neither panics when built nor when applied
+        assert!(
+            Cli::try_parse_from(["ccschedule", "--min-spacing", "9223372036854775807"]).is_err()
+        );
+        let mut huge = Spacing::new(Some(u64::MAX));
+        huge.record_run(first);
+        assert_eq!(huge.apply(second), second);
+    }
+
+    #[test]
+    fn test_parse_time_error_messages() {
+        let malformed = parse_time("12").unwrap_err().to_string();
+        assert!(malformed.contains("Invalid time format"));
+
+        let out_of_range = parse_time("12:30:75").unwrap_err().to_string();
+        assert!(out_of_range.contains("must be 0-59"));
+    }
+
+    #[test]
+    fn test_parse_time_am_pm() {
+        let time = parse_time("6:00 AM").unwrap();
+        assert_eq!(time.hour(), 6);
+        assert_eq!(time.minute(), 0);
+
+        let time = parse_time("11:30pm").unwrap();
+        assert_eq!(time.hour(), 23);
+        assert_eq!(time.minute(), 30);
+
+        // 12 AM is midnight, 12 PM is noon
+        assert_eq!(parse_time("12:00 AM").unwrap().hour(), 0);
+        assert_eq!(parse_time("12:00 PM").unwrap().hour(), 12);
+    }
+
+    #[test]
+    fn test_parse_invalid_am_pm_time() {
+        assert!(parse_time("13:00 PM").is_err());
+        assert!(parse_time("0:30 AM").is_err());
+        assert!(parse_time("6:60 AM").is_err());
+    }
+
+    fn no_delay_policy(retries: u32) -> RetryPolicy {
+        RetryPolicy {
+            retries,
+            network_retries: 0,
+            base_delay: Duration::ZERO,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retry_succeeds_after_failures() {
+        let mut attempts = 0;
+        let mut retried = Vec::new();
+
+        let result = retry_with_backoff(
+            &no_delay_policy(3),
+            || {
+                attempts += 1;
+                let attempt = attempts;
+                async move {
+                    if attempt < 3 {
+                        anyhow::bail!("transient failure {attempt}");
+                    }
+                    Ok("done".to_string())
+                }
+            },
+            |attempt, _, _| retried.push(attempt),
+        )
+        .await;
+
+        assert_eq!(result.unwrap(), "done");
+        assert_eq!(attempts, 3);
+        assert_eq!(retried, vec![1, 2]);
+    }
+
+    #[tokio::test]
+    async fn test_retry_gives_up_after_limit() {
+        let mut attempts = 0;
+
+        let result: Result<String> = retry_with_backoff(
+            &no_delay_policy(2),
+            || {
+                attempts += 1;
+                async { anyhow::bail!("always fails") }
+            },
+            |_, _, _| {},
+        )
+        .await;
+
+        assert!(result.is_err());
+        assert_eq!(attempts, 3);
+    }
+
+    #[tokio::test]
+    async fn test_no_retries_by_default() {
+        let mut attempts = 0;
+
+        let result: Result<String> = retry_with_backoff(
+            &no_delay_policy(0),
+            || {
+                attempts += 1;
+                async { anyhow::bail!("fails") }
+            },
+            |_, _, _| panic!("should not retry"),
+        )
+        .await;
+
+        assert!(result.is_err());
+        assert_eq!(attempts, 1);
+    }
+
+    #[test]
+    fn test_retry_delay_backoff() {
+        let policy = RetryPolicy {
+            retries: 3,
+            network_retries: 0,
+            base_delay: Duration::from_secs(10),
+        };
+        assert_eq!(policy.delay_for(1), Duration::from_secs(10));
+        assert_eq!(policy.delay_for(2), Duration::from_secs(20));
+        assert_eq!(policy.delay_for(3), Duration::from_secs(40));
+    }
+
+    #[test]
+    fn test_retries_are_bounded() {
+        assert_eq!(
+            parse_args(&["ccschedule", "--retries", "100"]).retries,
+            MAX_RETRIES
+        );
+        assert!(Cli::try_parse_from(["ccschedule", "--retries", "101"]).is_err());
+        assert!(Cli::try_parse_from(["ccschedule", "--retries", "4294967295"]).is_err());
+        assert_eq!(
+            no_delay_policy(u32::MAX).attempts_for(FailureKind::Permanent),
+            u32::MAX
+        );
+    }
+
+    #[test]
+    fn test_network_retries_are_bounded() {
+        assert!(Cli::try_parse_from(["ccschedule", "--network-retries", "101"]).is_err());
+        let policy = RetryPolicy {
+            network_retries: u32::MAX,
+            ..no_delay_policy(u32::MAX)
+        };
+        assert_eq!(policy.attempts_for(FailureKind::Transient), u32::MAX);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_run_with_timeout_kills_slow_command() {
+        let mut command = Command::new("sleep");
+        command.arg("5");
+
+        let start = std::time::Instant::now();
+        let err = run_with_timeout(command, Some(Duration::from_millis(200)))
+            .await
+            .unwrap_err();
+
+        assert!(err.to_string().contains("timed out"));
+        assert!(matches!(
+            err.downcast_ref::<SchedulerError>(),
+            Some(SchedulerError::Timeout { program, .. }) if program == "sleep"
+        ));
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn test_failures_carry_scheduler_errors() {
+        let err = parse_time("25:00").unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<SchedulerError>(),
+            Some(SchedulerError::TimeParse { input, .. }) if input == "25:00"
+        ));
+        // Still found under added context
+        let err = parse_schedule("08:00,12:xx").unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<SchedulerError>(),
+            Some(SchedulerError::TimeParse { reason, .. }) if reason.starts_with("Invalid minute 'xx'")
+        ));
+
+        let mut options = parse_args(&["ccschedule"]).claude_options();
+        options.program = "ccschedule-missing-binary".to_string();
+        let err = run_claude_command("hi", &options).await.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<SchedulerError>(),
+            Some(SchedulerError::Spawn { program, .. }) if program == "ccschedule-missing-binary"
+        ));
+
+        // A log directory that is really a file can't hold log files
+        let temp_dir = tempfile::tempdir().unwrap();
+        let not_a_dir = temp_dir.path().join("logs");
+        std::fs::write(&not_a_dir, "").unwrap();
+        let logger = Logger::new(not_a_dir.to_str().unwrap()).with_echo(false);
+        let err = logger.log_warning("lost").unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<SchedulerError>(),
+            Some(SchedulerError::LogIo { .. })
+        ));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_run_with_timeout_completes_fast_command() {
+        let mut command = Command::new("echo");
+        command.arg("hello");
+
+        let output = run_with_timeout(command, Some(Duration::from_secs(5)))
+            .await
+            .unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "hello");
+    }
+
+    #[test]
+    fn test_claude_args_ordering() {
+        let extra = vec![
+            "--model".to_string(),
+            "opus".to_string(),
+            "--verbose".to_string(),
+        ];
+        assert_eq!(
+            claude_args("Do the thing", &extra),
+            vec![
+                "--dangerously-skip-permissions",
+                "--model",
+                "opus",
+                "--verbose",
+                "Do the thing"
+            ]
+        );
+        assert_eq!(
+            claude_args("Hi", &[]),
+            vec!["--dangerously-skip-permissions", "Hi"]
+        );
+    }
+
+    #[test]
+    fn test_build_claude_command_with_extra_args() {
+        let extra = vec![
+            "--model".to_string(),
+            "opus".to_string(),
+            "--append-system-prompt".to_string(),
+            "be brief".to_string(),
+        ];
+        assert_eq!(
+            build_claude_command("Hello", &extra),
+            "claude --dangerously-skip-permissions --model opus --append-system-prompt \"be brief\" \"Hello\""
+        );
+    }
+
+    #[test]
+    fn test_message_from_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("prompt.txt");
+        std::fs::write(&path, "Line one\nLine \"two\"\n").unwrap();
+        let path = path.to_string_lossy().to_string();
+
+        let args = parse_args(&[
+            "ccschedule",
+            "--message",
+            "ignored",
+            "--message-file",
+            &path,
+        ]);
+        let messages = resolve_messages(&args, std::io::empty()).unwrap();
+        assert_eq!(messages, vec!["Line one\nLine \"two\"\n"]);
+
+        let message = &messages[0];
+        assert_eq!(
+            build_claude_command(message, &[]),
+            "claude --dangerously-skip-permissions \"Line one\nLine \\\"two\\\"\n\""
+        );
+        assert_eq!(claude_args(message, &[]).last().unwrap(), message);
+    }
+
+    #[test]
+    fn test_message_defaults() {
+        let args = parse_args(&["ccschedule"]);
+        assert_eq!(
+            resolve_messages(&args, std::io::empty()).unwrap(),
+            vec![DEFAULT_MESSAGE]
+        );
+
+        let args = parse_args(&["ccschedule", "--message", "Hello"]);
+        assert_eq!(
+            resolve_messages(&args, std::io::empty()).unwrap(),
+            vec!["Hello"]
+        );
+
+        let args = parse_args(&["ccschedule", "-m", "Plan", "-m", "Implement"]);
+        assert_eq!(
+            resolve_messages(&args, std::io::empty()).unwrap(),
+            vec!["Plan", "Implement"]
+        );
+        assert_eq!(
+            args.action_banner(),
+            "Command 1: claude --dangerously-skip-permissions \"Plan\"\n\
+             Command 2: claude --dangerously-skip-permissions \"Implement\""
+        );
+    }
+
+    #[test]
+    fn test_message_file_missing_or_empty() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let empty = temp_dir.path().join("empty.txt");
+        std::fs::write(&empty, "  \n").unwrap();
+
+        assert!(read_message_file(&empty.to_string_lossy()).is_err());
+        assert!(read_message_file(&temp_dir.path().join("missing.txt").to_string_lossy()).is_err());
+    }
+
+    #[test]
+    fn test_message_from_stdin() {
+        let stdin = std::io::Cursor::new("Prompt from a pipeline\n");
+
+        let args = parse_args(&["ccschedule", "--message", "ignored", "--message-stdin"]);
+        let messages = resolve_messages(&args, stdin).unwrap();
+        assert_eq!(messages, vec!["Prompt from a pipeline\n"]);
+    }
+
+    #[test]
+    fn test_message_stdin_empty_is_error() {
+        let args = parse_args(&["ccschedule", "--message-stdin"]);
+        let err = resolve_messages(&args, std::io::Cursor::new("\n")).unwrap_err();
+        assert!(err.to_string().contains("No message received on stdin"));
+    }
+
+    #[test]
+    fn test_message_source_warning() {
+        let args = parse_args(&["ccschedule", "--message", "hi"]);
+        assert_eq!(message_source_warning(&args), None);
+
+        let args = parse_args(&["ccschedule", "--message", "hi", "--message-stdin"]);
+        assert_eq!(
+            message_source_warning(&args).unwrap(),
+            "Multiple message sources given (--message-stdin, --message); using --message-stdin"
+        );
+    }
+
+    #[test]
+    fn test_should_continue_loop() {
+        assert!(should_continue_loop(1, None));
+        assert!(should_continue_loop(1_000, None));
+
+        assert!(should_continue_loop(1, Some(3)));
+        assert!(should_continue_loop(2, Some(3)));
+        assert!(!should_continue_loop(3, Some(3)));
+        assert!(!should_continue_loop(1, Some(1)));
+    }
+
+    #[test]
+    fn test_is_past_until() {
+        let next = Local.with_ymd_and_hms(2024, 3, 12, 22, 0, 0).unwrap();
+
+        assert!(!is_past_until(next, None));
+        assert!(!is_past_until(next, Some(next)));
+        assert!(!is_past_until(
+            next,
+            Some(next + chrono::Duration::minutes(1))
+        ));
+        assert!(is_past_until(
+            next,
+            Some(next - chrono::Duration::minutes(1))
+        ));
+    }
+
+    #[test]
+    fn test_schedule_beyond_until_terminates() {
+        let schedule = LoopSchedule::Times(parse_schedule("08:00,14:00,20:00").unwrap());
+        let until = Local.with_ymd_and_hms(2024, 3, 12, 18, 0, 0).unwrap();
+
+        let now = Local.with_ymd_and_hms(2024, 3, 12, 9, 0, 0).unwrap();
+        let next = next_loop_time(now, &schedule, None, None, None);
+        assert!(!is_past_until(next, Some(until)));
+
+        // After the 14:00 run, the next fire point (20:00) is past the bound
+        let now = Local.with_ymd_and_hms(2024, 3, 12, 14, 0, 1).unwrap();
+        let next = next_loop_time(now, &schedule, None, None, None);
+        assert!(is_past_until(next, Some(until)));
+    }
+
+    #[test]
+    fn test_next_loop_time_skips_blackout() {
+        let schedule = LoopSchedule::Times(parse_schedule("01:00,03:00,12:00").unwrap());
+        let now = Local.with_ymd_and_hms(2024, 3, 12, 0, 0, 0).unwrap();
+
+        let blackout = Blackout::parse("02:00-04:00").unwrap();
+        assert_eq!(
+            next_loop_time(now, &schedule, None, None, Some(&blackout)),
+            Local.with_ymd_and_hms(2024, 3, 12, 1, 0, 0).unwrap()
+        );
+        let after_one = now + chrono::Duration::hours(1);
+        assert_eq!(
+            next_loop_time(after_one, &schedule, None, None, Some(&blackout)),
+            Local.with_ymd_and_hms(2024, 3, 12, 12, 0, 0).unwrap()
+        );
+
+        // Wrapping past midnight covers both early fire points
+        let blackout = Blackout::parse("23:30-03:30").unwrap();
+        let evening = Local.with_ymd_and_hms(2024, 3, 12, 13, 0, 0).unwrap();
+        assert_eq!(
+            next_loop_time(evening, &schedule, None, None, Some(&blackout)),
+            Local.with_ymd_and_hms(2024, 3, 13, 12, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_format_dual_clock() {
+        let london = chrono_tz::Europe::London
+            .with_ymd_and_hms(2024, 3, 12, 14, 0, 0)
+            .unwrap();
+        assert_eq!(
+            format_dual_clock(&london, chrono_tz::America::New_York),
+            "2024-03-12 10:00:00 America/New_York = 2024-03-12 14:00:00 local"
+        );
+
+        // The zones can be on different dates
+        let tokyo = chrono_tz::Asia::Tokyo
+            .with_ymd_and_hms(2024, 7, 1, 8, 30, 0)
+            .unwrap();
+        assert_eq!(
+            format_dual_clock(&tokyo, chrono_tz::America::Los_Angeles),
+            "2024-06-30 16:30:00 America/Los_Angeles = 2024-07-01 08:30:00 local"
+        );
+    }
+
+    #[test]
+    fn test_simulate_fire_times() {
+        let at = |day, hour| Local.with_ymd_and_hms(2024, 3, day, hour, 0, 0).unwrap();
+        let schedule = LoopSchedule::Times(parse_schedule("08:00,20:00").unwrap());
+        let days = [Weekday::Mon, Weekday::Wed];
+        let blackout = Blackout::parse("19:00-21:00").unwrap();
+        // Monday morning, after the 08:00 run
+        let now = at(11, 9);
+
+        let fire_times =
+            simulate_fire_times(now, &schedule, None, Some(&days), Some(&blackout), None, 3);
+        assert_eq!(fire_times, vec![at(13, 8), at(18, 8), at(20, 8)]);
+
+        let fire_times = simulate_fire_times(now, &schedule, None, None, None, None, 4);
+        assert_eq!(
+            fire_times,
+            vec![at(11, 20), at(12, 8), at(12, 20), at(13, 8)]
+        );
+
+        // The list ends at --until
+        let fire_times = simulate_fire_times(now, &schedule, None, None, None, Some(at(12, 8)), 5);
+        assert_eq!(fire_times, vec![at(11, 20), at(12, 8)]);
+
+        let interval = LoopSchedule::Interval {
+            anchor: (0, 0),
+            interval: chrono::Duration::hours(6),
+        };
+        let fire_times = simulate_fire_times(now, &interval, None, None, None, None, 3);
+        assert_eq!(fire_times, vec![at(11, 12), at(11, 18), at(12, 0)]);
+    }
+
+    #[test]
+    fn test_apply_reload() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("ccschedule.toml");
+        let path = path.to_str().unwrap();
+        let args = parse_args(&["ccschedule", "--loop-mode", "--config", path]);
+        let mut plan = LoopPlan::new(&args, actions_from_args(&args)).unwrap();
+        assert_eq!(
+            plan.schedule.describe(),
+            format_schedule(&get_loop_schedule())
+        );
+
+        std::fs::write(
+            path,
+            "schedule = \"09:00,21:00\"\ndays = \"sat,sun\"\nmessage = \"Tidy up\"\n",
+        )
+        .unwrap();
+        let matches =
+            Cli::command().get_matches_from(["ccschedule", "--loop-mode", "--config", path]);
+        let reloaded = ConfigFile::new(path, matches).load().unwrap();
+        let message = apply_reload(&mut plan, &reloaded).unwrap();
+        assert_eq!(plan.schedule.describe(), "09:00, 21:00");
+        assert_eq!(plan.days, Some(vec![Weekday::Sat, Weekday::Sun]));
+        assert_eq!(plan.actions.len(), 1);
+        assert_eq!(
+            plan.actions[0].argv().unwrap().last().map(String::as_str),
+            Some("Tidy up")
+        );
+        assert!(
+            message.starts_with("Reloaded configuration: schedule 09:00, 21:00, running claude")
+        );
+
+        // Invalid settings leave the plan as it was
+        let reloaded = parse_args(&[
+            "ccschedule",
+            "--loop-mode",
+            "--schedule",
+            "03:00",
+            "--blackout",
+            "02:00-04:00",
+        ]);
+        assert!(apply_reload(&mut plan, &reloaded).is_err());
+        assert_eq!(plan.schedule.describe(), "09:00, 21:00");
+    }
+
+    #[test]
+    fn test_check_first_fire_point() {
+        let schedule = LoopSchedule::Times(parse_schedule("08:00,20:00").unwrap());
+        // A Monday
+        let now = Local.with_ymd_and_hms(2024, 3, 11, 10, 0, 0).unwrap();
+        let until = Local.with_ymd_and_hms(2024, 3, 13, 18, 0, 0).unwrap();
+
+        assert!(check_first_fire_point(now, &schedule, None, None, None, Some(until)).is_ok());
+        let weekdays = [Weekday::Mon, Weekday::Wed];
+        assert!(
+            check_first_fire_point(now, &schedule, None, Some(&weekdays), None, Some(until))
+                .is_ok()
+        );
+
+        // Saturdays only, but the loop has to stop on Wednesday
+        let err = check_first_fire_point(
+            now,
+            &schedule,
+            None,
+            Some(&[Weekday::Sat]),
+            None,
+            Some(until),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "The schedule (08:00, 20:00 on Sat) never fires before --until 2024-03-13 18:00:00: \
+             its first fire point is 2024-03-16 08:00:00"
+        );
+
+        // Nothing after today's last run and before the bound
+        let now = Local.with_ymd_and_hms(2024, 3, 13, 8, 0, 0).unwrap();
+        assert!(check_first_fire_point(now, &schedule, None, None, None, Some(until)).is_err());
+        assert!(check_first_fire_point(now, &schedule, None, None, None, None).is_ok());
+    }
+
+    #[test]
+    fn test_resolve_until() {
+        let until = resolve_until("2024-03-12 22:00", None).unwrap();
+        assert_eq!(
+            until,
+            Local.with_ymd_and_hms(2024, 3, 12, 22, 0, 0).unwrap()
+        );
+
+        let tz: Tz = "UTC".parse().unwrap();
+        let until = resolve_until("2024-03-12 22:00:30", Some(tz)).unwrap();
+        assert_eq!(
+            until,
+            chrono::Utc
+                .with_ymd_and_hms(2024, 3, 12, 22, 0, 30)
+                .unwrap()
+        );
+
+        let until = resolve_until("22:00", None).unwrap();
+        assert!(until > Local::now());
+
+        assert!(resolve_until("2024-13-12 22:00", None).is_err());
+    }
+
+    #[test]
+    fn test_parse_stop_command() {
+        let cli = Cli::parse_from(["ccschedule", "stop", "--pid-file", "run.pid"]);
+        match cli.into_command() {
+            Commands::Stop { pid_file } => assert_eq!(pid_file, "run.pid"),
+            command => panic!("expected the stop command, got {command:?}"),
+        }
+    }
+
+    #[test]
+    fn test_dry_run_json_output() {
+        let args = parse_args(&["ccschedule", "--json", "--dry-run", "--message", "hi"]);
+        let fire_times = [
+            Local.with_ymd_and_hms(2030, 1, 1, 6, 0, 0).unwrap(),
+            Local.with_ymd_and_hms(2030, 1, 1, 18, 30, 0).unwrap(),
+        ];
+
+        let mut output = Vec::new();
+        for record in dry_run_records(&args, &fire_times) {
+            console::write_json_line(&mut output, &record).unwrap();
+        }
+
+        let output = String::from_utf8(output).unwrap();
+        let records: Vec<RunRecord> = output
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(records.len(), 2);
+        for (i, record) in records.iter().enumerate() {
+            assert_eq!(record.fire_time, fire_times[i]);
+            assert_eq!(record.entry.action, "claude");
+            assert_eq!(record.entry.status, "dry_run");
+            assert_eq!(record.entry.cycle_number, Some(i as u32 + 1));
+            assert_eq!(
+                record.entry.message.as_deref(),
+                Some("claude --dangerously-skip-permissions \"hi\"")
+            );
+        }
+    }
+
+    #[test]
+    fn test_redact_env_value() {
+        assert_eq!(redact_env_value("ANTHROPIC_API_KEY", "sk-secret"), "***");
+        assert_eq!(redact_env_value("GITHUB_TOKEN", "ghp_secret"), "***");
+        assert_eq!(redact_env_value("github_token", "ghp_secret"), "***");
+        assert_eq!(redact_env_value("PATH", "/usr/bin"), "/usr/bin");
+        assert_eq!(redact_env_value("KEYBOARD", "us"), "us");
+    }
+
+    #[test]
+    fn test_describe_invocation() {
+        let argv = vec![
+            "claude".to_string(),
+            "--dangerously-skip-permissions".to_string(),
+            "Hello world".to_string(),
+        ];
+        let env = [
+            ("PATH", "/usr/bin"),
+            ("ANTHROPIC_API_KEY", "sk-secret"),
+            ("CLAUDE_CODE_USE_BEDROCK", "1"),
+            ("UNRELATED", "ignored"),
+        ]
+        .map(|(name, value)| (name.to_string(), value.to_string()));
+
+        assert_eq!(
+            describe_invocation(&argv, "/work", env.clone(), &[]),
+            "argv: [\"claude\", \"--dangerously-skip-permissions\", \"Hello world\"]\n\
+             cwd: /work\n\
+             env: ANTHROPIC_API_KEY=*** CLAUDE_CODE_USE_BEDROCK=1 PATH=/usr/bin"
+        );
+
+        let extra = [
+            ("PATH".to_string(), "/opt/bin".to_string()),
+            ("PROJECT".to_string(), "demo".to_string()),
+        ];
+        assert!(describe_invocation(&argv, "/work", env, &extra).ends_with(
+            "env: ANTHROPIC_API_KEY=*** CLAUDE_CODE_USE_BEDROCK=1 PATH=/opt/bin PROJECT=demo"
+        ));
+    }
+
+    #[test]
+    fn test_claude_command_working_dir() {
+        let mut options =
+            parse_args(&["ccschedule", "--working-dir", "/tmp/project"]).claude_options();
+        let command = claude_command("Hello", &options);
+        assert_eq!(
+            command.as_std().get_current_dir(),
+            Some(std::path::Path::new("/tmp/project"))
+        );
+
+        options.working_dir = None;
+        assert_eq!(
+            claude_command("Hello", &options).as_std().get_current_dir(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_validate_working_dir() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        assert!(validate_working_dir(temp_dir.path().to_str().unwrap()).is_ok());
+
+        let missing = temp_dir.path().join("missing");
+        let err = validate_working_dir(missing.to_str().unwrap()).unwrap_err();
+        assert!(err.to_string().contains("does not exist"));
+
+        let file = temp_dir.path().join("file.txt");
+        std::fs::write(&file, "").unwrap();
+        let err = validate_working_dir(file.to_str().unwrap()).unwrap_err();
+        assert!(err.to_string().contains("is not a directory"));
+    }
+
+    #[test]
+    fn test_parse_env_var() {
+        assert_eq!(
+            parse_env_var("ANTHROPIC_API_KEY=sk-123").unwrap(),
+            ("ANTHROPIC_API_KEY".to_string(), "sk-123".to_string())
+        );
+        assert_eq!(
+            parse_env_var("QUERY=a=1&b=2").unwrap(),
+            ("QUERY".to_string(), "a=1&b=2".to_string())
+        );
+        assert_eq!(
+            parse_env_var("EMPTY=").unwrap(),
+            ("EMPTY".to_string(), String::new())
+        );
+        assert!(parse_env_var("NO_EQUALS").is_err());
+        assert!(parse_env_var("=value").is_err());
+    }
+
+    #[test]
+    fn test_env_flag_is_applied_to_command() {
+        let args = parse_args(&[
+            "ccschedule",
+            "--env",
+            "FOO=bar",
+            "--env",
+            "URL=http://x?a=b",
+        ]);
+        let command = claude_command("Hello", &args.claude_options());
+        let envs: Vec<_> = command.as_std().get_envs().collect();
+        assert_eq!(
+            envs,
+            vec![
+                (
+                    std::ffi::OsStr::new("FOO"),
+                    Some(std::ffi::OsStr::new("bar"))
+                ),
+                (
+                    std::ffi::OsStr::new("URL"),
+                    Some(std::ffi::OsStr::new("http://x?a=b"))
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_env_flag_is_rejected_at_parse_time() {
+        let result = Cli::try_parse_from(["ccschedule", "--env", "MISSING_EQUALS"]);
+        assert!(result.is_err());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_stderr_is_recorded_on_success() {
+        let mut command = Command::new("sh");
+        command.args(["-c", "echo response; echo 'warning: slow network' >&2"]);
+        let output = run_command(command, None).await.unwrap();
+        assert_eq!(output.response, "response\n");
+        assert_eq!(output.stderr, "warning: slow network\n");
+        assert_eq!(output.exit_code, Some(0));
+
+        let entry = LogEntry::claude_success(&output.response, Some(1), output.duration)
+            .with_stderr(&output.stderr);
+        let json = serde_json::to_value(&entry).unwrap();
+        assert_eq!(json["response_content"], "response\n");
+        assert_eq!(json["stderr_content"], "warning: slow network\n");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_stderr_is_recorded_on_failure() {
+        let mut command = Command::new("sh");
+        command.args(["-c", "echo 'fatal: bad flag' >&2; exit 2"]);
+        let error = run_command(command, None).await.unwrap_err();
+
+        let entry = run_error_entry("claude", &error, Some(3));
+        assert_eq!(entry.status, "error");
+        assert_eq!(entry.exit_code, Some(2));
+        assert_eq!(entry.stderr_content.as_deref(), Some("fatal: bad flag\n"));
+        assert!(entry.message.unwrap().contains("exit code: Some(2)"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_exit_code_is_captured_on_failure() {
+        let mut command = Command::new("sh");
+        command.args(["-c", "exit 137"]);
+        let error = run_command(command, None).await.unwrap_err();
+
+        let entry = run_error_entry("claude", &error, None);
+        assert_eq!(entry.exit_code, Some(137));
+        assert_eq!(serde_json::to_value(&entry).unwrap()["exit_code"], 137);
+    }
+
+    #[cfg(unix)]
+    fn write_stub(dir: &std::path::Path, script: &str) -> String {
+        use std::os::unix::fs::PermissionsExt;
+        let path = dir.join("claude-stub");
+        std::fs::write(&path, format!("#!/bin/sh\n{script}\n")).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        path.to_str().unwrap().to_string()
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_api_key_file_is_read_at_each_run_and_redacted() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let log_dir = temp_dir.path().join("logs");
+        let key_file = temp_dir.path().join("api-key");
+        std::fs::write(&key_file, "sk-first-key\n").unwrap();
+        let stub = write_stub(
+            temp_dir.path(),
+            "echo \"length ${#ANTHROPIC_API_KEY}\"; echo \"key $ANTHROPIC_API_KEY\"; echo \"$ANTHROPIC_API_KEY\" >&2",
+        );
+        let args = parse_args(&[
+            "ccschedule",
+            "--verbose",
+            "--claude-bin",
+            &stub,
+            "--api-key-file",
+            key_file.to_str().unwrap(),
+            "--log-dir",
+            log_dir.to_str().unwrap(),
+        ]);
+        let logger = Logger::new(log_dir.to_str().unwrap()).with_echo(false);
+        logger.init().unwrap();
+        let context = RunContext {
+            args: &args,
+            logger: &logger,
+            notifier: &Notifier::new(None),
+            metrics: &Metrics::default(),
+            health: &Health::new(1),
+            console: silent_console(),
+        };
+        let actions = actions_from_args(&args);
+
+        execute_action(context, actions[0].as_ref(), None, None, Local::now())
+            .await
+            .unwrap();
+        // A rotated key is used by the next run
+        std::fs::write(&key_file, "sk-rotated-second-key").unwrap();
+        execute_action(context, actions[0].as_ref(), None, None, Local::now())
+            .await
+            .unwrap();
+
+        let log = std::fs::read_dir(&log_dir)
+            .unwrap()
+            .map(|file| std::fs::read_to_string(file.unwrap().path()).unwrap())
+            .collect::<String>();
+        assert!(log.contains("length 12\\nkey ***\\n"));
+        assert!(log.contains("length 21\\nkey ***\\n"));
+        assert!(log.contains("ANTHROPIC_API_KEY=***"));
+        assert!(!log.contains("sk-first-key"));
+        assert!(!log.contains("sk-rotated-second-key"));
+
+        std::fs::write(&key_file, "  \n").unwrap();
+        let err = execute_action(context, actions[0].as_ref(), None, None, Local::now())
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("is empty"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_check_claude_binary() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let stub = write_stub(temp_dir.path(), "echo '1.0.42 (Claude Code)'");
+        assert_eq!(
+            check_claude_binary(&stub).await.unwrap(),
+            "1.0.42 (Claude Code)"
+        );
+
+        let failing = write_stub(temp_dir.path(), "exit 1");
+        assert!(check_claude_binary(&failing).await.is_err());
+
+        let missing = temp_dir.path().join("missing");
+        assert!(
+            check_claude_binary(missing.to_str().unwrap())
+                .await
+                .is_err()
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_startup_health_check() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let log_dir = temp_dir.path().join("logs");
+        let log_dir = log_dir.to_str().unwrap();
+        let stub = write_stub(temp_dir.path(), "exit 1");
+        let logger = Logger::new(log_dir).with_echo(false);
+        logger.init().unwrap();
+        let console = silent_console();
+        let log_contents = || {
+            std::fs::read_dir(log_dir)
+                .unwrap()
+                .map(|file| std::fs::read_to_string(file.unwrap().path()).unwrap())
+                .collect::<String>()
+        };
+
+        let args = parse_args(&["ccschedule", "--claude-bin", &stub, "--fail-fast"]);
+        let err = startup_health_check(&args, &logger, console)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Health check failed"));
+        assert_eq!(log_contents(), "");
+
+        // Without the flag the failure is only a warning
+        let args = parse_args(&["ccschedule", "--claude-bin", &stub]);
+        startup_health_check(&args, &logger, console).await.unwrap();
+        let entry: LogEntry = serde_json::from_str(log_contents().trim()).unwrap();
+        assert_eq!(entry.status, "warning");
+        assert!(entry.message.unwrap().contains("--fail-fast"));
+
+        // Nothing to check when claude won't be run
+        let args = parse_args(&[
+            "ccschedule",
+            "--claude-bin",
+            &stub,
+            "--fail-fast",
+            "--exec",
+            "true",
+        ]);
+        startup_health_check(&args, &logger, console).await.unwrap();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_dry_run_fails_when_binary_is_not_runnable() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let log_dir = temp_dir.path().join("logs");
+        let stub = write_stub(temp_dir.path(), "exit 127");
+        let args = parse_args(&[
+            "ccschedule",
+            "--dry-run",
+            "--json",
+            "--claude-bin",
+            &stub,
+            "--log-dir",
+            log_dir.to_str().unwrap(),
+        ]);
+        let err = run(args, &SystemClock, None).await.unwrap_err();
+        assert!(err.to_string().contains("failed validation"));
+    }
+
+    #[test]
+    fn test_parse_days() {
+        assert_eq!(
+            parse_days("mon,wed,fri").unwrap(),
+            vec![Weekday::Mon, Weekday::Wed, Weekday::Fri]
+        );
+        // Case-insensitive, unordered, and duplicates collapse
+        assert_eq!(
+            parse_days(" Fri, MON ,fri").unwrap(),
+            vec![Weekday::Mon, Weekday::Fri]
+        );
+
+        assert!(parse_days("mon,funday").is_err());
+        assert!(parse_days("monday").is_err());
+        assert!(parse_days("").is_err());
+        assert!(parse_days(" , ").is_err());
+    }
+
+    #[test]
+    fn test_next_loop_time_with_restricted_days() {
+        let schedule = LoopSchedule::Times(parse_schedule("08:00,20:00").unwrap());
+        let days = parse_days("mon,wed").unwrap();
+
+        // Tuesday morning skips to Wednesday's first slot
+        let now = Local.with_ymd_and_hms(2024, 3, 12, 9, 0, 0).unwrap();
+        assert_eq!(
+            next_loop_time(now, &schedule, None, Some(&days), None),
+            Local.with_ymd_and_hms(2024, 3, 13, 8, 0, 0).unwrap()
+        );
+
+        // Later the same Wednesday stays on Wednesday
+        let now = Local.with_ymd_and_hms(2024, 3, 13, 9, 0, 0).unwrap();
+        assert_eq!(
+            next_loop_time(now, &schedule, None, Some(&days), None),
+            Local.with_ymd_and_hms(2024, 3, 13, 20, 0, 0).unwrap()
+        );
+
+        // After Wednesday's last slot, the next allowed day is Monday
+        let now = Local.with_ymd_and_hms(2024, 3, 13, 21, 0, 0).unwrap();
+        assert_eq!(
+            next_loop_time(now, &schedule, None, Some(&days), None),
+            Local.with_ymd_and_hms(2024, 3, 18, 8, 0, 0).unwrap()
+        );
+
+        let interval = LoopSchedule::Interval {
+            anchor: (6, 0),
+            interval: chrono::Duration::hours(12),
+        };
+        let now = Local.with_ymd_and_hms(2024, 3, 12, 9, 0, 0).unwrap();
+        assert_eq!(
+            next_loop_time(now, &interval, None, Some(&days), None),
+            Local.with_ymd_and_hms(2024, 3, 13, 6, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_roll_to_allowed_day() {
+        let days = parse_days("sat,sun").unwrap();
+        // Tuesday 09:00 keeps its time of day and moves to Saturday
+        let time = Local.with_ymd_and_hms(2024, 3, 12, 9, 0, 0).unwrap();
+        assert_eq!(
+            roll_to_allowed_day(time, &days),
+            Local.with_ymd_and_hms(2024, 3, 16, 9, 0, 0).unwrap()
+        );
+
+        let sunday = Local.with_ymd_and_hms(2024, 3, 17, 9, 0, 0).unwrap();
+        assert_eq!(roll_to_allowed_day(sunday, &days), sunday);
+    }
+
+    #[test]
+    fn test_resolve_full_timestamp() {
+        let target = (Local::now() + chrono::Duration::days(3))
+            .with_second(0)
+            .and_then(|t| t.with_nanosecond(0))
+            .unwrap();
+        let time_str = target.format("%Y-%m-%d %H:%M").to_string();
+        assert_eq!(
+            resolve_target_time(&time_str, None, chrono::Duration::zero()).unwrap(),
+            target
+        );
+
+        // The date is kept even when --days would otherwise move it
+        let other_day = match target.weekday() {
+            Weekday::Mon => "tue",
+            _ => "mon",
+        };
+        let days = parse_days(other_day).unwrap();
+        let resolved =
+            resolve_target_times(&[time_str], None, Some(&days), chrono::Duration::zero()).unwrap();
+        assert_eq!(resolved, vec![target]);
+    }
+
+    #[test]
+    fn test_resolve_full_timestamp_in_past() {
+        let err =
+            resolve_target_time("2020-01-01 09:00", None, chrono::Duration::zero()).unwrap_err();
+        assert!(err.to_string().contains("in the past"));
+        assert!(resolve_target_time("2020-13-01 09:00", None, chrono::Duration::zero()).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_wait_until_stops_on_shutdown() {
+        let shutdown = Shutdown::new();
+        let console = silent_console();
+        let target = Local::now() + chrono::Duration::hours(1);
+
+        let waiter = {
+            let shutdown = shutdown.clone();
+            tokio::spawn(async move {
+                wait_until(
+                    target,
+                    "Time remaining",
+                    console,
+                    &SystemClock,
+                    &shutdown,
+                    Systemd::default(),
+                )
+                .await
+            })
+        };
+        shutdown.request();
+        let reached = tokio::time::timeout(Duration::from_secs(2), waiter)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(!reached);
+
+        let past = Local::now() - chrono::Duration::seconds(1);
+        assert!(
+            wait_until(
+                past,
+                "Time remaining",
+                console,
+                &SystemClock,
+                &shutdown,
+                Systemd::default()
+            )
+            .await
+        );
+    }
+
+    #[test]
+    fn test_sleep_chunk() {
+        let cap = Duration::from_secs(60);
+        assert_eq!(
+            sleep_chunk(chrono::Duration::seconds(5), cap),
+            Duration::from_secs(5)
+        );
+        assert_eq!(sleep_chunk(chrono::Duration::hours(3), cap), cap);
+        assert_eq!(sleep_chunk(chrono::Duration::zero(), cap), Duration::ZERO);
+        // The target passed while the clock was adjusted or the machine slept
+        assert_eq!(
+            sleep_chunk(chrono::Duration::seconds(-30), cap),
+            Duration::ZERO
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_start_now_runs_before_waiting() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let trace = temp_dir.path().join("trace");
+        let log_dir = temp_dir.path().join("logs");
+        let logger = Logger::new(log_dir.to_str().unwrap()).with_echo(false);
+        logger.init().unwrap();
+        let console = silent_console();
+        let exec = format!("echo run >> {}", trace.display());
+
+        let start = Local::now();
+        let clock = clock::MockClock::new(start);
+        let args = parse_args(&[
+            "ccschedule",
+            "--loop-mode",
+            "--interval",
+            "1h",
+            "--start-now",
+            "--count",
+            "1",
+            "--exec",
+            &exec,
+            "--log-dir",
+            log_dir.to_str().unwrap(),
+        ]);
+        run_loop_mode(
+            &args,
+            &logger,
+            console,
+            &clock,
+            None,
+            actions_from_args(&args),
+            None,
+        )
+        .await
+        .unwrap();
+        // The run happened without the clock moving at all
+        assert_eq!(std::fs::read_to_string(&trace).unwrap(), "run\n");
+        assert_eq!(clock.now(), start);
+
+        // The immediate run counts towards --count
+        let args = parse_args(&[
+            "ccschedule",
+            "--loop-mode",
+            "--interval",
+            "1h",
+            "--start-now",
+            "--count",
+            "2",
+            "--exec",
+            &exec,
+            "--log-dir",
+            log_dir.to_str().unwrap(),
+        ]);
+        run_loop_mode(
+            &args,
+            &logger,
+            console,
+            &clock,
+            None,
+            actions_from_args(&args),
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(std::fs::read_to_string(&trace).unwrap(), "run\nrun\nrun\n");
+        assert!(clock.now() > start);
+
+        assert!(
+            Cli::try_parse_from(["ccschedule", "--loop-mode", "--start-now", "--catch-up"])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_find_missed_fire_point() {
+        let schedule = LoopSchedule::Times(parse_schedule("08:00,14:00,20:00").unwrap());
+        let now = Local.with_ymd_and_hms(2024, 3, 12, 15, 0, 0).unwrap();
+        let last_fire = Local.with_ymd_and_hms(2024, 3, 12, 14, 0, 0).unwrap();
+
+        // Nothing logged today, so the 14:00 run was missed
+        assert_eq!(
+            find_missed_fire_point(now, &schedule, None, None, None),
+            Some(last_fire)
+        );
+        // Last run was for the 08:00 slot
+        let morning_run = Local.with_ymd_and_hms(2024, 3, 12, 8, 0, 30).unwrap();
+        assert_eq!(
+            find_missed_fire_point(now, &schedule, None, None, Some(morning_run)),
+            Some(last_fire)
+        );
+        // The 14:00 run was logged once it finished
+        let afternoon_run = Local.with_ymd_and_hms(2024, 3, 12, 14, 2, 0).unwrap();
+        assert_eq!(
+            find_missed_fire_point(now, &schedule, None, None, Some(afternoon_run)),
+            None
+        );
+
+        // Before the first slot of the day there is nothing to catch up on
+        let early = Local.with_ymd_and_hms(2024, 3, 12, 7, 0, 0).unwrap();
+        assert_eq!(
+            find_missed_fire_point(early, &schedule, None, None, None),
+            None
+        );
+
+        // A day excluded by --days is never caught up
+        let days = parse_days("mon").unwrap();
+        assert_eq!(
+            find_missed_fire_point(now, &schedule, None, Some(&days), None),
+            None
+        );
+    }
+
+    #[test]
+    fn test_catch_up_sees_exec_and_keepalive_runs() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let log_dir = temp_dir.path().to_string_lossy().to_string();
+        let log_file = format!("{log_dir}/2024-03-12.log");
+        let logger = Logger::new(&log_dir);
+        let schedule = LoopSchedule::Times(parse_schedule("08:00,14:00,20:00").unwrap());
+        let now = Local.with_ymd_and_hms(2024, 3, 12, 15, 0, 0).unwrap();
+        let afternoon_run = Local.with_ymd_and_hms(2024, 3, 12, 14, 2, 0).unwrap();
+
+        let mut entry = LogEntry::success("exec", None);
+        entry.timestamp = afternoon_run;
+        std::fs::write(&log_file, serde_json::to_string(&entry).unwrap() + "\n").unwrap();
+        let last_run = logger.last_run_on(now.date_naive()).unwrap();
+        assert_eq!(last_run, Some(afternoon_run));
+        assert_eq!(
+            find_missed_fire_point(now, &schedule, None, None, last_run),
+            None
+        );
+
+        std::fs::write(&log_file, "2024-03-12 14:02:00 [keepalive] success\n").unwrap();
+        let last_run = logger.last_run_on(now.date_naive()).unwrap();
+        assert_eq!(last_run, Some(afternoon_run));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_ping_prompt_overrides_default() {
+        // `echo` stands in for claude and prints back the prompt it was given
+        let args = parse_args(&["ccschedule", "--ping-mode", "--claude-bin", "echo"]);
+        let output = actions_from_args(&args)
+            .remove(0)
+            .execute(None)
+            .await
+            .unwrap();
+        assert!(
+            output
+                .response
+                .ends_with(&format!("{DEFAULT_PING_PROMPT}\n"))
+        );
+
+        let args = parse_args(&[
+            "ccschedule",
+            "--ping-mode",
+            "--ping-prompt",
+            "Summarize today's top news",
+            "--claude-bin",
+            "echo",
+        ]);
+        let output = actions_from_args(&args)
+            .remove(0)
+            .execute(None)
+            .await
+            .unwrap();
+        assert_eq!(
+            output.response,
+            "--dangerously-skip-permissions Summarize today's top news\n"
+        );
+        assert_eq!(args.ping_description(), "Send a custom ping prompt");
+    }
+
+    #[test]
+    fn test_ping_prompt_requires_ping_mode() {
+        assert!(Cli::try_parse_from(["ccschedule", "--ping-prompt", "hi"]).is_err());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_keepalive_sends_minimal_prompt() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let stub = write_stub(
+            temp_dir.path(),
+            "[ \"$2\" = \"Reply with just OK.\" ] || exit 1; sleep 0.05; echo OK",
+        );
+        let args = parse_args(&["ccschedule", "--keepalive", "--claude-bin", &stub]);
+
+        let action = actions_from_args(&args).remove(0);
+        let output = action.execute(None).await.unwrap();
+        assert!(output.duration >= Duration::from_millis(50));
+
+        let entry = action.success_entry(&output, None);
+        assert_eq!(entry.action, "keepalive");
+        assert_eq!(entry.duration_ms, Some(output.duration.as_millis() as u64));
+        assert_eq!(entry.response_content, None);
+    }
+
+    /// Fails its first `failures` runs, then answers with a fixed response.
+    struct MockAction {
+        failures: u32,
+        attempts: std::sync::atomic::AtomicU32,
+    }
+
+    impl Action for MockAction {
+        fn name(&self) -> &str {
+            "mock"
+        }
+
+        fn execute(&self, _cycle_number: Option<u32>) -> action::ActionFuture<'_> {
+            let attempt = self
+                .attempts
+                .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            let failed = attempt < self.failures;
+            Box::pin(async move {
+                if failed {
+                    anyhow::bail!("mock failure {}", attempt + 1);
+                }
+                Ok(ClaudeOutput {
+                    response: "mock response".to_string(),
+                    stderr: String::new(),
+                    exit_code: Some(0),
+                    duration: Duration::from_millis(3),
+                    warnings: Vec::new(),
+                })
+            })
+        }
+
+        fn success_entry(&self, output: &ClaudeOutput, cycle_number: Option<u32>) -> LogEntry {
+            LogEntry::success_with_response(
+                self.name(),
+                None,
+                Some(output.response.clone()),
+                cycle_number,
+            )
+        }
+    }
+
+    #[tokio::test]
+    async fn test_output_file_holds_latest_response() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let log_dir = temp_dir.path().join("logs");
+        let output_file = temp_dir.path().join("latest.txt");
+        let logger = Logger::new(log_dir.to_str().unwrap()).with_echo(false);
+        logger.init().unwrap();
+        let args = parse_args(&["ccschedule", "--output-file", output_file.to_str().unwrap()]);
+        let context = RunContext {
+            args: &args,
+            logger: &logger,
+            notifier: &Notifier::new(None),
+            metrics: &Metrics::default(),
+            health: &Health::new(1),
+            console: silent_console(),
+        };
+        std::fs::write(&output_file, "an older, longer response").unwrap();
+
+        let action = MockAction {
+            failures: 0,
+            attempts: Default::default(),
+        };
+        execute_action(context, &action, Some(1), None, Local::now())
+            .await
+            .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&output_file).unwrap(),
+            "mock response"
+        );
+
+        // A failed run leaves the last response in place
+        let action = MockAction {
+            failures: 1,
+            attempts: Default::default(),
+        };
+        execute_action(context, &action, Some(2), None, Local::now())
+            .await
+            .unwrap_err();
+        assert_eq!(
+            std::fs::read_to_string(&output_file).unwrap(),
+            "mock response"
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_missing_git_context_is_logged_once() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let log_dir = temp_dir.path().join("logs");
+        let work_dir = temp_dir.path().join("not-a-repo");
+        std::fs::create_dir(&work_dir).unwrap();
+        let stub = write_stub(temp_dir.path(), "echo ok");
+        let logger = Logger::new(log_dir.to_str().unwrap()).with_echo(false);
+        logger.init().unwrap();
+        let args = parse_args(&[
+            "ccschedule",
+            "--git-context",
+            "--claude-bin",
+            &stub,
+            "--working-dir",
+            work_dir.to_str().unwrap(),
+        ]);
+        let context = RunContext {
+            args: &args,
+            logger: &logger,
+            notifier: &Notifier::new(None),
+            metrics: &Metrics::default(),
+            health: &Health::new(1),
+            console: silent_console(),
+        };
+
+        let action = actions_from_args(&args).remove(0);
+        execute_action(context, action.as_ref(), None, None, Local::now())
+            .await
+            .unwrap();
+
+        let log_file = logger::daily_log_path(log_dir.to_str().unwrap(), Local::now().date_naive());
+        let log = std::fs::read_to_string(log_file).unwrap();
+        let warnings: Vec<LogEntry> = log
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .filter(|entry: &LogEntry| entry.status == "warning")
+            .collect();
+        assert_eq!(warnings.len(), 1);
+        let message = warnings[0].message.as_deref().unwrap();
+        assert!(message.contains("without git context"), "{message}");
+    }
+
+    #[test]
+    fn test_timing_drift_ms() {
+        let fire_time = Local.with_ymd_and_hms(2024, 3, 9, 6, 0, 0).unwrap();
+        let late = fire_time + chrono::Duration::milliseconds(1250);
+        assert_eq!(timing_drift_ms(fire_time, late), 1250);
+        assert_eq!(timing_drift_ms(fire_time, fire_time), 0);
+        let early = fire_time - chrono::Duration::milliseconds(40);
+        assert_eq!(timing_drift_ms(fire_time, early), -40);
+    }
+
+    #[tokio::test]
+    async fn test_execute_action_with_mock() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let log_dir = temp_dir.path().to_str().unwrap();
+        let logger = Logger::new(log_dir).with_echo(false);
+        logger.init().unwrap();
+        let notifier = Notifier::new(None);
+        let console = silent_console();
+        let args = parse_args(&["ccschedule", "--retries", "1", "--retry-delay", "0"]);
+        let fire_time = Local::now();
+
+        let action = MockAction {
+            failures: 1,
+            attempts: Default::default(),
+        };
+        let context = RunContext {
+            args: &args,
+            logger: &logger,
+            notifier: &notifier,
+            metrics: &Metrics::default(),
+            health: &Health::new(1),
+            console,
+        };
+        let success = execute_action(context, &action, Some(4), None, fire_time)
+            .await
+            .unwrap();
+        assert_eq!(success.line, "mock completed successfully!");
+        assert_eq!(success.response.as_deref(), Some("mock response"));
+
+        let action = MockAction {
+            failures: 2,
+            attempts: Default::default(),
+        };
+        let err = execute_action(context, &action, Some(5), None, fire_time)
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "mock failure 2");
+
+        let log_file = std::fs::read_dir(log_dir)
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap()
+            .path();
+        let statuses: Vec<(String, String, Option<u32>)> = std::fs::read_to_string(log_file)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str::<LogEntry>(line).unwrap())
+            .map(|entry| (entry.action, entry.status, entry.cycle_number))
+            .collect();
+        let expected = [
+            ("mock", "retry", 4),
+            ("mock", "success", 4),
+            ("mock", "retry", 5),
+            ("mock", "error", 5),
+        ];
+        assert_eq!(
+            statuses,
+            expected.map(|(action, status, cycle)| (
+                action.to_string(),
+                status.to_string(),
+                Some(cycle)
+            ))
+        );
+    }
+
+    #[test]
+    fn test_build_exec_command() {
+        assert_eq!(
+            build_exec_command("./backup.sh --all"),
+            format!("{} {} \"./backup.sh --all\"", SHELL[0], SHELL[1])
+        );
+        assert_eq!(
+            build_exec_command("echo \"quoted\" 'single'"),
+            format!("{} {} \"echo \\\"quoted\\\" 'single'\"", SHELL[0], SHELL[1])
+        );
+
+        let args = parse_args(&["ccschedule", "--exec", "tar czf backup.tgz \"my docs\""]);
+        assert_eq!(args.exec.as_deref(), Some("tar czf backup.tgz \"my docs\""));
+        assert_eq!(
+            args.action_banner(),
+            format!(
+                "Command: {} {} \"tar czf backup.tgz \\\"my docs\\\"\"",
+                SHELL[0], SHELL[1]
+            )
+        );
+
+        // The whole command reaches the shell as a single argument
+        let command = exec_command("echo \"a  b\" 'c'", &args.claude_options());
+        let argv: Vec<_> = command.as_std().get_args().collect();
+        assert_eq!(command.as_std().get_program(), SHELL[0]);
+        assert_eq!(argv, [SHELL[1], "echo \"a  b\" 'c'"]);
+
+        assert!(Cli::try_parse_from(["ccschedule", "--exec", "true", "--ping-mode"]).is_err());
+        assert!(Cli::try_parse_from(["ccschedule", "--exec", "true", "--keepalive"]).is_err());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_exec_action_runs_through_shell() {
+        let args = parse_args(&[
+            "ccschedule",
+            "--exec",
+            "printf '%s|' \"a  b\" c; echo oops >&2",
+        ]);
+        let action = actions_from_args(&args).remove(0);
+        assert_eq!(action.name(), "exec");
+
+        let output = action.execute(None).await.unwrap();
+        assert_eq!(output.response, "a  b|c|");
+        assert_eq!(output.stderr, "oops\n");
+
+        let entry = action.success_entry(&output, Some(1));
+        assert_eq!(entry.action, "exec");
+        assert_eq!(entry.status, "success");
+        assert_eq!(entry.stderr_content.as_deref(), Some("oops\n"));
+
+        // A failing command is an error, as a failing claude run is
+        let args = parse_args(&["ccschedule", "--exec", "exit 3"]);
+        let err = actions_from_args(&args)
+            .remove(0)
+            .execute(None)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<SchedulerError>(),
+            Some(SchedulerError::CommandFailed { code: Some(3), .. })
+        ));
+    }
+
+    #[test]
+    fn test_hook_command() {
+        let args = parse_args(&[
+            "ccschedule",
+            "--pre-hook",
+            "systemctl --user stop sync",
+            "--post-hook",
+            "systemctl --user start sync",
+        ]);
+        assert_eq!(Hook::Pre.command(&args), Some("systemctl --user stop sync"));
+        let post = Hook::Post { status: "error" };
+        assert_eq!(post.command(&args), Some("systemctl --user start sync"));
+
+        let options = args.claude_options();
+        let command = hook_command(Hook::Pre, "systemctl --user stop sync", &options);
+        let argv: Vec<_> = command.as_std().get_args().collect();
+        assert_eq!(command.as_std().get_program(), SHELL[0]);
+        assert_eq!(argv, [SHELL[1], "systemctl --user stop sync"]);
+        assert_eq!(command.as_std().get_envs().count(), 0);
+
+        let command = hook_command(post, "true", &options);
+        let envs: Vec<_> = command.as_std().get_envs().collect();
+        assert_eq!(
+            envs,
+            [(
+                std::ffi::OsStr::new("CCS_STATUS"),
+                Some(std::ffi::OsStr::new("error"))
+            )]
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_hooks_surround_execution() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let trace = temp_dir.path().join("trace");
+        let trace = trace.to_str().unwrap();
+        let log_dir = temp_dir.path().join("logs");
+        let log_dir = log_dir.to_str().unwrap();
+        let logger = Logger::new(log_dir).with_echo(false);
+        logger.init().unwrap();
+        let notifier = Notifier::new(None);
+        let context = RunContext {
+            args: &parse_args(&[
+                "ccschedule",
+                "--exec",
+                &format!("echo run >> {trace}"),
+                "--pre-hook",
+                &format!("echo pre >> {trace}"),
+                "--post-hook",
+                &format!("echo \"post $CCS_STATUS\" >> {trace}; exit 1"),
+            ]),
+            logger: &logger,
+            notifier: &notifier,
+            metrics: &Metrics::default(),
+            health: &Health::new(1),
+            console: silent_console(),
+        };
+        let actions = actions_from_args(context.args);
+
+        // The failing post-hook doesn't fail the run
+        execute_action(context, actions[0].as_ref(), Some(1), None, Local::now())
+            .await
+            .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(trace).unwrap(),
+            "pre\nrun\npost success\n"
+        );
+
+        let log = std::fs::read_dir(log_dir)
+            .unwrap()
+            .map(|file| std::fs::read_to_string(file.unwrap().path()).unwrap())
+            .collect::<String>();
+        let hook_entry = log
+            .lines()
+            .map(|line| serde_json::from_str::<LogEntry>(line).unwrap())
+            .find(|entry| entry.action == "post-hook")
+            .unwrap();
+        assert_eq!(hook_entry.status, "error");
+        assert_eq!(hook_entry.cycle_number, Some(1));
+    }
+
+    #[tokio::test]
+    async fn test_run_steps_stops_at_first_failure() {
+        let responses = [Ok(10), Err("step 2 failed"), Ok(30)];
+        let mut attempted = Vec::new();
+        let outcome = run_steps(responses.len(), FailurePolicy::Stop, |index| {
+            attempted.push(index);
+            let response = responses[index].map_err(|e| anyhow::anyhow!(e));
+            async move { response }
+        })
+        .await;
+
+        assert_eq!(attempted, vec![0, 1]);
+        assert!(!outcome.is_success());
+        assert_eq!(outcome.succeeded, 1);
+        assert_eq!(outcome.errors.len(), 1);
+        assert_eq!(outcome.describe(), "partial (1 of 3 steps succeeded)");
+    }
+
+    #[tokio::test]
+    async fn test_run_steps_continue_on_error() {
+        let responses = [Err("step 1 failed"), Ok(20), Ok(30)];
+        let outcome = run_steps(responses.len(), FailurePolicy::Continue, |index| {
+            let response = responses[index].map_err(|e| anyhow::anyhow!(e));
+            async move { response }
+        })
+        .await;
+
+        assert_eq!(outcome.succeeded, 2);
+        assert_eq!(outcome.response_len, 50);
+        assert_eq!(outcome.errors[0].to_string(), "step 1 failed");
+        assert_eq!(outcome.describe(), "partial (2 of 3 steps succeeded)");
+
+        let outcome = run_steps(2, FailurePolicy::Stop, |_| async { Ok(5) }).await;
+        assert!(outcome.is_success());
+        assert_eq!(outcome.describe(), "success (2 of 2 steps succeeded)");
+
+        let outcome = run_steps(1, FailurePolicy::Continue, |_| async {
+            anyhow::bail!("boom")
+        })
+        .await;
+        assert_eq!(outcome.describe(), "failed (0 of 1 steps succeeded)");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_chained_messages_log_each_step() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        // Echoes the message back, failing on the one that says so
+        let stub = write_stub(
+            temp_dir.path(),
+            r#"for arg; do last="$arg"; done; [ "$last" = fail ] && exit 1; echo "$last""#,
+        );
+        let log_dir = temp_dir.path().join("logs");
+        let log_dir = log_dir.to_str().unwrap();
+        let logger = Logger::new(log_dir).with_echo(false);
+        logger.init().unwrap();
+        let console = silent_console();
+        let notifier = Notifier::new(None);
+
+        let args = parse_args(&[
+            "ccschedule",
+            "--claude-bin",
+            &stub,
+            "-m",
+            "one",
+            "-m",
+            "fail",
+            "-m",
+            "three",
+        ]);
+        let actions = actions_from_args(&args);
+        let mut context = RunContext {
+            args: &args,
+            logger: &logger,
+            notifier: &notifier,
+            metrics: &Metrics::default(),
+            health: &Health::new(1),
+            console,
+        };
+        let outcome = run_fire(context, &actions, None, Local::now(), "").await;
+        assert_eq!(outcome.describe(), "partial (1 of 3 steps succeeded)");
+
+        let continuing = parse_args(&[
+            "ccschedule",
+            "--claude-bin",
+            &stub,
+            "-m",
+            "one",
+            "-m",
+            "fail",
+            "-m",
+            "three",
+            "--continue-on-error",
+        ]);
+        context.args = &continuing;
+        let outcome = run_fire(context, &actions, None, Local::now(), "").await;
+        assert_eq!(outcome.describe(), "partial (2 of 3 steps succeeded)");
+
+        let log_file = std::fs::read_dir(log_dir)
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap()
+            .path();
+        let steps: Vec<(Option<u32>, String)> = std::fs::read_to_string(log_file)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str::<LogEntry>(line).unwrap())
+            .map(|entry| (entry.step, entry.status))
+            .collect();
+        let expected = [
+            (1, "success"),
+            (2, "error"),
+            (1, "success"),
+            (2, "error"),
+            (3, "success"),
+        ];
+        assert_eq!(
+            steps,
+            expected.map(|(step, status)| (Some(step), status.to_string()))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_every_action_runs_and_logs_at_a_fire_point() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let log_dir = temp_dir.path().join("logs");
+        let logger = Logger::new(log_dir.to_str().unwrap()).with_echo(false);
+        logger.init().unwrap();
+        let notifier = Notifier::new(None);
+        let args = parse_args(&["ccschedule"]);
+        let context = RunContext {
+            args: &args,
+            logger: &logger,
+            notifier: &notifier,
+            metrics: &Metrics::default(),
+            health: &Health::new(1),
+            console: silent_console(),
+        };
+        let mock = || MockAction {
+            failures: 0,
+            attempts: Default::default(),
+        };
+        let actions: Vec<Box<dyn Action>> = vec![Box::new(mock()), Box::new(mock())];
+
+        let outcome = run_fire(context, &actions, Some(4), Local::now(), "").await;
+        assert!(outcome.is_success());
+        assert_eq!(outcome.steps, 2);
+
+        let log_file = std::fs::read_dir(&log_dir)
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap()
+            .path();
+        let entries: Vec<LogEntry> = std::fs::read_to_string(log_file)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .filter(|entry: &LogEntry| entry.status == "success")
+            .collect();
+        assert_eq!(entries.len(), 2);
+        for (entry, step) in entries.iter().zip(1..) {
+            assert_eq!(entry.action, "mock");
+            assert_eq!(entry.step, Some(step));
+            assert_eq!(entry.cycle_number, Some(4));
+        }
+    }
+
+    #[test]
+    fn test_also_adds_actions_after_the_main_one() {
+        let args = parse_args(&[
+            "ccschedule",
+            "-m",
+            "Plan",
+            "--also",
+            "ping",
+            "--also",
+            "keepalive",
+        ]);
+        let names: Vec<String> = actions_from_args(&args)
+            .iter()
+            .map(|action| action.name().to_string())
+            .collect();
+        assert_eq!(names, ["claude", "ping", "keepalive"]);
+        assert_eq!(
+            args.action_banner(),
+            "Command: claude --dangerously-skip-permissions \"Plan\"\n\
+             Also: Query global weather information\n\
+             Also: Send a minimal keepalive prompt"
+        );
+    }
+
+    #[test]
+    fn test_failure_policy() {
+        // How many runs of a schedule happen before the policy stops it
+        fn runs_attempted(outcomes: &[bool], policy: FailurePolicy) -> usize {
+            let mut attempted = 0;
+            for &succeeded in outcomes {
+                attempted += 1;
+                if !policy.proceeds_after(succeeded) {
+                    break;
+                }
+            }
+            attempted
+        }
+
+        let outcomes = [true, false, true, false, true];
+        assert_eq!(runs_attempted(&outcomes, FailurePolicy::Stop), 2);
+        assert_eq!(runs_attempted(&outcomes, FailurePolicy::Continue), 5);
+        assert_eq!(runs_attempted(&[true, true], FailurePolicy::Stop), 2);
+
+        assert_eq!(
+            FailurePolicy::from_args(&parse_args(&["ccschedule", "--loop-mode"])),
+            FailurePolicy::Stop
+        );
+        assert_eq!(
+            FailurePolicy::from_args(&parse_args(&["ccschedule", "--continue-on-error"])),
+            FailurePolicy::Continue
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_single_mode_failure_policy() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let stub = write_stub(temp_dir.path(), "exit 1");
+        let log_dir = temp_dir.path().join("logs");
+        let log_dir = log_dir.to_str().unwrap();
+        let logger = Logger::new(log_dir).with_echo(false);
+        logger.init().unwrap();
+        let console = silent_console();
+        let now = Local::now();
+        let target_times = [now, now];
+
+        let count_errors = || {
+            std::fs::read_dir(log_dir)
+                .unwrap()
+                .map(|file| std::fs::read_to_string(file.unwrap().path()).unwrap())
+                .map(|contents| contents.matches(r#""status":"error""#).count())
+                .sum::<usize>()
+        };
+
+        // Without the flag, the first failure ends the run
+        let args = parse_args(&["ccschedule", "--claude-bin", &stub]);
+        let actions = actions_from_args(&args);
+        assert!(
+            run_single_mode(
+                &args,
+                &logger,
+                console,
+                &SystemClock,
+                None,
+                &target_times,
+                actions
+            )
+            .await
+            .is_err()
+        );
+        assert_eq!(count_errors(), 1);
+
+        // With it, every scheduled time still runs, and the run still reports failure
+        let args = parse_args(&["ccschedule", "--claude-bin", &stub, "--continue-on-error"]);
+        let actions = actions_from_args(&args);
+        let err = run_single_mode(
+            &args,
+            &logger,
+            console,
+            &SystemClock,
+            None,
+            &target_times,
+            actions,
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(err.to_string(), "2 of 2 scheduled runs failed");
+        assert_eq!(count_errors(), 3);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_no_banner_leaves_out_branding() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let log_dir = temp_dir.path().join("logs");
+        let logger = Logger::new(log_dir.to_str().unwrap()).with_echo(false);
+        logger.init().unwrap();
+        let target_times = [Local::now()];
+
+        let run = |no_banner: bool| {
+            let logger = &logger;
+            let target_times = &target_times;
+            async move {
+                let args = parse_args(&["ccschedule", "--exec", "echo hi"]);
+                let (console, capture) = Console::new(false).with_banner(!no_banner).capturing();
+                let actions = actions_from_args(&args);
+                run_single_mode(
+                    &args,
+                    logger,
+                    console,
+                    &SystemClock,
+                    None,
+                    target_times,
+                    actions,
+                )
+                .await
+                .unwrap();
+                capture.stderr()
+            }
+        };
+
+        let output = run(false).await;
+        assert!(output.starts_with(&format!("{}\n", Banner::Single.text())));
+        assert!(output.ends_with(&format!("{}\n", Banner::Footer.text())));
+
+        let output = run(true).await;
+        assert!(!output.contains("Claude Code Schedule by"));
+        // Only the branding goes
+        assert!(output.contains("Scheduled to run at: "));
+        assert!(output.contains("Command completed successfully!"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_stdout_holds_only_the_response() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let log_dir = temp_dir.path().join("logs");
+        let logger = Logger::new(log_dir.to_str().unwrap()).with_echo(false);
+        logger.init().unwrap();
+        let args = parse_args(&["ccschedule", "--exec", "echo hi"]);
+        let (console, capture) = Console::new(false).capturing();
+        let actions = actions_from_args(&args);
+
+        run_single_mode(
+            &args,
+            &logger,
+            console,
+            &SystemClock,
+            None,
+            &[Local::now()],
+            actions,
+        )
+        .await
+        .unwrap();
+
+        let stdout = capture.stdout();
+        let (next_run, response) = stdout.split_once('\n').unwrap();
+        assert!(next_run.starts_with("NEXT_RUN="));
+        assert_eq!(response, "hi\n");
+        let stderr = capture.stderr();
+        assert!(stderr.starts_with(Banner::Single.text()));
+        assert!(stderr.contains("Response length: 3 characters\n"));
+        assert!(stderr.contains("Command completed successfully!\n"));
+    }
+
+    #[test]
+    fn test_cooldown_survives_reload() {
+        let now = Local.with_ymd_and_hms(2024, 3, 12, 6, 0, 0).unwrap();
+        let next = now + chrono::Duration::minutes(10);
+        let mut cooldown = Cooldown::new(1800).unwrap();
+        assert_eq!(cooldown.apply(next), next);
+
+        let end = cooldown.start(now);
+        assert_eq!(end, now + chrono::Duration::minutes(30));
+        assert_eq!(cooldown.apply(next), end);
+        // A reload interrupts the wait and the fire time is computed again
+        assert_eq!(cooldown.apply(next), end);
+
+        // Once the wait completes the cooldown is over
+        cooldown.finish();
+        assert_eq!(cooldown.apply(next), next);
+    }
+
+    #[test]
+    fn test_rate_limit_cooldown_is_bounded() {
+        let args = parse_args(&["ccschedule", "--rate-limit-cooldown", "604800"]);
+        assert_eq!(args.rate_limit_cooldown, MAX_COOLDOWN_SECONDS);
+        for seconds in ["604801", "9223372036854775807", "18446744073709551615"] {
+            assert!(Cli::try_parse_from(["ccschedule", "--rate-limit-cooldown", seconds]).is_err());
+        }
+    }
+
+    #[test]
+    fn test_is_rate_limit_error() {
+        assert!(is_rate_limit_error(
+            "API Error: 429 {\"type\":\"error\",\"error\":{\"type\":\"rate_limit_error\"}}"
+        ));
+        assert!(is_rate_limit_error(
+            "Claude AI usage limit reached|1760000000"
+        ));
+        assert!(is_rate_limit_error(
+            "Error: Rate limit exceeded, try again later"
+        ));
+        assert!(is_rate_limit_error(
+            "request failed with status 429 Too Many Requests"
+        ));
+
+        assert!(!is_rate_limit_error(""));
+        assert!(!is_rate_limit_error("fatal: bad flag\n"));
+        assert!(!is_rate_limit_error(
+            "Error: Invalid API key · Please run /login"
+        ));
+        assert!(!is_rate_limit_error("processed 4290 files"));
+    }
+
+    #[tokio::test]
+    async fn test_rate_limited_runs_are_not_retried() {
+        let policy = RetryPolicy {
+            retries: 3,
+            network_retries: 0,
+            base_delay: Duration::ZERO,
+        };
+        let mut attempts = 0;
+        let result: Result<String> = retry_with_backoff(
+            &policy,
+            || {
+                attempts += 1;
+                async {
+                    Err(SchedulerError::CommandFailed {
+                        program: "claude".to_string(),
+                        code: Some(1),
+                        stderr: "Claude AI usage limit reached".to_string(),
+                    }
+                    .into())
+                }
+            },
+            |_, _, _| panic!("should not retry"),
+        )
+        .await;
+        assert!(result.is_err());
+        assert_eq!(attempts, 1);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_message_template_filled_in_at_execution() {
+        let args = parse_args(&[
+            "ccschedule",
+            "--message",
+            "Cycle {{cycle}}, {{unknown}}",
+            "--claude-bin",
+            "echo",
+        ]);
+        let output = actions_from_args(&args)
+            .remove(0)
+            .execute(Some(3))
+            .await
+            .unwrap();
+        assert!(output.response.ends_with("Cycle 3, {{unknown}}\n"));
+
+        let args = parse_args(&[
+            "ccschedule",
+            "--message",
+            "Cycle {{cycle}}, {{unknown}}",
+            "--strict-template",
+            "--claude-bin",
+            "echo",
+        ]);
+        let action = actions_from_args(&args).remove(0);
+        let err = action.execute(Some(3)).await.unwrap_err();
+        assert!(err.to_string().contains("'{{unknown}}'"));
+    }
+
+    #[test]
+    fn test_max_runtime_elapsed() {
+        let started = Local.with_ymd_and_hms(2024, 3, 12, 22, 0, 0).unwrap();
+        let fire_time = started + chrono::Duration::hours(10);
+
+        let unlimited = MaxRuntime::from_args(&parse_args(&["ccschedule"]), started).unwrap();
+        assert!(!unlimited.has_elapsed(started + chrono::Duration::days(365)));
+        assert_eq!(unlimited.cap(fire_time), fire_time);
+
+        let args = parse_args(&["ccschedule", "--max-runtime", "8h"]);
+        let max_runtime = MaxRuntime::from_args(&args, started).unwrap();
+        let deadline = started + chrono::Duration::hours(8);
+        assert!(!max_runtime.has_elapsed(started));
+        assert!(!max_runtime.has_elapsed(deadline - chrono::Duration::seconds(1)));
+        assert!(max_runtime.has_elapsed(deadline));
+        assert!(max_runtime.has_elapsed(deadline + chrono::Duration::hours(1)));
+
+        // Waits stop at the deadline rather than the later fire time
+        assert_eq!(max_runtime.cap(fire_time), deadline);
+        let soon = started + chrono::Duration::hours(1);
+        assert_eq!(max_runtime.cap(soon), soon);
+
+        let args = parse_args(&["ccschedule", "--max-runtime", "8 hours"]);
+        assert!(MaxRuntime::from_args(&args, started).is_err());
+    }
+
+    #[test]
+    fn test_classify_failure() {
+        let transient = [
+            "Error: read ECONNRESET",
+            "fetch failed: connect ECONNREFUSED 127.0.0.1:443",
+            "Error: Connection reset by peer (os error 104)",
+            "API Error: Request timed out.",
+            "getaddrinfo EAI_AGAIN api.anthropic.com",
+            "API Error: 529 {\"type\":\"error\",\"error\":{\"type\":\"overloaded_error\"}}",
+            "502 Bad Gateway",
+        ];
+        for stderr in transient {
+            assert_eq!(classify_failure(stderr), FailureKind::Transient, "{stderr}");
+        }
+
+        let permanent = [
+            "",
+            "error: unknown option '--bogus'",
+            "Error: Invalid API key · Please run /login",
+            "fatal: not a git repository",
+        ];
+        for stderr in permanent {
+            assert_eq!(classify_failure(stderr), FailureKind::Permanent, "{stderr}");
+        }
+
+        assert_eq!(
+            classify_failure("Claude AI usage limit reached|1760000000"),
+            FailureKind::RateLimited
+        );
+        // A rate limit wins over the connection closing because of it
+        assert_eq!(
+            classify_failure("429 Too Many Requests; connection closed"),
+            FailureKind::RateLimited
+        );
+    }
+
+    #[tokio::test]
+    async fn test_network_retries_only_retry_transient_failures() {
+        let policy = RetryPolicy {
+            retries: 0,
+            network_retries: 2,
+            base_delay: Duration::ZERO,
+        };
+        let failing = |stderr: &'static str| {
+            let mut attempts = 0;
+            move || {
+                attempts += 1;
+                let attempt = attempts;
+                async move {
+                    Err::<u32, anyhow::Error>(
+                        SchedulerError::CommandFailed {
+                            program: "claude".to_string(),
+                            code: Some(1),
+                            stderr: stderr.to_string(),
+                        }
+                        .into(),
+                    )
+                    .context(attempt)
+                }
+            }
+        };
+
+        let mut retried = Vec::new();
+        let err = retry_with_backoff(&policy, failing("read ECONNRESET"), |attempt, _, _| {
+            retried.push(attempt)
+        })
+        .await
+        .unwrap_err();
+        assert_eq!(retried, vec![1, 2]);
+        assert_eq!(err.to_string(), "3");
+
+        let err = retry_with_backoff(&policy, failing("unknown option '--bogus'"), |_, _, _| {
+            panic!("should not retry")
+        })
+        .await
+        .unwrap_err();
+        assert_eq!(err.to_string(), "1");
+    }
+
+    #[test]
+    fn test_transient_retry_delay_is_jittered() {
+        let policy = RetryPolicy {
+            retries: 0,
+            network_retries: 3,
+            base_delay: Duration::from_secs(10),
+        };
+        for _ in 0..20 {
+            let delay = policy.jittered_delay_for(2, FailureKind::Transient);
+            assert!(delay >= Duration::from_secs(10) && delay <= Duration::from_secs(20));
+        }
+        assert_eq!(
+            policy.jittered_delay_for(2, FailureKind::Permanent),
+            Duration::from_secs(20)
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_next_run_line_leads_stdout() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let log_dir = temp_dir.path().join("logs");
+        let logger = Logger::new(log_dir.to_str().unwrap()).with_echo(false);
+        logger.init().unwrap();
+        let args = parse_args(&["ccschedule", "--exec", "echo hi", "--quiet"]);
+        let (console, capture) = Console::new(false).with_quiet(true).capturing();
+        let target_time = Local::now();
+
+        run_single_mode(
+            &args,
+            &logger,
+            console,
+            &SystemClock,
+            None,
+            &[target_time],
+            actions_from_args(&args),
+        )
+        .await
+        .unwrap();
+
+        let stdout = capture.stdout();
+        let (first, rest) = stdout.split_once('\n').unwrap();
+        let time = first.strip_prefix("NEXT_RUN=").unwrap();
+        let pattern = Regex::new(r"^\d{4}-\d{2}-\d{2}T\d{2}:\d{2}:\d{2}[+-]\d{2}:\d{2}$").unwrap();
+        assert!(pattern.is_match(time), "unexpected timestamp {time}");
+        let parsed = DateTime::parse_from_rfc3339(time).unwrap();
+        assert_eq!(parsed.timestamp(), target_time.timestamp());
+        assert_eq!(rest, "hi\n");
+
+        // JSON mode announces it as a JSON line of its own
+        let args = parse_args(&["ccschedule", "--exec", "echo hi", "--json"]);
+        let (console, capture) = Console::new(true).capturing();
+        run_single_mode(
+            &args,
+            &logger,
+            console,
+            &SystemClock,
+            None,
+            &[target_time],
+            actions_from_args(&args),
+        )
+        .await
+        .unwrap();
+
+        let stdout = capture.stdout();
+        let first = stdout.lines().next().unwrap();
+        let line: serde_json::Value = serde_json::from_str(first).unwrap();
+        let parsed = DateTime::parse_from_rfc3339(line["next_run"].as_str().unwrap()).unwrap();
+        assert_eq!(parsed.timestamp(), target_time.timestamp());
+    }
+}
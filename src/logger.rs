@@ -1,18 +1,42 @@
+use crate::console::{Tone, paint, write_json_line};
+use crate::error::SchedulerError;
+#[cfg(feature = "sqlite")]
+use crate::sqlite_sink::SqliteSink;
+use crate::stats::LoopSummary;
+use crate::syslog_sink::SyslogSink;
 use anyhow::{Context, Result};
-use chrono::{DateTime, Local};
+use chrono::{DateTime, Days, Local, NaiveDate};
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::fs::{self, OpenOptions};
-use std::io::Write;
-use std::path::Path;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct LogEntry {
     pub timestamp: DateTime<Local>,
     pub action: String,
     pub status: String,
     pub message: Option<String>,
     pub response_content: Option<String>,
+    /// Length of the response in characters, recorded even when the content
+    /// itself is not stored.
+    pub response_length: Option<usize>,
     pub cycle_number: Option<u32>,
+    /// Position of this run among several `--message` steps, from 1.
+    pub step: Option<u32>,
+    pub duration_ms: Option<u64>,
+    /// Anything the command wrote to stderr, kept even when it succeeded.
+    pub stderr_content: Option<String>,
+    /// Exit status of the command, when it ran to completion.
+    pub exit_code: Option<i32>,
+    /// How many milliseconds after its scheduled time the run started, negative if
+    /// early. Only measured with `--verbose-timing`.
+    pub drift_ms: Option<i64>,
 }
 
 impl LogEntry {
@@ -25,6 +49,12 @@ impl LogEntry {
             message,
             response_content: None,
             cycle_number: None,
+            step: None,
+            duration_ms: None,
+            stderr_content: None,
+            exit_code: None,
+            drift_ms: None,
+            response_length: None,
         }
     }
 
@@ -42,9 +72,43 @@ impl LogEntry {
             message,
             response_content,
             cycle_number,
+            step: None,
+            duration_ms: None,
+            stderr_content: None,
+            exit_code: None,
+            drift_ms: None,
+            response_length: None,
         }
     }
 
+    pub fn with_step(mut self, step: Option<u32>) -> Self {
+        self.step = step;
+        self
+    }
+
+    /// Records the command's stderr output, if it wrote any.
+    pub fn with_stderr(mut self, stderr: &str) -> Self {
+        self.stderr_content = (!stderr.is_empty()).then(|| stderr.to_string());
+        self
+    }
+
+    pub fn with_exit_code(mut self, exit_code: Option<i32>) -> Self {
+        self.exit_code = exit_code;
+        self
+    }
+
+    /// Records how far from its scheduled time the run started.
+    pub fn with_drift(mut self, drift_ms: Option<i64>) -> Self {
+        self.drift_ms = drift_ms;
+        self
+    }
+
+    /// Records how long the action took to run.
+    pub fn with_duration(mut self, duration: Duration) -> Self {
+        self.duration_ms = Some(duration.as_millis() as u64);
+        self
+    }
+
     #[allow(dead_code)]
     pub fn success(action: &str, message: Option<String>) -> Self {
         Self::new(action, "success", message)
@@ -72,74 +136,684 @@ impl LogEntry {
     ) -> Self {
         Self::new_with_response(action, "error", message, response_content, cycle_number)
     }
+
+    pub fn ping_success(response: &str, cycle_number: Option<u32>, duration: Duration) -> Self {
+        Self::success_with_response(
+            "ping",
+            Some("Ping sent successfully".to_string()),
+            Some(response.to_string()),
+            cycle_number,
+        )
+        .with_duration(duration)
+    }
+
+    pub fn claude_success(response: &str, cycle_number: Option<u32>, duration: Duration) -> Self {
+        Self::success_with_response(
+            "claude",
+            Some("Claude command executed successfully".to_string()),
+            Some(response.to_string()),
+            cycle_number,
+        )
+        .with_duration(duration)
+    }
+
+    /// A successful keepalive, which records only the round-trip latency.
+    pub fn keepalive(latency: Duration, cycle_number: Option<u32>) -> Self {
+        Self::new_with_response(
+            "keepalive",
+            "success",
+            Some(format!("Keepalive answered in {} ms", latency.as_millis())),
+            None,
+            cycle_number,
+        )
+        .with_duration(latency)
+    }
+
+    /// A failed `ping` or `claude` run.
+    pub fn run_error(action: &str, error_msg: &str, cycle_number: Option<u32>) -> Self {
+        Self::error_with_response(action, Some(error_msg.to_string()), None, cycle_number)
+    }
+}
+
+/// How entries are written to the log file.
+#[derive(clap::ValueEnum, Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// One JSON object per line
+    #[default]
+    Json,
+    /// Human-readable lines
+    Text,
+}
+
+/// What happens when the log directory can't be written at startup.
+#[derive(clap::ValueEnum, Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFailurePolicy {
+    /// Warn once and carry on without the log file
+    Warn,
+    /// Refuse to start
+    #[default]
+    Abort,
+}
+
+/// How much of the log is echoed to the console. The log file always records
+/// every entry.
+#[derive(
+    clap::ValueEnum,
+    Serialize,
+    Deserialize,
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    /// Only failed runs
+    Error,
+    /// Runs and scheduling events, without per-cycle bookkeeping
+    Info,
+    /// Everything, including cycle start/end and --verbose detail
+    #[default]
+    Debug,
+}
+
+impl LogLevel {
+    /// The level an entry belongs to, inferred from its action and status.
+    pub fn of(entry: &LogEntry) -> Self {
+        match (entry.action.as_str(), entry.status.as_str()) {
+            (_, "error") => Self::Error,
+            (_, "debug") | ("cycle", _) => Self::Debug,
+            _ => Self::Info,
+        }
+    }
+
+    /// Whether `entry` is shown when echoing at this level.
+    pub fn shows(self, entry: &LogEntry) -> bool {
+        Self::of(entry) <= self
+    }
+}
+
+/// The dated file entries logged on `date` go to, before any size rotation.
+pub fn daily_log_path(log_dir: &str, date: NaiveDate) -> String {
+    format!("{log_dir}/{}.log", date.format("%Y-%m-%d"))
+}
+
+/// Creates `dir` if needed and checks that files can be made in it, by creating
+/// and removing an empty one.
+fn ensure_writable(dir: &Path) -> Result<()> {
+    fs::create_dir_all(dir)
+        .with_context(|| format!("Failed to create log directory {}", dir.display()))?;
+    let probe = dir.join(format!(".ccschedule-write-test-{}", std::process::id()));
+    fs::write(&probe, b"")
+        .and_then(|()| fs::remove_file(&probe))
+        .with_context(|| format!("Log directory {} is not writable", dir.display()))
+}
+
+/// Extracts the date from a log file name such as `2024-01-01.log`, a
+/// rotated `2024-01-01.3.log`, or a compressed `2024-01-01.log.gz`.
+fn log_file_date(file_name: &str) -> Option<NaiveDate> {
+    let (date_part, rest) = file_name.split_at_checked(10)?;
+    let date = NaiveDate::parse_from_str(date_part, "%Y-%m-%d").ok()?;
+
+    let rest = rest.strip_suffix(".gz").unwrap_or(rest);
+    let rest = rest.strip_suffix(".log")?;
+    let is_log_name = rest.is_empty()
+        || rest
+            .strip_prefix('.')
+            .is_some_and(|index| !index.is_empty() && index.chars().all(|c| c.is_ascii_digit()));
+    is_log_name.then_some(date)
+}
+
+/// Formats an entry as a single human-readable line, e.g.
+/// `2024-01-01 06:00:00 [claude] success (1234 chars, 850 ms)`.
+pub fn format_text_line(entry: &LogEntry) -> String {
+    let mut line = format!(
+        "{} [{}] {}",
+        entry.timestamp.format("%Y-%m-%d %H:%M:%S"),
+        entry.action,
+        entry.status
+    );
+
+    let mut details = Vec::new();
+    if let Some(cycle) = entry.cycle_number {
+        details.push(format!("cycle {cycle}"));
+    }
+    if let Some(step) = entry.step {
+        details.push(format!("step {step}"));
+    }
+    let response_length = entry
+        .response_length
+        .or_else(|| entry.response_content.as_ref().map(|r| r.chars().count()));
+    if let Some(length) = response_length {
+        details.push(format!("{length} chars"));
+    }
+    if let Some(ms) = entry.duration_ms {
+        details.push(format!("{ms} ms"));
+    }
+    if !details.is_empty() {
+        line.push_str(&format!(" ({})", details.join(", ")));
+    }
+
+    // The response itself is only kept in the JSON format
+    if response_length.is_none()
+        && let Some(message) = &entry.message
+    {
+        line.push_str(&format!(" - {}", message.replace('\n', " ")));
+    }
+
+    line
+}
+
+/// Replaces every match of each pattern in `text` with `[REDACTED]`.
+fn redact(text: &str, patterns: &[Regex]) -> String {
+    patterns.iter().fold(text.to_string(), |text, pattern| {
+        pattern.replace_all(&text, "[REDACTED]").into_owned()
+    })
+}
+
+/// The `index`th rotated name for a log file: `name.log` becomes `name.N.log`,
+/// and a name without an extension gets `.N` appended.
+fn rotated_log_path(path: &str, index: u32) -> PathBuf {
+    let path = Path::new(path);
+    match (path.file_stem(), path.extension()) {
+        (Some(stem), Some(extension)) => path.with_file_name(format!(
+            "{}.{index}.{}",
+            stem.to_string_lossy(),
+            extension.to_string_lossy()
+        )),
+        _ => PathBuf::from(format!("{}.{index}", path.display())),
+    }
+}
+
+/// Cuts `text` to its first `max_chars` characters, marking the cut. Slicing
+/// on a char index keeps multibyte UTF-8 intact.
+fn truncate_response(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((end, _)) => format!("{}…[truncated]", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+/// The actions whose entries record a scheduled run, one per kind of action.
+const RUN_ACTIONS: [&str; 4] = ["claude", "ping", "keepalive", "exec"];
+
+/// The statuses a finished run is logged with.
+const RUN_STATUSES: [&str; 2] = ["success", "error"];
+
+/// The time of a run recorded on `line`, in either log format. Other entries,
+/// such as cycle markers and warnings, give `None`.
+fn run_time_from_line(line: &str) -> Option<DateTime<Local>> {
+    if let Ok(entry) = serde_json::from_str::<LogEntry>(line) {
+        let is_run = RUN_ACTIONS.contains(&entry.action.as_str())
+            && RUN_STATUSES.contains(&entry.status.as_str());
+        return is_run.then_some(entry.timestamp);
+    }
+
+    let (timestamp, rest) = line.split_at_checked(19)?;
+    let rest = rest.trim_start();
+    let is_run = RUN_ACTIONS.iter().any(|action| {
+        RUN_STATUSES
+            .iter()
+            .any(|status| rest.starts_with(&format!("[{action}] {status}")))
+    });
+    if !is_run {
+        return None;
+    }
+    let naive = chrono::NaiveDateTime::parse_from_str(timestamp, "%Y-%m-%d %H:%M:%S").ok()?;
+    naive.and_local_timezone(Local).earliest()
 }
 
 pub struct Logger {
     log_dir: String,
+    format: LogFormat,
+    max_log_size: Option<u64>,
+    retention_days: Option<u32>,
+    compress: bool,
+    echo: bool,
+    /// The most detailed entries still echoed to the console.
+    echo_level: LogLevel,
+    /// Whether the console summary is colored by status. Never affects the file.
+    color: bool,
+    /// Whether response content is written, or only its length.
+    store_responses: bool,
+    /// Patterns masked out of stored responses.
+    redactions: Vec<Regex>,
+    /// Stored responses longer than this many characters are cut short.
+    max_response_chars: Option<usize>,
+    /// Append everything to this one file instead of a `{date}.log` per day.
+    single_file: Option<String>,
+    /// Whether entries are written to a log file at all.
+    write_file: bool,
+    /// What [`Logger::init`] does if the log directory isn't writable.
+    failure_policy: LogFailurePolicy,
+    /// Set when the log directory turned out not to be writable, so entries skip
+    /// the file instead of failing one by one.
+    file_unwritable: AtomicBool,
+    /// Also write each entry to stdout as a JSON line, for container log collectors.
+    stdout: bool,
+    /// Also send each entry to the local syslog daemon.
+    syslog: Option<SyslogSink>,
+    /// Also insert each entry into a SQLite database.
+    #[cfg(feature = "sqlite")]
+    sqlite: Option<SqliteSink>,
 }
 
 impl Logger {
     pub fn new(log_dir: &str) -> Self {
         Self {
             log_dir: log_dir.to_string(),
+            format: LogFormat::default(),
+            max_log_size: None,
+            retention_days: None,
+            compress: false,
+            echo: true,
+            echo_level: LogLevel::default(),
+            color: false,
+            store_responses: true,
+            redactions: Vec::new(),
+            max_response_chars: None,
+            single_file: None,
+            write_file: true,
+            failure_policy: LogFailurePolicy::default(),
+            file_unwritable: AtomicBool::new(false),
+            stdout: false,
+            syslog: None,
+            #[cfg(feature = "sqlite")]
+            sqlite: None,
         }
     }
 
+    pub fn with_format(mut self, format: LogFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    pub fn with_max_log_size(mut self, max_log_size: Option<u64>) -> Self {
+        self.max_log_size = max_log_size;
+        self
+    }
+
+    pub fn with_retention_days(mut self, retention_days: Option<u32>) -> Self {
+        self.retention_days = retention_days;
+        self
+    }
+
+    pub fn with_compression(mut self, compress: bool) -> Self {
+        self.compress = compress;
+        self
+    }
+
+    /// Whether each entry is also summarized on the console, on stderr.
+    pub fn with_echo(mut self, echo: bool) -> Self {
+        self.echo = echo;
+        self
+    }
+
+    pub fn with_echo_level(mut self, echo_level: LogLevel) -> Self {
+        self.echo_level = echo_level;
+        self
+    }
+
+    pub fn with_color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Whether response content is stored; when off only its length is kept.
+    pub fn with_response_content(mut self, store_responses: bool) -> Self {
+        self.store_responses = store_responses;
+        self
+    }
+
+    pub fn with_redactions(mut self, redactions: Vec<Regex>) -> Self {
+        self.redactions = redactions;
+        self
+    }
+
+    pub fn with_max_response_chars(mut self, max_response_chars: Option<usize>) -> Self {
+        self.max_response_chars = max_response_chars;
+        self
+    }
+
+    pub fn with_single_file(mut self, single_file: Option<String>) -> Self {
+        self.single_file = single_file;
+        self
+    }
+
+    pub fn with_file(mut self, write_file: bool) -> Self {
+        self.write_file = write_file;
+        self
+    }
+
+    pub fn with_failure_policy(mut self, policy: LogFailurePolicy) -> Self {
+        self.failure_policy = policy;
+        self
+    }
+
+    /// Whether [`init`](Self::init) found the log directory unwritable and left
+    /// the file out of logging, so other state kept there should be skipped too.
+    pub fn file_unwritable(&self) -> bool {
+        self.file_unwritable.load(Ordering::Relaxed)
+    }
+
+    pub fn with_stdout(mut self, stdout: bool) -> Self {
+        self.stdout = stdout;
+        self
+    }
+
+    pub fn with_syslog(mut self, syslog: Option<SyslogSink>) -> Self {
+        self.syslog = syslog;
+        self
+    }
+
+    #[cfg(feature = "sqlite")]
+    pub fn with_sqlite(mut self, sqlite: Option<SqliteSink>) -> Self {
+        self.sqlite = sqlite;
+        self
+    }
+
+    /// Creates the log directory and checks it can be written, then applies
+    /// retention and compression. An unwritable directory fails or, with
+    /// [`LogFailurePolicy::Warn`], is warned about once and left out of logging.
     pub fn init(&self) -> Result<()> {
-        // Create log directory if it doesn't exist
-        if !Path::new(&self.log_dir).exists() {
-            fs::create_dir_all(&self.log_dir).context("Failed to create log directory")?;
+        if !self.write_file {
+            return Ok(());
+        }
+
+        let dir = match &self.single_file {
+            Some(path) => Path::new(path)
+                .parent()
+                .filter(|parent| !parent.as_os_str().is_empty())
+                .unwrap_or(Path::new(".")),
+            None => Path::new(&self.log_dir),
+        };
+        if let Err(e) = ensure_writable(dir) {
+            match self.failure_policy {
+                LogFailurePolicy::Abort => return Err(e),
+                LogFailurePolicy::Warn => {
+                    eprintln!("Warning: {e:#}; log entries will not be written to a file");
+                    self.file_unwritable.store(true, Ordering::Relaxed);
+                    return Ok(());
+                }
+            }
+        }
+
+        // Dated retention and compression don't apply to a single log file
+        if self.single_file.is_some() {
+            return Ok(());
+        }
+
+        if let Some(days) = self.retention_days {
+            self.remove_logs_older_than(days, Local::now().date_naive())?;
+        }
+
+        if self.compress {
+            self.compress_logs_before(Local::now().date_naive())?;
         }
         Ok(())
     }
 
-    pub fn log(&self, entry: LogEntry) -> Result<()> {
-        let date_str = entry.timestamp.format("%Y-%m-%d").to_string();
-        let log_file_path = format!("{}/{}.log", self.log_dir, date_str);
+    /// Gzips every plain dated log file from before `today` into a `.log.gz`
+    /// alongside it and removes the original, returning how many were compressed.
+    fn compress_logs_before(&self, today: NaiveDate) -> Result<usize> {
+        let mut compressed = 0;
 
-        let json_line = serde_json::to_string(&entry).context("Failed to serialize log entry")?;
+        for entry in fs::read_dir(&self.log_dir).context("Failed to read log directory")? {
+            let path = entry.context("Failed to read log directory entry")?.path();
+            let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+            if !file_name.ends_with(".log") || log_file_date(file_name).is_none_or(|d| d >= today) {
+                continue;
+            }
 
-        let mut file = OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(&log_file_path)
-            .context("Failed to open log file")?;
+            let gz_path = path.with_file_name(format!("{file_name}.gz"));
+            let mut input = fs::File::open(&path)
+                .with_context(|| format!("Failed to open log file {}", path.display()))?;
+            let output = fs::File::create(&gz_path)
+                .with_context(|| format!("Failed to create {}", gz_path.display()))?;
+
+            let mut encoder = GzEncoder::new(output, Compression::default());
+            io::copy(&mut input, &mut encoder)
+                .and_then(|_| encoder.finish())
+                .with_context(|| format!("Failed to compress log file {}", path.display()))?;
+
+            fs::remove_file(&path)
+                .with_context(|| format!("Failed to remove log file {}", path.display()))?;
+            compressed += 1;
+        }
+
+        Ok(compressed)
+    }
+
+    /// Deletes dated log files more than `days` days older than `today`,
+    /// returning how many were removed. Files whose names don't carry a
+    /// date are left alone.
+    fn remove_logs_older_than(&self, days: u32, today: NaiveDate) -> Result<usize> {
+        // A retention reaching back past the earliest date keeps everything
+        let Some(cutoff) = today.checked_sub_days(Days::new(days.into())) else {
+            return Ok(0);
+        };
+        let mut removed = 0;
+
+        for entry in fs::read_dir(&self.log_dir).context("Failed to read log directory")? {
+            let path = entry.context("Failed to read log directory entry")?.path();
+            let Some(date) = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(log_file_date)
+            else {
+                continue;
+            };
+
+            if date < cutoff {
+                fs::remove_file(&path)
+                    .with_context(|| format!("Failed to remove old log file {}", path.display()))?;
+                removed += 1;
+            }
+        }
+
+        Ok(removed)
+    }
+
+    /// The latest time a run was logged on `date`, looking
+    /// through that day's log file and any rotated parts of it.
+    pub fn last_run_on(&self, date: NaiveDate) -> Result<Option<DateTime<Local>>> {
+        if let Some(path) = &self.single_file {
+            let contents = match fs::read_to_string(path) {
+                Ok(contents) => contents,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+                Err(e) => return Err(e).with_context(|| format!("Failed to read log file {path}")),
+            };
+            return Ok(contents
+                .lines()
+                .filter_map(run_time_from_line)
+                .filter(|time| time.date_naive() == date)
+                .max());
+        }
+
+        let entries = match fs::read_dir(&self.log_dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e).context("Failed to read log directory"),
+        };
+
+        let mut last_run = None;
+        for entry in entries {
+            let path = entry.context("Failed to read log directory entry")?.path();
+            let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+            if !file_name.ends_with(".log") || log_file_date(file_name) != Some(date) {
+                continue;
+            }
+
+            let contents = fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read log file {}", path.display()))?;
+            last_run = contents
+                .lines()
+                .filter_map(run_time_from_line)
+                .chain(last_run)
+                .max();
+        }
 
-        writeln!(file, "{json_line}").context("Failed to write to log file")?;
+        Ok(last_run)
+    }
+
+    /// Applies the response storage settings to an entry about to be written.
+    fn prepare(&self, mut entry: LogEntry) -> LogEntry {
+        if let Some(response) = entry.response_content.take() {
+            entry.response_length = Some(response.chars().count());
+            entry.response_content = self.store_responses.then(|| {
+                let redacted = redact(&response, &self.redactions);
+                match self.max_response_chars {
+                    Some(max_chars) => truncate_response(&redacted, max_chars),
+                    None => redacted,
+                }
+            });
+        }
+        entry
+    }
+
+    pub fn log(&self, entry: LogEntry) -> Result<()> {
+        self.log_to(entry, &mut io::stdout())
+    }
+
+    /// Logs `entry`, sending the `--log-stdout` JSON line to `stdout`.
+    fn log_to(&self, entry: LogEntry, stdout: &mut impl Write) -> Result<()> {
+        let entry = self.prepare(entry);
+        if self.write_file && !self.file_unwritable.load(Ordering::Relaxed) {
+            self.append_to_file(&entry)?;
+        }
+        if self.stdout {
+            write_json_line(stdout, &entry).context("Failed to write log entry to stdout")?;
+        }
+        // Syslog and SQLite are best-effort: an unreachable daemon or a locked
+        // database shouldn't fail the run
+        if let Some(syslog) = &self.syslog
+            && let Err(e) = syslog.send(&entry)
+        {
+            eprintln!("Warning: {e}");
+        }
+        #[cfg(feature = "sqlite")]
+        if let Some(sqlite) = &self.sqlite
+            && let Err(e) = sqlite.insert(&entry)
+        {
+            eprintln!("Warning: {e:#}");
+        }
+
+        if !self.echo || !self.echo_level.shows(&entry) {
+            return Ok(());
+        }
 
         // Also print to console for immediate feedback
-        println!(
-            "LOG: {} - {} - {}",
+        let duration = entry
+            .duration_ms
+            .map(|ms| format!(" ({ms} ms)"))
+            .unwrap_or_default();
+        let status = match Tone::of_status(&entry.status) {
+            Some(tone) => paint(&entry.status, tone, self.color),
+            None => entry.status.clone(),
+        };
+        eprintln!(
+            "LOG: {} - {} - {status}{duration}",
             entry.timestamp.format("%H:%M:%S"),
             entry.action,
-            entry.status
         );
 
         if let Some(msg) = &entry.message {
-            println!("     {msg}");
+            eprintln!("     {msg}");
         }
 
         Ok(())
     }
 
+    fn append_to_file(&self, entry: &LogEntry) -> Result<()> {
+        let log_file_path = self.log_file_path(&entry.timestamp);
+
+        let line = match self.format {
+            LogFormat::Json => {
+                serde_json::to_string(entry).context("Failed to serialize log entry")?
+            }
+            LogFormat::Text => format_text_line(entry),
+        };
+
+        self.rotate_if_needed(&log_file_path, line.len() as u64 + 1)?;
+
+        let log_io = |source| SchedulerError::LogIo {
+            path: log_file_path.clone(),
+            source,
+        };
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&log_file_path)
+            .map_err(log_io)?;
+
+        writeln!(file, "{line}").map_err(log_io)?;
+        Ok(())
+    }
+
+    /// The file an entry logged at `timestamp` is appended to.
+    fn log_file_path(&self, timestamp: &DateTime<Local>) -> String {
+        match &self.single_file {
+            Some(path) => path.clone(),
+            None => daily_log_path(&self.log_dir, timestamp.date_naive()),
+        }
+    }
+
+    /// Rolls `log_file_path` (e.g. `{date}.log`) to the next free numbered
+    /// name (`{date}.N.log`) if appending `incoming` bytes would push it past
+    /// the configured maximum size.
+    fn rotate_if_needed(&self, log_file_path: &str, incoming: u64) -> Result<()> {
+        let Some(max_size) = self.max_log_size else {
+            return Ok(());
+        };
+
+        let current_size = match fs::metadata(log_file_path) {
+            Ok(metadata) => metadata.len(),
+            Err(_) => return Ok(()),
+        };
+
+        // An empty file is never rotated, so a single oversized entry still gets written
+        if current_size == 0 || current_size + incoming <= max_size {
+            return Ok(());
+        }
+
+        let mut index = 1;
+        let rotated_path = loop {
+            let candidate = rotated_log_path(log_file_path, index);
+            if !candidate.exists() {
+                break candidate;
+            }
+            index += 1;
+        };
+
+        fs::rename(log_file_path, &rotated_path).context("Failed to rotate log file")?;
+        Ok(())
+    }
+
     #[allow(dead_code)]
     pub fn log_ping_success(&self) -> Result<()> {
         let entry = LogEntry::success("ping", Some("Ping sent successfully".to_string()));
         self.log(entry)
     }
 
+    #[allow(dead_code)]
     pub fn log_ping_success_with_response(
         &self,
         response: &str,
         cycle_number: Option<u32>,
+        duration: Duration,
+        stderr: &str,
     ) -> Result<()> {
-        let entry = LogEntry::success_with_response(
-            "ping",
-            Some("Ping sent successfully".to_string()),
-            Some(response.to_string()),
-            cycle_number,
-        );
-        self.log(entry)
+        self.log(LogEntry::ping_success(response, cycle_number, duration).with_stderr(stderr))
     }
 
     #[allow(dead_code)]
@@ -148,14 +822,9 @@ impl Logger {
         self.log(entry)
     }
 
+    #[allow(dead_code)]
     pub fn log_ping_error_with_cycle(&self, error_msg: &str, cycle_number: Option<u32>) -> Result<()> {
-        let entry = LogEntry::error_with_response(
-            "ping",
-            Some(error_msg.to_string()),
-            None,
-            cycle_number,
-        );
-        self.log(entry)
+        self.log(LogEntry::run_error("ping", error_msg, cycle_number))
     }
 
     #[allow(dead_code)]
@@ -167,18 +836,15 @@ impl Logger {
         self.log(entry)
     }
 
+    #[allow(dead_code)]
     pub fn log_claude_success_with_response(
         &self,
         response: &str,
         cycle_number: Option<u32>,
+        duration: Duration,
+        stderr: &str,
     ) -> Result<()> {
-        let entry = LogEntry::success_with_response(
-            "claude",
-            Some("Claude command executed successfully".to_string()),
-            Some(response.to_string()),
-            cycle_number,
-        );
-        self.log(entry)
+        self.log(LogEntry::claude_success(response, cycle_number, duration).with_stderr(stderr))
     }
 
     #[allow(dead_code)]
@@ -187,14 +853,14 @@ impl Logger {
         self.log(entry)
     }
 
+    #[allow(dead_code)]
     pub fn log_claude_error_with_cycle(&self, error_msg: &str, cycle_number: Option<u32>) -> Result<()> {
-        let entry = LogEntry::error_with_response(
-            "claude",
-            Some(error_msg.to_string()),
-            None,
-            cycle_number,
-        );
-        self.log(entry)
+        self.log(LogEntry::run_error("claude", error_msg, cycle_number))
+    }
+
+    #[allow(dead_code)]
+    pub fn log_keepalive(&self, latency: Duration, cycle_number: Option<u32>) -> Result<()> {
+        self.log(LogEntry::keepalive(latency, cycle_number))
     }
 
     pub fn log_cycle_start(&self, cycle_number: u32) -> Result<()> {
@@ -218,12 +884,150 @@ impl Logger {
         );
         self.log(entry)
     }
+
+    pub fn log_jitter(
+        &self,
+        offset_seconds: u64,
+        fire_time: DateTime<Local>,
+        cycle_number: Option<u32>,
+    ) -> Result<()> {
+        let entry = LogEntry::new_with_response(
+            "schedule",
+            "jitter",
+            Some(format!(
+                "Applied {offset_seconds}s jitter, firing at {}",
+                fire_time.format("%Y-%m-%d %H:%M:%S")
+            )),
+            None,
+            cycle_number,
+        );
+        self.log(entry)
+    }
+
+    pub fn log_spacing(
+        &self,
+        delay: chrono::Duration,
+        fire_time: DateTime<Local>,
+        cycle_number: Option<u32>,
+    ) -> Result<()> {
+        let entry = LogEntry::new_with_response(
+            "schedule",
+            "spacing",
+            Some(format!(
+                "Delayed {}s to respect --min-spacing, firing at {}",
+                delay.num_seconds(),
+                fire_time.format("%Y-%m-%d %H:%M:%S")
+            )),
+            None,
+            cycle_number,
+        );
+        self.log(entry)
+    }
+
+    pub fn log_retry(
+        &self,
+        action: &str,
+        attempt: u32,
+        total_attempts: u32,
+        error_msg: &str,
+        cycle_number: Option<u32>,
+    ) -> Result<()> {
+        let entry = LogEntry::new_with_response(
+            action,
+            "retry",
+            Some(format!(
+                "Attempt {attempt} of {total_attempts} failed: {error_msg}"
+            )),
+            None,
+            cycle_number,
+        );
+        self.log(entry)
+    }
+
+    /// Records diagnostic detail, such as the exact command about to run.
+    pub fn log_debug(&self, action: &str, message: &str, cycle_number: Option<u32>) -> Result<()> {
+        let entry = LogEntry::new_with_response(
+            action,
+            "debug",
+            Some(message.to_string()),
+            None,
+            cycle_number,
+        );
+        self.log(entry)
+    }
+
+    pub fn log_warning(&self, warning: &str) -> Result<()> {
+        let entry =
+            LogEntry::new_with_response("config", "warning", Some(warning.to_string()), None, None);
+        self.log(entry)
+    }
+
+    /// Records a fire point skipped because an earlier execution was still running
+    /// (`--on-overlap skip`).
+    pub fn log_overlap_skip(&self, cycle_number: Option<u32>) -> Result<()> {
+        let entry = LogEntry::new_with_response(
+            "scheduler",
+            "skipped",
+            Some("Skipped the fire point, a previous execution is still running".to_string()),
+            None,
+            cycle_number,
+        );
+        self.log(entry)
+    }
+
+    /// Records a fire point skipped because the `--control-file` says to pause.
+    pub fn log_paused(&self, control_file: &str, cycle_number: u32) -> Result<()> {
+        let entry = LogEntry::new_with_response(
+            "scheduler",
+            "paused",
+            Some(format!("Skipped the fire point, paused by {control_file}")),
+            None,
+            Some(cycle_number),
+        );
+        self.log(entry)
+    }
+
+    /// Records that the `--config` file was reloaded on SIGHUP.
+    pub fn log_reload(&self, message: &str) -> Result<()> {
+        let entry = LogEntry::new_with_response(
+            "config",
+            "reloaded",
+            Some(message.to_string()),
+            None,
+            None,
+        );
+        self.log(entry)
+    }
+
+    /// Records that the scheduler is stopping early, e.g. on reaching `--max-runtime`.
+    pub fn log_termination(&self, reason: &str) -> Result<()> {
+        let entry = LogEntry::new_with_response(
+            "scheduler",
+            "terminated",
+            Some(reason.to_string()),
+            None,
+            None,
+        );
+        self.log(entry)
+    }
+
+    pub fn log_loop_summary(&self, summary: &LoopSummary) -> Result<()> {
+        let entry = LogEntry::new_with_response(
+            "summary",
+            "complete",
+            Some(format!("Loop finished after {}", summary.describe())),
+            None,
+            None,
+        );
+        self.log(entry)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    use chrono::TimeZone;
     use tempfile::tempdir;
 
     #[test]
@@ -234,6 +1038,7 @@ mod tests {
         assert_eq!(entry.message, Some("test message".to_string()));
         assert_eq!(entry.response_content, None);
         assert_eq!(entry.cycle_number, None);
+        assert_eq!(entry.duration_ms, None);
     }
 
     #[test]
@@ -249,6 +1054,10 @@ mod tests {
         assert_eq!(entry.message, Some("test message".to_string()));
         assert_eq!(entry.response_content, Some("response content".to_string()));
         assert_eq!(entry.cycle_number, Some(5));
+        assert_eq!(entry.duration_ms, None);
+
+        let entry = entry.with_duration(Duration::from_millis(1234));
+        assert_eq!(entry.duration_ms, Some(1234));
     }
 
     #[test]
@@ -277,4 +1086,570 @@ mod tests {
         let log_file_path = format!("{}/{}.log", log_dir, date_str);
         assert!(Path::new(&log_file_path).exists());
     }
+
+    #[test]
+    fn test_log_entry_serialization() {
+        let entry = LogEntry::success("test", None)
+            .with_duration(Duration::from_millis(250))
+            .with_exit_code(Some(0));
+        let json = serde_json::to_value(&entry).unwrap();
+        assert_eq!(json["duration_ms"], 250);
+        assert_eq!(json["exit_code"], 0);
+
+        // Entries written before duration, stderr and exit code tracking still deserialize
+        let old = r#"{"timestamp":"2024-01-01T06:00:00+00:00","action":"claude","status":"success","message":null,"response_content":null,"cycle_number":null}"#;
+        let entry: LogEntry = serde_json::from_str(old).unwrap();
+        assert_eq!(entry.duration_ms, None);
+        assert_eq!(entry.stderr_content, None);
+        assert_eq!(entry.exit_code, None);
+    }
+
+    #[test]
+    fn test_success_records_duration() {
+        let temp_dir = tempdir().unwrap();
+        let log_dir = temp_dir.path().to_string_lossy().to_string();
+
+        let logger = Logger::new(&log_dir);
+        logger.init().unwrap();
+        logger
+            .log_claude_success_with_response("done", Some(1), Duration::from_millis(1500), "")
+            .unwrap();
+
+        let date_str = Local::now().format("%Y-%m-%d").to_string();
+        let contents = fs::read_to_string(format!("{log_dir}/{date_str}.log")).unwrap();
+        let entry: LogEntry = serde_json::from_str(contents.lines().last().unwrap()).unwrap();
+        assert_eq!(entry.status, "success");
+        assert_eq!(entry.duration_ms, Some(1500));
+    }
+
+    fn read_today_log(log_dir: &str) -> String {
+        let date_str = Local::now().format("%Y-%m-%d").to_string();
+        fs::read_to_string(format!("{log_dir}/{date_str}.log")).unwrap()
+    }
+
+    #[test]
+    fn test_json_log_format() {
+        let temp_dir = tempdir().unwrap();
+        let log_dir = temp_dir.path().to_string_lossy().to_string();
+
+        let logger = Logger::new(&log_dir).with_format(LogFormat::Json);
+        logger.init().unwrap();
+        logger
+            .log_claude_success_with_response("response", None, Duration::from_millis(10), "")
+            .unwrap();
+
+        let contents = read_today_log(&log_dir);
+        let entry: LogEntry = serde_json::from_str(contents.trim()).unwrap();
+        assert_eq!(entry.action, "claude");
+        assert_eq!(entry.response_content, Some("response".to_string()));
+    }
+
+    #[test]
+    fn test_text_log_format() {
+        let temp_dir = tempdir().unwrap();
+        let log_dir = temp_dir.path().to_string_lossy().to_string();
+
+        let logger = Logger::new(&log_dir).with_format(LogFormat::Text);
+        logger.init().unwrap();
+        logger
+            .log_claude_success_with_response(
+                &"x".repeat(1234),
+                None,
+                Duration::from_millis(850),
+                "",
+            )
+            .unwrap();
+        logger
+            .log_claude_error_with_cycle("exit code 1\nbad flag", Some(2))
+            .unwrap();
+
+        let contents = read_today_log(&log_dir);
+        let lines: Vec<&str> = contents.lines().collect();
+        assert_eq!(lines.len(), 2);
+
+        let date = Local::now().format("%Y-%m-%d").to_string();
+        assert!(lines[0].starts_with(&date));
+        assert!(lines[0].ends_with("[claude] success (1234 chars, 850 ms)"));
+        assert!(lines[1].ends_with("[claude] error (cycle 2) - exit code 1 bad flag"));
+        assert!(serde_json::from_str::<LogEntry>(lines[0]).is_err());
+    }
+
+    #[test]
+    fn test_log_rotation_by_size() {
+        let temp_dir = tempdir().unwrap();
+        let log_dir = temp_dir.path().to_string_lossy().to_string();
+
+        let logger = Logger::new(&log_dir).with_max_log_size(Some(500));
+        logger.init().unwrap();
+        for _ in 0..5 {
+            logger
+                .log_claude_success_with_response(&"x".repeat(200), None, Duration::ZERO, "")
+                .unwrap();
+        }
+
+        let date_str = Local::now().format("%Y-%m-%d").to_string();
+        assert!(Path::new(&format!("{log_dir}/{date_str}.log")).exists());
+        assert!(Path::new(&format!("{log_dir}/{date_str}.1.log")).exists());
+        assert!(Path::new(&format!("{log_dir}/{date_str}.2.log")).exists());
+
+        // Every entry is kept across the rotated files
+        let total_entries: usize = fs::read_dir(&log_dir)
+            .unwrap()
+            .map(|entry| {
+                fs::read_to_string(entry.unwrap().path())
+                    .unwrap()
+                    .lines()
+                    .count()
+            })
+            .sum();
+        assert_eq!(total_entries, 5);
+    }
+
+    #[test]
+    fn test_log_file_date() {
+        let date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        assert_eq!(log_file_date("2024-01-01.log"), Some(date));
+        assert_eq!(log_file_date("2024-01-01.3.log"), Some(date));
+        assert_eq!(log_file_date("2024-01-01.log.gz"), Some(date));
+        assert_eq!(log_file_date("2024-01-01.txt"), None);
+        assert_eq!(log_file_date("2024-01-01.x.log"), None);
+        assert_eq!(log_file_date("notes.log"), None);
+        assert_eq!(log_file_date("2024-13-01.log"), None);
+    }
+
+    #[test]
+    fn test_log_retention_removes_old_files() {
+        let temp_dir = tempdir().unwrap();
+        let log_dir = temp_dir.path().to_string_lossy().to_string();
+
+        for name in [
+            "2024-01-01.log",
+            "2024-01-01.1.log",
+            "2024-01-05.log",
+            "2024-01-08.log",
+            "2024-01-10.log",
+            "notes.log",
+            "README",
+        ] {
+            fs::write(format!("{log_dir}/{name}"), "").unwrap();
+        }
+
+        let logger = Logger::new(&log_dir);
+        let today = NaiveDate::from_ymd_opt(2024, 1, 10).unwrap();
+        let removed = logger.remove_logs_older_than(5, today).unwrap();
+        assert_eq!(removed, 2);
+
+        let mut remaining: Vec<String> = fs::read_dir(&log_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        remaining.sort();
+        assert_eq!(
+            remaining,
+            vec![
+                "2024-01-05.log",
+                "2024-01-08.log",
+                "2024-01-10.log",
+                "README",
+                "notes.log"
+            ]
+        );
+
+        assert_eq!(logger.remove_logs_older_than(u32::MAX, today).unwrap(), 0);
+        assert_eq!(fs::read_dir(&log_dir).unwrap().count(), 5);
+    }
+
+    #[test]
+    fn test_compress_old_logs() {
+        use flate2::read::GzDecoder;
+        use std::io::Read;
+
+        let temp_dir = tempdir().unwrap();
+        let log_dir = temp_dir.path().to_string_lossy().to_string();
+        fs::write(format!("{log_dir}/2024-01-01.log"), "old entry\n").unwrap();
+        fs::write(format!("{log_dir}/2024-01-10.log"), "today entry\n").unwrap();
+
+        let logger = Logger::new(&log_dir).with_compression(true);
+        let today = NaiveDate::from_ymd_opt(2024, 1, 10).unwrap();
+        assert_eq!(logger.compress_logs_before(today).unwrap(), 1);
+
+        assert!(!Path::new(&format!("{log_dir}/2024-01-01.log")).exists());
+        assert!(Path::new(&format!("{log_dir}/2024-01-10.log")).exists());
+
+        let gz = fs::File::open(format!("{log_dir}/2024-01-01.log.gz")).unwrap();
+        let mut contents = String::new();
+        GzDecoder::new(gz).read_to_string(&mut contents).unwrap();
+        assert_eq!(contents, "old entry\n");
+    }
+
+    #[test]
+    fn test_with_stderr_ignores_empty_output() {
+        let entry = LogEntry::success("claude", None).with_stderr("");
+        assert_eq!(entry.stderr_content, None);
+
+        let entry = LogEntry::success("claude", None).with_stderr("warning: deprecated flag\n");
+        assert_eq!(
+            entry.stderr_content.as_deref(),
+            Some("warning: deprecated flag\n")
+        );
+    }
+
+    #[test]
+    fn test_last_run_on() {
+        let temp_dir = tempdir().unwrap();
+        let log_dir = temp_dir.path().to_string_lossy().to_string();
+        let today = Local::now().date_naive();
+
+        let logger = Logger::new(&log_dir).with_echo(false);
+        logger.init().unwrap();
+        assert_eq!(logger.last_run_on(today).unwrap(), None);
+
+        logger.log_cycle_start(1).unwrap();
+        assert_eq!(logger.last_run_on(today).unwrap(), None);
+
+        let entry = LogEntry::run_error("claude", "exit code 1", Some(1));
+        let logged_at = entry.timestamp;
+        logger.log(entry).unwrap();
+        logger.log_cycle_end(1).unwrap();
+        assert_eq!(logger.last_run_on(today).unwrap(), Some(logged_at));
+
+        // Text logs record whole seconds
+        let text_logger = Logger::new(&log_dir)
+            .with_format(LogFormat::Text)
+            .with_echo(false);
+        text_logger
+            .log_ping_error_with_cycle("timed out", None)
+            .unwrap();
+        let last = text_logger.last_run_on(today).unwrap().unwrap();
+        assert!(last >= logged_at - chrono::Duration::seconds(1));
+
+        let yesterday = today - chrono::Duration::days(1);
+        assert_eq!(logger.last_run_on(yesterday).unwrap(), None);
+    }
+
+    #[test]
+    fn test_keepalive_records_latency_only() {
+        let temp_dir = tempdir().unwrap();
+        let log_dir = temp_dir.path().to_string_lossy().to_string();
+
+        let logger = Logger::new(&log_dir).with_echo(false);
+        logger.init().unwrap();
+        logger
+            .log_keepalive(Duration::from_millis(1234), Some(3))
+            .unwrap();
+
+        let entry: LogEntry = serde_json::from_str(read_today_log(&log_dir).trim()).unwrap();
+        assert_eq!(entry.action, "keepalive");
+        assert_eq!(entry.status, "success");
+        assert_eq!(entry.duration_ms, Some(1234));
+        assert_eq!(entry.cycle_number, Some(3));
+        assert_eq!(entry.response_content, None);
+        assert_eq!(
+            entry.message.as_deref(),
+            Some("Keepalive answered in 1234 ms")
+        );
+    }
+
+    #[test]
+    fn test_redact() {
+        let patterns = vec![
+            Regex::new(r"sk-ant-[A-Za-z0-9-]+").unwrap(),
+            Regex::new(r"(?i)password: \S+").unwrap(),
+        ];
+        assert_eq!(
+            redact(
+                "key sk-ant-api03-abc123 and Password: hunter2, again sk-ant-xyz",
+                &patterns
+            ),
+            "key [REDACTED] and [REDACTED] again [REDACTED]"
+        );
+        assert_eq!(redact("nothing to hide", &patterns), "nothing to hide");
+        assert_eq!(redact("sk-ant-abc", &[]), "sk-ant-abc");
+    }
+
+    #[test]
+    fn test_redaction_applies_to_stored_response() {
+        let temp_dir = tempdir().unwrap();
+        let log_dir = temp_dir.path().to_string_lossy().to_string();
+
+        let logger = Logger::new(&log_dir)
+            .with_echo(false)
+            .with_redactions(vec![Regex::new(r"sk-[a-z0-9]+").unwrap()]);
+        logger.init().unwrap();
+        logger
+            .log(LogEntry::claude_success(
+                "token sk-abc123",
+                None,
+                Duration::ZERO,
+            ))
+            .unwrap();
+
+        let entry: LogEntry = serde_json::from_str(read_today_log(&log_dir).trim()).unwrap();
+        assert_eq!(entry.response_content.as_deref(), Some("token [REDACTED]"));
+        assert_eq!(entry.response_length, Some(15));
+    }
+
+    #[test]
+    fn test_length_only_response_log() {
+        let temp_dir = tempdir().unwrap();
+        let log_dir = temp_dir.path().to_string_lossy().to_string();
+
+        let logger = Logger::new(&log_dir)
+            .with_echo(false)
+            .with_response_content(false);
+        logger.init().unwrap();
+        logger
+            .log(LogEntry::claude_success("秘密の応答", None, Duration::ZERO))
+            .unwrap();
+
+        let contents = read_today_log(&log_dir);
+        assert!(!contents.contains("秘密"));
+        let entry: LogEntry = serde_json::from_str(contents.trim()).unwrap();
+        assert_eq!(entry.response_content, None);
+        assert_eq!(entry.response_length, Some(5));
+
+        let text_logger = Logger::new(&log_dir)
+            .with_echo(false)
+            .with_format(LogFormat::Text)
+            .with_response_content(false);
+        text_logger
+            .log(LogEntry::claude_success("秘密の応答", None, Duration::ZERO))
+            .unwrap();
+        let last_line = read_today_log(&log_dir).lines().last().unwrap().to_string();
+        assert!(last_line.ends_with("[claude] success (5 chars, 0 ms)"));
+    }
+
+    #[test]
+    fn test_truncate_response() {
+        assert_eq!(truncate_response("short", 10), "short");
+        assert_eq!(truncate_response("exactly10!", 10), "exactly10!");
+        assert_eq!(
+            truncate_response("a longer response", 8),
+            "a longer…[truncated]"
+        );
+        // Each of these characters is three bytes; a byte-based cut at 4 would panic
+        assert_eq!(truncate_response("今天全球天气", 2), "今天…[truncated]");
+        assert_eq!(truncate_response("天气🌡️很热", 3), "天气🌡…[truncated]");
+    }
+
+    #[test]
+    fn test_max_response_chars_keeps_true_length() {
+        let temp_dir = tempdir().unwrap();
+        let log_dir = temp_dir.path().to_string_lossy().to_string();
+
+        let logger = Logger::new(&log_dir)
+            .with_echo(false)
+            .with_max_response_chars(Some(4));
+        logger.init().unwrap();
+        logger
+            .log(LogEntry::ping_success(
+                "今天最热的地方",
+                None,
+                Duration::ZERO,
+            ))
+            .unwrap();
+
+        let entry: LogEntry = serde_json::from_str(read_today_log(&log_dir).trim()).unwrap();
+        assert_eq!(
+            entry.response_content.as_deref(),
+            Some("今天最热…[truncated]")
+        );
+        assert_eq!(entry.response_length, Some(7));
+    }
+
+    #[test]
+    fn test_rotated_log_path() {
+        assert_eq!(
+            rotated_log_path("logs/2024-01-01.log", 2),
+            PathBuf::from("logs/2024-01-01.2.log")
+        );
+        assert_eq!(
+            rotated_log_path("/var/log/ccschedule", 1),
+            PathBuf::from("/var/log/ccschedule.1")
+        );
+    }
+
+    #[test]
+    fn test_single_log_file_ignores_dates() {
+        let temp_dir = tempdir().unwrap();
+        let log_file = temp_dir.path().join("nested/ccschedule.log");
+        let log_file = log_file.to_string_lossy().to_string();
+
+        let logger = Logger::new("unused")
+            .with_echo(false)
+            .with_single_file(Some(log_file.clone()));
+        logger.init().unwrap();
+
+        let mut first = LogEntry::claude_success("monday", None, Duration::ZERO);
+        first.timestamp = Local.with_ymd_and_hms(2024, 3, 11, 23, 59, 0).unwrap();
+        let mut second = LogEntry::claude_success("tuesday", None, Duration::ZERO);
+        second.timestamp = Local.with_ymd_and_hms(2024, 3, 12, 0, 1, 0).unwrap();
+        logger.log(first).unwrap();
+        logger.log(second).unwrap();
+
+        let contents = fs::read_to_string(&log_file).unwrap();
+        assert_eq!(contents.lines().count(), 2);
+        assert!(contents.contains("monday") && contents.contains("tuesday"));
+        assert!(!Path::new("unused").exists());
+        // Only the single file exists under the temp directory
+        assert_eq!(
+            fs::read_dir(temp_dir.path().join("nested"))
+                .unwrap()
+                .count(),
+            1
+        );
+
+        let tuesday = NaiveDate::from_ymd_opt(2024, 3, 12).unwrap();
+        assert_eq!(
+            logger.last_run_on(tuesday).unwrap(),
+            Some(Local.with_ymd_and_hms(2024, 3, 12, 0, 1, 0).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_single_log_file_rotates_by_size() {
+        let temp_dir = tempdir().unwrap();
+        let log_file = temp_dir.path().join("ccschedule.log");
+        let log_file = log_file.to_string_lossy().to_string();
+
+        let logger = Logger::new("unused")
+            .with_echo(false)
+            .with_single_file(Some(log_file.clone()))
+            .with_max_log_size(Some(300));
+        logger.init().unwrap();
+        for _ in 0..3 {
+            logger
+                .log(LogEntry::claude_success(
+                    &"x".repeat(100),
+                    None,
+                    Duration::ZERO,
+                ))
+                .unwrap();
+        }
+
+        assert!(Path::new(&log_file).exists());
+        assert!(temp_dir.path().join("ccschedule.1.log").exists());
+    }
+
+    #[test]
+    fn test_log_stdout_writes_json_line() {
+        let temp_dir = tempdir().unwrap();
+        let log_dir = temp_dir.path().join("logs");
+        let log_dir = log_dir.to_string_lossy().to_string();
+
+        let logger = Logger::new(&log_dir)
+            .with_echo(false)
+            .with_format(LogFormat::Text)
+            .with_stdout(true)
+            .with_file(false);
+        logger.init().unwrap();
+
+        let mut stdout = Vec::new();
+        logger
+            .log_to(
+                LogEntry::claude_success("hello", Some(2), Duration::from_millis(5)),
+                &mut stdout,
+            )
+            .unwrap();
+
+        // Stdout always gets JSON, whatever the file format
+        let output = String::from_utf8(stdout).unwrap();
+        assert_eq!(output.lines().count(), 1);
+        let entry: LogEntry = serde_json::from_str(output.trim()).unwrap();
+        assert_eq!(entry.action, "claude");
+        assert_eq!(entry.response_content.as_deref(), Some("hello"));
+        assert_eq!(entry.cycle_number, Some(2));
+
+        // With the file disabled, nothing touches the log directory
+        assert!(!Path::new(&log_dir).exists());
+    }
+
+    #[test]
+    fn test_log_level_shows() {
+        let failed = LogEntry::run_error("claude", "boom", Some(1));
+        let succeeded = LogEntry::claude_success("ok", Some(1), Duration::ZERO);
+        let cycle_start = LogEntry::new_with_response("cycle", "start", None, None, Some(1));
+        let detail = LogEntry::new_with_response("claude", "debug", None, None, Some(1));
+
+        assert!(LogLevel::Error.shows(&failed));
+        assert!(!LogLevel::Error.shows(&succeeded));
+        assert!(!LogLevel::Error.shows(&cycle_start));
+
+        assert!(LogLevel::Info.shows(&failed));
+        assert!(LogLevel::Info.shows(&succeeded));
+        assert!(!LogLevel::Info.shows(&cycle_start));
+        assert!(!LogLevel::Info.shows(&detail));
+
+        for entry in [&failed, &succeeded, &cycle_start, &detail] {
+            assert!(LogLevel::Debug.shows(entry));
+        }
+    }
+
+    #[test]
+    fn test_color_never_reaches_log_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let log_dir = temp_dir.path().to_str().unwrap();
+        for format in [LogFormat::Json, LogFormat::Text] {
+            let logger = Logger::new(log_dir).with_format(format).with_color(true);
+            logger.log(LogEntry::success("claude", None)).unwrap();
+            logger
+                .log(LogEntry::error("claude", Some("failed".to_string())))
+                .unwrap();
+            logger.log_warning("heads up").unwrap();
+        }
+
+        let mut contents = String::new();
+        for file in fs::read_dir(log_dir).unwrap() {
+            contents += &fs::read_to_string(file.unwrap().path()).unwrap();
+        }
+        assert_eq!(contents.lines().count(), 6);
+        assert!(!contents.contains('\x1b'));
+    }
+
+    /// A directory log files can't be created in. Root ignores permission bits, so
+    /// when a read-only directory still takes files, use a path under a plain file.
+    #[cfg(unix)]
+    fn unwritable_dir(temp_dir: &Path) -> PathBuf {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = temp_dir.join("read-only");
+        fs::create_dir(&dir).unwrap();
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o555)).unwrap();
+        if fs::write(dir.join("probe"), b"").is_err() {
+            return dir;
+        }
+        let file = temp_dir.join("file");
+        fs::write(&file, b"").unwrap();
+        file.join("logs")
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_unwritable_log_dir_follows_failure_policy() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = unwritable_dir(temp_dir.path());
+        let dir = dir.to_str().unwrap();
+
+        let aborting = Logger::new(dir)
+            .with_echo(false)
+            .with_failure_policy(LogFailurePolicy::Abort);
+        let err = aborting.init().unwrap_err();
+        assert!(format!("{err:#}").contains(dir));
+        // Aborting is the default, as a directory that couldn't be created always was
+        assert!(Logger::new(dir).with_echo(false).init().is_err());
+
+        let warning = Logger::new(dir)
+            .with_echo(false)
+            .with_stdout(true)
+            .with_failure_policy(LogFailurePolicy::Warn);
+        warning.init().unwrap();
+        assert!(warning.file_unwritable());
+        // Entries still reach the other sinks instead of failing on the file
+        let mut stdout = Vec::new();
+        warning
+            .log_to(LogEntry::success("ping", None), &mut stdout)
+            .unwrap();
+        let stdout = String::from_utf8(stdout).unwrap();
+        assert!(stdout.contains("\"action\":\"ping\""));
+    }
 }
@@ -0,0 +1,161 @@
+use crate::http::{self, Response};
+use crate::logger::LogEntry;
+use hyper::StatusCode;
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+use tokio::net::TcpListener;
+
+/// Upper bounds of the response length histogram buckets, in characters.
+const RESPONSE_LENGTH_BUCKETS: [usize; 5] = [100, 1_000, 10_000, 100_000, 1_000_000];
+
+/// Counters served in the Prometheus text format by `--metrics-port`.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    state: Mutex<MetricsState>,
+}
+
+#[derive(Debug, Default)]
+struct MetricsState {
+    succeeded: u64,
+    failed: u64,
+    last_run_timestamp: Option<i64>,
+    /// Runs with a response no longer than each bucket's bound.
+    response_length_buckets: [u64; RESPONSE_LENGTH_BUCKETS.len()],
+    response_length_sum: u64,
+    response_length_count: u64,
+}
+
+impl Metrics {
+    /// Counts a finished run from its log entry.
+    pub fn record(&self, entry: &LogEntry) {
+        let mut state = self.state.lock().unwrap();
+        match entry.status.as_str() {
+            "success" => state.succeeded += 1,
+            "error" => state.failed += 1,
+            _ => return,
+        }
+        state.last_run_timestamp = Some(entry.timestamp.timestamp());
+
+        let response_length = entry.response_length.or_else(|| {
+            entry
+                .response_content
+                .as_ref()
+                .map(|content| content.chars().count())
+        });
+        if let Some(length) = response_length {
+            for (bucket, &bound) in RESPONSE_LENGTH_BUCKETS.iter().enumerate() {
+                if length <= bound {
+                    state.response_length_buckets[bucket] += 1;
+                }
+            }
+            state.response_length_sum += length as u64;
+            state.response_length_count += 1;
+        }
+    }
+
+    /// The metrics in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let state = self.state.lock().unwrap();
+        let mut out = String::new();
+
+        out.push_str("# HELP ccs_runs_total Scheduled executions by outcome.\n");
+        out.push_str("# TYPE ccs_runs_total counter\n");
+        let _ = writeln!(
+            out,
+            "ccs_runs_total{{status=\"success\"}} {}",
+            state.succeeded
+        );
+        let _ = writeln!(out, "ccs_runs_total{{status=\"error\"}} {}", state.failed);
+
+        if let Some(timestamp) = state.last_run_timestamp {
+            out.push_str("# HELP ccs_last_run_timestamp Unix time of the last execution.\n");
+            out.push_str("# TYPE ccs_last_run_timestamp gauge\n");
+            let _ = writeln!(out, "ccs_last_run_timestamp {timestamp}");
+        }
+
+        out.push_str("# HELP ccs_response_length_chars Length of each response.\n");
+        out.push_str("# TYPE ccs_response_length_chars histogram\n");
+        for (bound, count) in RESPONSE_LENGTH_BUCKETS
+            .iter()
+            .zip(state.response_length_buckets)
+        {
+            let _ = writeln!(
+                out,
+                "ccs_response_length_chars_bucket{{le=\"{bound}\"}} {count}"
+            );
+        }
+        let _ = writeln!(
+            out,
+            "ccs_response_length_chars_bucket{{le=\"+Inf\"}} {}",
+            state.response_length_count
+        );
+        let _ = writeln!(
+            out,
+            "ccs_response_length_chars_sum {}",
+            state.response_length_sum
+        );
+        let _ = writeln!(
+            out,
+            "ccs_response_length_chars_count {}",
+            state.response_length_count
+        );
+        out
+    }
+}
+
+/// Serves `/metrics` from `listener` in the background.
+pub fn serve_metrics(listener: TcpListener, metrics: Arc<Metrics>) {
+    http::spawn(listener, move |path| match path {
+        "/metrics" => Response {
+            status: StatusCode::OK,
+            content_type: "text/plain; version=0.0.4",
+            body: metrics.render(),
+        },
+        _ => Response::not_found(),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_metrics_endpoint_after_runs() {
+        let metrics = Arc::new(Metrics::default());
+        let listener = http::bind(0).await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        serve_metrics(listener, Arc::clone(&metrics));
+
+        let success =
+            LogEntry::success_with_response("claude", None, Some("x".repeat(150)), Some(1));
+        metrics.record(&success);
+        metrics.record(&LogEntry::error("claude", Some("failed".to_string())));
+        // Not a run
+        metrics.record(&LogEntry::new_with_response(
+            "cycle",
+            "start",
+            None,
+            None,
+            Some(1),
+        ));
+
+        let body = reqwest::get(format!("http://127.0.0.1:{port}/metrics"))
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        assert!(body.contains("ccs_runs_total{status=\"success\"} 1\n"));
+        assert!(body.contains("ccs_runs_total{status=\"error\"} 1\n"));
+        assert!(body.contains("ccs_last_run_timestamp "));
+        assert!(body.contains("ccs_response_length_chars_bucket{le=\"100\"} 0\n"));
+        assert!(body.contains("ccs_response_length_chars_bucket{le=\"1000\"} 1\n"));
+        assert!(body.contains("ccs_response_length_chars_sum 150\n"));
+        assert!(body.contains("ccs_response_length_chars_count 1\n"));
+
+        let missing = reqwest::get(format!("http://127.0.0.1:{port}/other"))
+            .await
+            .unwrap();
+        assert_eq!(missing.status(), reqwest::StatusCode::NOT_FOUND);
+    }
+}
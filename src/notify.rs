@@ -0,0 +1,429 @@
+use crate::logger::{LogEntry, Logger};
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::time::Duration;
+
+/// How long to wait for a notification endpoint before giving up.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Responses longer than this are cut short in notifications.
+const MAX_RESPONSE_CHARS: usize = 500;
+
+/// How long to wait before retrying a rate-limited request without a `Retry-After` header.
+const DEFAULT_RATE_LIMIT_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Upper bound on a `Retry-After` wait, so a notification can't stall the scheduler.
+const MAX_RATE_LIMIT_BACKOFF: Duration = Duration::from_secs(30);
+
+/// JSON body POSTed to `--webhook` after each run.
+#[derive(Serialize, Debug)]
+pub struct WebhookPayload {
+    pub action: String,
+    pub status: String,
+    pub timestamp: String,
+    pub cycle_number: Option<u32>,
+    pub message: Option<String>,
+    pub response: Option<String>,
+}
+
+impl From<&LogEntry> for WebhookPayload {
+    fn from(entry: &LogEntry) -> Self {
+        Self {
+            action: entry.action.clone(),
+            status: entry.status.clone(),
+            timestamp: entry.timestamp.to_rfc3339(),
+            cycle_number: entry.cycle_number,
+            message: entry.message.clone(),
+            response: entry
+                .response_content
+                .as_deref()
+                .map(|response| truncate_chars(response, MAX_RESPONSE_CHARS)),
+        }
+    }
+}
+
+/// Cuts `text` to at most `max_chars` characters, marking where it was cut.
+fn truncate_chars(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((end, _)) => format!("{}...", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+/// Builds a Slack incoming-webhook message summarizing a run result, with a
+/// plain `text` fallback and a block layout for clients that render it.
+pub fn slack_payload(entry: &LogEntry) -> serde_json::Value {
+    let (title, _) = desktop_notification(entry);
+    let icon = match entry.status.as_str() {
+        "success" => ":white_check_mark:",
+        "alert" => ":rotating_light:",
+        _ => ":x:",
+    };
+
+    let mut fields = vec![("Status", entry.status.clone())];
+    if let Some(cycle) = entry.cycle_number {
+        fields.push(("Cycle", cycle.to_string()));
+    }
+    if let Some(ms) = entry.duration_ms {
+        fields.push(("Duration", format!("{:.1}s", ms as f64 / 1000.0)));
+    }
+
+    let excerpt = entry
+        .response_content
+        .as_deref()
+        .or(entry.message.as_deref())
+        .map(|text| truncate_chars(text, MAX_RESPONSE_CHARS));
+
+    let mut blocks = vec![
+        serde_json::json!({
+            "type": "section",
+            "text": { "type": "mrkdwn", "text": format!("{icon} *{title}*") },
+        }),
+        serde_json::json!({
+            "type": "section",
+            "fields": fields
+                .iter()
+                .map(|(label, value)| {
+                    serde_json::json!({ "type": "mrkdwn", "text": format!("*{label}:* {value}") })
+                })
+                .collect::<Vec<_>>(),
+        }),
+    ];
+    if let Some(excerpt) = &excerpt {
+        blocks.push(serde_json::json!({
+            "type": "section",
+            "text": { "type": "mrkdwn", "text": format!("```{excerpt}```") },
+        }));
+    }
+
+    let summary = fields
+        .iter()
+        .map(|(label, value)| format!("{label}: {value}"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    serde_json::json!({
+        "text": format!("{title} ({summary})"),
+        "blocks": blocks,
+    })
+}
+
+/// Builds the title and body of the desktop notification for a run result.
+pub fn desktop_notification(entry: &LogEntry) -> (String, String) {
+    let subject = match entry.action.as_str() {
+        "claude" => "Claude run",
+        "ping" => "Ping",
+        other => other,
+    };
+    let title = match entry.status.as_str() {
+        "success" => format!("{subject} succeeded"),
+        "alert" => "Claude Code Schedule alert".to_string(),
+        _ => format!("{subject} failed"),
+    };
+
+    let detail = entry
+        .message
+        .as_deref()
+        .and_then(|message| message.lines().next())
+        .map(|line| truncate_chars(line, 200))
+        .unwrap_or_else(|| entry.status.clone());
+    let body = match entry.cycle_number {
+        Some(cycle) => format!("Cycle {cycle}: {detail}"),
+        None => detail,
+    };
+
+    (title, body)
+}
+
+/// Sends run results to the notification targets chosen on the command line.
+pub struct Notifier {
+    webhook: Option<String>,
+    slack_webhook: Option<String>,
+    desktop: bool,
+    client: reqwest::Client,
+}
+
+impl Notifier {
+    pub fn new(webhook: Option<String>) -> Self {
+        Self {
+            webhook,
+            slack_webhook: None,
+            desktop: false,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    pub fn with_slack_webhook(mut self, slack_webhook: Option<String>) -> Self {
+        self.slack_webhook = slack_webhook;
+        self
+    }
+
+    pub fn with_desktop(mut self, desktop: bool) -> Self {
+        self.desktop = desktop;
+        self
+    }
+
+    /// Delivers `entry` to every configured target. Delivery failures are
+    /// logged as warnings and never interrupt the scheduler.
+    pub async fn notify(&self, entry: &LogEntry, logger: &Logger) {
+        if let Some(url) = &self.webhook
+            && let Err(e) = self.post_json(url, &WebhookPayload::from(entry)).await
+        {
+            warn(logger, &format!("Webhook notification failed: {e:#}"));
+        }
+
+        if let Some(url) = &self.slack_webhook
+            && let Err(e) = self.post_json(url, &slack_payload(entry)).await
+        {
+            warn(logger, &format!("Slack notification failed: {e:#}"));
+        }
+
+        if self.desktop {
+            let (title, body) = desktop_notification(entry);
+            if let Err(e) = notify_rust::Notification::new()
+                .summary(&title)
+                .body(&body)
+                .show()
+            {
+                warn(logger, &format!("Desktop notification failed: {e}"));
+            }
+        }
+    }
+
+    /// POSTs `body` as JSON, backing off and retrying once if the endpoint
+    /// answers 429 Too Many Requests.
+    async fn post_json(&self, url: &str, body: &impl Serialize) -> Result<()> {
+        let mut response = self.send_json(url, body).await?;
+
+        if response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            let delay = retry_after(&response).unwrap_or(DEFAULT_RATE_LIMIT_BACKOFF);
+            tokio::time::sleep(delay.min(MAX_RATE_LIMIT_BACKOFF)).await;
+            response = self.send_json(url, body).await?;
+        }
+
+        response
+            .error_for_status()
+            .context("Notification endpoint returned an error")?;
+        Ok(())
+    }
+
+    async fn send_json(&self, url: &str, body: &impl Serialize) -> Result<reqwest::Response> {
+        self.client
+            .post(url)
+            .timeout(REQUEST_TIMEOUT)
+            .json(body)
+            .send()
+            .await
+            .with_context(|| format!("Failed to send POST to {url}"))
+    }
+}
+
+/// Reads the number of seconds to wait from a `Retry-After` header.
+fn retry_after(response: &reqwest::Response) -> Option<Duration> {
+    let seconds = response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()?;
+    Some(Duration::from_secs(seconds))
+}
+
+fn warn(logger: &Logger, warning: &str) {
+    if let Err(e) = logger.log_warning(warning) {
+        eprintln!("Warning: {warning} (and failed to log it: {e})");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{Value, json};
+    use tempfile::tempdir;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn test_truncate_chars() {
+        assert_eq!(truncate_chars("short", 10), "short");
+        assert_eq!(truncate_chars("exactly10!", 10), "exactly10!");
+        assert_eq!(truncate_chars("a longer response", 8), "a longer...");
+        assert_eq!(truncate_chars("天气很好今天", 2), "天气...");
+    }
+
+    #[test]
+    fn test_webhook_payload_truncates_response() {
+        let entry = LogEntry::claude_success(&"x".repeat(1000), Some(3), Duration::ZERO);
+        let payload = WebhookPayload::from(&entry);
+
+        assert_eq!(payload.action, "claude");
+        assert_eq!(payload.status, "success");
+        assert_eq!(payload.cycle_number, Some(3));
+        assert_eq!(payload.response.unwrap().len(), MAX_RESPONSE_CHARS + 3);
+    }
+
+    #[test]
+    fn test_desktop_notification_for_success() {
+        let entry = LogEntry::claude_success("done", None, Duration::ZERO);
+        let (title, body) = desktop_notification(&entry);
+        assert_eq!(title, "Claude run succeeded");
+        assert_eq!(body, "Claude command executed successfully");
+    }
+
+    #[test]
+    fn test_desktop_notification_for_failure_in_loop() {
+        let entry = LogEntry::run_error("ping", "timed out\nstderr details", Some(4));
+        let (title, body) = desktop_notification(&entry);
+        assert_eq!(title, "Ping failed");
+        assert_eq!(body, "Cycle 4: timed out");
+    }
+
+    #[test]
+    fn test_desktop_notification_for_alert() {
+        let message = "3 cycles in a row have failed, most recently cycle 7";
+        let entry = LogEntry::new_with_response(
+            "scheduler",
+            "alert",
+            Some(message.to_string()),
+            None,
+            Some(7),
+        );
+        let (title, body) = desktop_notification(&entry);
+        assert_eq!(title, "Claude Code Schedule alert");
+        assert_eq!(body, format!("Cycle 7: {message}"));
+    }
+
+    #[test]
+    fn test_slack_payload_for_success() {
+        let entry =
+            LogEntry::claude_success("All tests pass", Some(2), Duration::from_millis(1500));
+        assert_eq!(
+            slack_payload(&entry),
+            json!({
+                "text": "Claude run succeeded (Status: success, Cycle: 2, Duration: 1.5s)",
+                "blocks": [
+                    {
+                        "type": "section",
+                        "text": { "type": "mrkdwn", "text": ":white_check_mark: *Claude run succeeded*" },
+                    },
+                    {
+                        "type": "section",
+                        "fields": [
+                            { "type": "mrkdwn", "text": "*Status:* success" },
+                            { "type": "mrkdwn", "text": "*Cycle:* 2" },
+                            { "type": "mrkdwn", "text": "*Duration:* 1.5s" },
+                        ],
+                    },
+                    {
+                        "type": "section",
+                        "text": { "type": "mrkdwn", "text": "```All tests pass```" },
+                    },
+                ],
+            })
+        );
+    }
+
+    #[test]
+    fn test_slack_payload_for_error() {
+        let entry = LogEntry::run_error("ping", "connection refused", None);
+        assert_eq!(
+            slack_payload(&entry),
+            json!({
+                "text": "Ping failed (Status: error)",
+                "blocks": [
+                    {
+                        "type": "section",
+                        "text": { "type": "mrkdwn", "text": ":x: *Ping failed*" },
+                    },
+                    {
+                        "type": "section",
+                        "fields": [{ "type": "mrkdwn", "text": "*Status:* error" }],
+                    },
+                    {
+                        "type": "section",
+                        "text": { "type": "mrkdwn", "text": "```connection refused```" },
+                    },
+                ],
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rate_limited_post_is_retried_once() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "0"))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+
+        let notifier = Notifier::new(None);
+        notifier.post_json(&server.uri(), &json!({})).await.unwrap();
+        assert_eq!(server.received_requests().await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_webhook_posts_run_result() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/hook"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let temp_dir = tempdir().unwrap();
+        let logger = Logger::new(&temp_dir.path().to_string_lossy());
+        let notifier = Notifier::new(Some(format!("{}/hook", server.uri())));
+
+        let entry = LogEntry::run_error("claude", "exit code 1", Some(2));
+        notifier.notify(&entry, &logger).await;
+
+        let requests = server.received_requests().await.unwrap();
+        let body: Value = serde_json::from_slice(&requests[0].body).unwrap();
+        assert_eq!(
+            body,
+            json!({
+                "action": "claude",
+                "status": "error",
+                "timestamp": entry.timestamp.to_rfc3339(),
+                "cycle_number": 2,
+                "message": "exit code 1",
+                "response": null,
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_webhook_failure_is_logged_as_warning() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(500))
+            .mount(&server)
+            .await;
+
+        let temp_dir = tempdir().unwrap();
+        let log_dir = temp_dir.path().to_string_lossy().to_string();
+        let logger = Logger::new(&log_dir);
+        let notifier = Notifier::new(Some(server.uri()));
+
+        let entry = LogEntry::ping_success("ok", None, Duration::ZERO);
+        notifier.notify(&entry, &logger).await;
+
+        let date_str = chrono::Local::now().format("%Y-%m-%d").to_string();
+        let contents = std::fs::read_to_string(format!("{log_dir}/{date_str}.log")).unwrap();
+        let warning: LogEntry = serde_json::from_str(contents.trim()).unwrap();
+        assert_eq!(warning.status, "warning");
+        assert!(
+            warning
+                .message
+                .unwrap()
+                .contains("Webhook notification failed")
+        );
+    }
+}
@@ -0,0 +1,93 @@
+use serde::{Deserialize, Serialize};
+use tokio::sync::{Mutex, MutexGuard};
+
+/// What happens when a fire point arrives while another execution in the same
+/// process is still running, chosen with `--on-overlap`.
+#[derive(clap::ValueEnum, Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum OverlapPolicy {
+    /// Drop the late fire point
+    Skip,
+    /// Run it as soon as the execution in progress finishes
+    #[default]
+    Queue,
+}
+
+/// What to do with one fire point.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Overlap {
+    Run,
+    Wait,
+    Skip,
+}
+
+impl OverlapPolicy {
+    /// Decides what a fire point does, given whether an execution is in progress.
+    pub fn decide(self, in_progress: bool) -> Overlap {
+        match (in_progress, self) {
+            (false, _) => Overlap::Run,
+            (true, Self::Queue) => Overlap::Wait,
+            (true, Self::Skip) => Overlap::Skip,
+        }
+    }
+}
+
+/// Held for the whole of an execution, so no two overlap.
+pub struct RunLock {
+    mutex: Mutex<()>,
+}
+
+/// Shared by every scheduler in the process, whatever its mode.
+static GLOBAL: RunLock = RunLock::new();
+
+impl RunLock {
+    pub const fn new() -> Self {
+        Self {
+            mutex: Mutex::const_new(()),
+        }
+    }
+
+    pub fn global() -> &'static Self {
+        &GLOBAL
+    }
+
+    /// Takes the lock for an execution, waiting for the one in progress or giving up
+    /// as `policy` says. `None` means the fire point is skipped.
+    pub async fn acquire(&self, policy: OverlapPolicy) -> Option<MutexGuard<'_, ()>> {
+        if let Ok(guard) = self.mutex.try_lock() {
+            return Some(guard);
+        }
+        match policy.decide(true) {
+            Overlap::Run | Overlap::Wait => Some(self.mutex.lock().await),
+            Overlap::Skip => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_overlap_decision() {
+        assert_eq!(OverlapPolicy::Skip.decide(false), Overlap::Run);
+        assert_eq!(OverlapPolicy::Queue.decide(false), Overlap::Run);
+        assert_eq!(OverlapPolicy::Skip.decide(true), Overlap::Skip);
+        assert_eq!(OverlapPolicy::Queue.decide(true), Overlap::Wait);
+    }
+
+    #[tokio::test]
+    async fn test_lock_skips_or_queues_while_held() {
+        let lock = RunLock::new();
+        let running = lock.acquire(OverlapPolicy::Skip).await.unwrap();
+        assert!(lock.acquire(OverlapPolicy::Skip).await.is_none());
+
+        let queued = lock.acquire(OverlapPolicy::Queue);
+        tokio::pin!(queued);
+        let waited = tokio::time::timeout(Duration::from_millis(20), queued.as_mut()).await;
+        assert!(waited.is_err());
+        drop(running);
+        assert!(queued.await.is_some());
+    }
+}
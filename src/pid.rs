@@ -0,0 +1,584 @@
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+
+/// Which kind of run a scheduler process is doing.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RunMode {
+    Single,
+    Loop,
+}
+
+impl fmt::Display for RunMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RunMode::Single => write!(f, "single"),
+            RunMode::Loop => write!(f, "loop"),
+        }
+    }
+}
+
+/// How `--pid-file` is written.
+#[derive(clap::ValueEnum, Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PidFormat {
+    /// A JSON object with the PID, start time, run mode and next fire time
+    #[default]
+    Json,
+    /// Plain lines: the PID, the start time, then the next fire time once it is known
+    Text,
+    /// Only the PID, for tools that expect a plain number
+    Bare,
+}
+
+/// What a PID file records about the scheduler that wrote it. A legacy PID
+/// file holding a bare number only fills in `pid`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PidInfo {
+    pub pid: u32,
+    #[serde(default)]
+    pub started_at: Option<DateTime<Local>>,
+    /// When the next run is due, once it has been resolved.
+    #[serde(default)]
+    pub next_fire: Option<DateTime<Local>>,
+    #[serde(default)]
+    pub mode: Option<RunMode>,
+}
+
+impl PidInfo {
+    fn legacy(pid: u32) -> Self {
+        Self {
+            pid,
+            started_at: None,
+            next_fire: None,
+            mode: None,
+        }
+    }
+
+    /// Parses PID file contents in any [`PidFormat`]: JSON, or text whose first
+    /// line is the PID.
+    pub fn parse(contents: &str) -> Result<Self> {
+        let contents = contents.trim();
+        if contents.starts_with('{') {
+            let info: Self = serde_json::from_str(contents).context("Invalid JSON in PID file")?;
+            check_pid(info.pid)?;
+            return Ok(info);
+        }
+        let mut lines = contents.lines().map(str::trim);
+        let pid = lines
+            .next()
+            .unwrap_or_default()
+            .parse()
+            .context("Not a valid PID")
+            .and_then(check_pid)?;
+        let mut time = |name: &str| {
+            lines
+                .next()
+                .filter(|line| !line.is_empty())
+                .map(|line| {
+                    DateTime::parse_from_rfc3339(line)
+                        .map(|time| time.with_timezone(&Local))
+                        .with_context(|| format!("Invalid {name} '{line}' in PID file"))
+                })
+                .transpose()
+        };
+        Ok(Self {
+            started_at: time("start time")?,
+            next_fire: time("next fire time")?,
+            ..Self::legacy(pid)
+        })
+    }
+
+    /// The `--pid-format text` contents: one value per line, leaving out the
+    /// times that aren't known yet.
+    fn to_text(&self) -> String {
+        let mut text = format!("{}\n", self.pid);
+        for time in [self.started_at, self.next_fire].into_iter().flatten() {
+            text.push_str(&time.to_rfc3339());
+            text.push('\n');
+        }
+        text
+    }
+}
+
+/// The PID file owned by the running scheduler.
+#[derive(Debug)]
+pub struct PidFile {
+    path: String,
+    info: PidInfo,
+    format: PidFormat,
+}
+
+impl PidFile {
+    /// Writes a PID file for the current process, refusing to overwrite one
+    /// that belongs to another live process. A PID file left behind by a
+    /// process that has since exited is reclaimed.
+    pub fn create(path: &str, mode: RunMode, format: PidFormat) -> Result<Self> {
+        if let Ok(existing) = read_pid_file(path) {
+            if is_process_alive(existing) {
+                bail!(
+                    "Another scheduler is already running (PID {existing} in {path}); \
+                     stop it first or choose a different --pid-file"
+                );
+            }
+            eprintln!("Warning: Reclaiming stale PID file {path} (PID {existing} is not running)");
+        }
+
+        let pid_file = Self {
+            path: path.to_string(),
+            info: PidInfo {
+                pid: std::process::id(),
+                started_at: Some(Local::now()),
+                next_fire: None,
+                mode: Some(mode),
+            },
+            format,
+        };
+        pid_file.write(&pid_file.info)?;
+        Ok(pid_file)
+    }
+
+    pub fn pid(&self) -> u32 {
+        self.info.pid
+    }
+
+    /// Rewrites the PID file with the time the next run is due.
+    pub fn record_next_fire(&self, next_fire: DateTime<Local>) -> Result<()> {
+        if self.format == PidFormat::Bare {
+            return Ok(());
+        }
+        self.write(&PidInfo {
+            next_fire: Some(next_fire),
+            ..self.info.clone()
+        })
+    }
+
+    /// Replaces the file in one rename so readers never see a partial write.
+    fn write(&self, info: &PidInfo) -> Result<()> {
+        let contents = match self.format {
+            PidFormat::Json => {
+                let json = serde_json::to_string(info).context("Failed to serialize PID file")?;
+                format!("{json}\n")
+            }
+            PidFormat::Text => info.to_text(),
+            PidFormat::Bare => format!("{}\n", info.pid),
+        };
+
+        let temp_path = format!("{}.tmp", self.path);
+        fs::write(&temp_path, contents).context("Failed to write PID file")?;
+        fs::rename(&temp_path, &self.path).context("Failed to write PID file")?;
+        Ok(())
+    }
+}
+
+/// Removes the PID file if one was configured, returning whether it was removed.
+pub fn cleanup_pid_file(pid_file: &Option<String>) -> bool {
+    let Some(path) = pid_file else {
+        return false;
+    };
+    match fs::remove_file(path) {
+        Ok(()) => true,
+        Err(e) => {
+            eprintln!("Warning: Failed to remove PID file {path}: {e}");
+            false
+        }
+    }
+}
+
+/// Reads everything recorded in `pid_file`.
+pub fn read_pid_info(pid_file: &str) -> Result<PidInfo> {
+    let contents = fs::read_to_string(pid_file)
+        .with_context(|| format!("Failed to read PID file {pid_file}"))?;
+    PidInfo::parse(&contents)
+        .with_context(|| format!("PID file {pid_file} does not contain a valid PID"))
+}
+
+/// Rejects PIDs that can't belong to a scheduler: signalling 0 reaches the
+/// caller's whole process group, and 1 is init.
+fn check_pid(pid: u32) -> Result<u32> {
+    if pid <= 1 {
+        bail!("Not a valid PID: {pid}");
+    }
+    Ok(pid)
+}
+
+/// Reads the PID stored in `pid_file`.
+pub fn read_pid_file(pid_file: &str) -> Result<u32> {
+    read_pid_info(pid_file).map(|info| info.pid)
+}
+
+/// Whether the scheduler recorded in a PID file is still running.
+#[derive(Debug, PartialEq, Eq)]
+pub enum PidStatus {
+    Running(PidInfo),
+    Stopped(PidInfo),
+}
+
+impl PidStatus {
+    pub fn describe(&self) -> String {
+        match self {
+            PidStatus::Running(info) => {
+                let mut details = vec![format!("PID {}", info.pid)];
+                if let Some(mode) = info.mode {
+                    details.push(format!("{mode} mode"));
+                }
+                if let Some(next_fire) = info.next_fire {
+                    details.push(format!(
+                        "next run at {}",
+                        next_fire.format("%Y-%m-%d %H:%M:%S")
+                    ));
+                }
+                format!("running ({})", details.join(", "))
+            }
+            PidStatus::Stopped(info) => format!("stopped (PID {} is not running)", info.pid),
+        }
+    }
+}
+
+/// Reads `pid_file` and checks whether the process it names is alive.
+pub fn pid_status(pid_file: &str) -> Result<PidStatus> {
+    let info = read_pid_info(pid_file)?;
+    Ok(if is_process_alive(info.pid) {
+        PidStatus::Running(info)
+    } else {
+        PidStatus::Stopped(info)
+    })
+}
+
+/// Signals processes on behalf of the `stop` command, so the decision logic
+/// can be tested without touching real processes.
+pub trait ProcessControl {
+    fn is_alive(&self, pid: u32) -> bool;
+    fn terminate(&self, pid: u32) -> Result<()>;
+}
+
+/// Controls real processes through the operating system.
+pub struct SystemProcesses;
+
+impl ProcessControl for SystemProcesses {
+    fn is_alive(&self, pid: u32) -> bool {
+        is_process_alive(pid)
+    }
+
+    #[cfg(unix)]
+    fn terminate(&self, pid: u32) -> Result<()> {
+        let raw_pid = libc::pid_t::try_from(pid).context("PID is out of range")?;
+        if unsafe { libc::kill(raw_pid, libc::SIGTERM) } != 0 {
+            return Err(std::io::Error::last_os_error())
+                .with_context(|| format!("Failed to send SIGTERM to PID {pid}"));
+        }
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    fn terminate(&self, _pid: u32) -> Result<()> {
+        bail!("Stopping a scheduler is not supported on this platform")
+    }
+}
+
+/// Sends SIGTERM to the scheduler recorded in `pid_file` and removes the
+/// file, returning the stopped PID.
+pub fn stop_scheduler(pid_file: &str, processes: &impl ProcessControl) -> Result<u32> {
+    let pid = read_pid_file(pid_file)?;
+    if !processes.is_alive(pid) {
+        bail!("Scheduler with PID {pid} is not running (stale PID file {pid_file})");
+    }
+
+    processes.terminate(pid)?;
+    fs::remove_file(pid_file).with_context(|| format!("Failed to remove PID file {pid_file}"))?;
+    Ok(pid)
+}
+
+/// Returns whether a process with the given PID currently exists.
+#[cfg(unix)]
+pub fn is_process_alive(pid: u32) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return false;
+    };
+    // Signal 0 performs the existence and permission checks without sending anything.
+    // EPERM means the process exists but belongs to another user.
+    let result = unsafe { libc::kill(pid, 0) };
+    result == 0 || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+/// Without a portable liveness check, assume an existing PID file is stale.
+#[cfg(not(unix))]
+pub fn is_process_alive(_pid: u32) -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+    use std::cell::RefCell;
+    use tempfile::tempdir;
+
+    struct MockProcesses {
+        alive: bool,
+        terminated: RefCell<Vec<u32>>,
+    }
+
+    impl MockProcesses {
+        fn new(alive: bool) -> Self {
+            Self {
+                alive,
+                terminated: RefCell::new(Vec::new()),
+            }
+        }
+    }
+
+    impl ProcessControl for MockProcesses {
+        fn is_alive(&self, _pid: u32) -> bool {
+            self.alive
+        }
+
+        fn terminate(&self, pid: u32) -> Result<()> {
+            self.terminated.borrow_mut().push(pid);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_stop_terminates_live_scheduler() {
+        let temp_dir = tempdir().unwrap();
+        let pid_file = temp_dir.path().join("run.pid");
+        fs::write(&pid_file, "4242\n").unwrap();
+        let pid_file = pid_file.to_str().unwrap();
+
+        let processes = MockProcesses::new(true);
+        assert_eq!(stop_scheduler(pid_file, &processes).unwrap(), 4242);
+        assert_eq!(*processes.terminated.borrow(), vec![4242]);
+        assert!(!std::path::Path::new(pid_file).exists());
+    }
+
+    #[test]
+    fn test_stop_refuses_dead_scheduler() {
+        let temp_dir = tempdir().unwrap();
+        let pid_file = temp_dir.path().join("run.pid");
+        fs::write(&pid_file, "4242\n").unwrap();
+        let pid_file = pid_file.to_str().unwrap();
+
+        let processes = MockProcesses::new(false);
+        let err = stop_scheduler(pid_file, &processes).unwrap_err();
+        assert!(err.to_string().contains("not running"));
+        assert!(processes.terminated.borrow().is_empty());
+    }
+
+    #[test]
+    fn test_stop_refuses_process_group_pid() {
+        let temp_dir = tempdir().unwrap();
+        let pid_file = temp_dir.path().join("run.pid");
+        let pid_file = pid_file.to_str().unwrap();
+
+        let processes = MockProcesses::new(true);
+        for contents in ["0\n", "1\n", r#"{"pid": 0}"#] {
+            fs::write(pid_file, contents).unwrap();
+            let err = stop_scheduler(pid_file, &processes).unwrap_err();
+            assert!(format!("{err:#}").contains("Not a valid PID"), "{contents}");
+        }
+        assert!(processes.terminated.borrow().is_empty());
+        assert!(std::path::Path::new(pid_file).exists());
+    }
+
+    #[test]
+    fn test_stop_requires_pid_file() {
+        let temp_dir = tempdir().unwrap();
+        let pid_file = temp_dir.path().join("missing.pid");
+
+        let processes = MockProcesses::new(true);
+        let err = stop_scheduler(pid_file.to_str().unwrap(), &processes).unwrap_err();
+        assert!(err.to_string().contains("Failed to read PID file"));
+        assert!(processes.terminated.borrow().is_empty());
+    }
+
+    #[test]
+    fn test_write_pid_file_refuses_live_process() {
+        let temp_dir = tempdir().unwrap();
+        let pid_file = temp_dir.path().join("run.pid");
+        let pid_file = pid_file.to_str().unwrap();
+
+        PidFile::create(pid_file, RunMode::Single, PidFormat::Json).unwrap();
+        assert_eq!(read_pid_file(pid_file).unwrap(), std::process::id());
+
+        let err = PidFile::create(pid_file, RunMode::Single, PidFormat::Json).unwrap_err();
+        assert!(err.to_string().contains("already running"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_pid_file_reclaims_stale_file() {
+        let temp_dir = tempdir().unwrap();
+        let pid_file = temp_dir.path().join("run.pid");
+        let pid_file = pid_file.to_str().unwrap();
+
+        // Reap a child so its PID is known to be dead
+        let mut child = std::process::Command::new("true").spawn().unwrap();
+        let dead_pid = child.id();
+        child.wait().unwrap();
+        fs::write(pid_file, format!("{dead_pid}\n")).unwrap();
+
+        PidFile::create(pid_file, RunMode::Loop, PidFormat::Json).unwrap();
+        assert_eq!(read_pid_file(pid_file).unwrap(), std::process::id());
+    }
+
+    #[test]
+    fn test_read_pid_file() {
+        let temp_dir = tempdir().unwrap();
+        let pid_file = temp_dir.path().join("run.pid");
+        let pid_file = pid_file.to_str().unwrap();
+
+        assert!(read_pid_file(pid_file).is_err());
+
+        fs::write(pid_file, "4242\n").unwrap();
+        assert_eq!(read_pid_file(pid_file).unwrap(), 4242);
+
+        fs::write(pid_file, "not a pid").unwrap();
+        let err = read_pid_file(pid_file).unwrap_err();
+        assert!(err.to_string().contains("does not contain a valid PID"));
+    }
+
+    #[test]
+    fn test_pid_status_running() {
+        let temp_dir = tempdir().unwrap();
+        let pid_file = temp_dir.path().join("run.pid");
+        let pid_file = pid_file.to_str().unwrap();
+
+        let pid = std::process::id();
+        fs::write(pid_file, format!("{pid}\n")).unwrap();
+        let status = pid_status(pid_file).unwrap();
+        assert_eq!(status, PidStatus::Running(PidInfo::legacy(pid)));
+        assert_eq!(status.describe(), format!("running (PID {pid})"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_pid_status_stopped() {
+        let temp_dir = tempdir().unwrap();
+        let pid_file = temp_dir.path().join("run.pid");
+        let pid_file = pid_file.to_str().unwrap();
+
+        let mut child = std::process::Command::new("true").spawn().unwrap();
+        let dead_pid = child.id();
+        child.wait().unwrap();
+        fs::write(pid_file, format!("{dead_pid}\n")).unwrap();
+
+        assert_eq!(
+            pid_status(pid_file).unwrap(),
+            PidStatus::Stopped(PidInfo::legacy(dead_pid))
+        );
+    }
+
+    #[test]
+    fn test_pid_file_records_next_fire() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("run.pid");
+        let path = path.to_str().unwrap();
+
+        let pid_file = PidFile::create(path, RunMode::Loop, PidFormat::Json).unwrap();
+        let info = read_pid_info(path).unwrap();
+        assert_eq!(info.pid, pid_file.pid());
+        assert_eq!(info.mode, Some(RunMode::Loop));
+        assert!(info.started_at.is_some());
+        assert_eq!(info.next_fire, None);
+
+        let next_fire = Local.with_ymd_and_hms(2024, 3, 12, 14, 0, 0).unwrap();
+        pid_file.record_next_fire(next_fire).unwrap();
+        let updated = read_pid_info(path).unwrap();
+        assert_eq!(updated.next_fire, Some(next_fire));
+        assert_eq!(updated.started_at, info.started_at);
+
+        let status = PidStatus::Running(updated);
+        assert_eq!(
+            status.describe(),
+            format!(
+                "running (PID {}, loop mode, next run at 2024-03-12 14:00:00)",
+                pid_file.pid()
+            )
+        );
+    }
+
+    #[test]
+    fn test_pid_info_serialization() {
+        let info = PidInfo {
+            pid: 4242,
+            started_at: Some(Local.with_ymd_and_hms(2024, 3, 12, 9, 0, 0).unwrap()),
+            next_fire: Some(Local.with_ymd_and_hms(2024, 3, 12, 14, 0, 0).unwrap()),
+            mode: Some(RunMode::Single),
+        };
+        let json: serde_json::Value = serde_json::to_value(&info).unwrap();
+        assert_eq!(json["pid"], 4242);
+        assert_eq!(json["mode"], "single");
+        assert!(json["next_fire"].is_string());
+
+        let parsed = PidInfo::parse(&serde_json::to_string(&info).unwrap()).unwrap();
+        assert_eq!(parsed, info);
+
+        assert_eq!(PidInfo::parse("4242\n").unwrap(), PidInfo::legacy(4242));
+        assert!(PidInfo::parse("{\"pid\": \"oops\"}").is_err());
+        assert!(PidInfo::parse("oops").is_err());
+    }
+
+    #[test]
+    fn test_legacy_pid_format_writes_bare_pid() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("run.pid");
+        let path = path.to_str().unwrap();
+
+        let pid_file = PidFile::create(path, RunMode::Loop, PidFormat::Bare).unwrap();
+        pid_file.record_next_fire(Local::now()).unwrap();
+        assert_eq!(
+            fs::read_to_string(path).unwrap(),
+            format!("{}\n", std::process::id())
+        );
+    }
+
+    #[test]
+    fn test_text_pid_format_round_trips() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("run.pid");
+        let path = path.to_str().unwrap();
+
+        let pid_file = PidFile::create(path, RunMode::Loop, PidFormat::Text).unwrap();
+        let contents = fs::read_to_string(path).unwrap();
+        let lines: Vec<&str> = contents.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0], pid_file.pid().to_string());
+        assert!(DateTime::parse_from_rfc3339(lines[1]).is_ok());
+
+        let next_fire = Local.with_ymd_and_hms(2024, 3, 12, 14, 0, 0).unwrap();
+        pid_file.record_next_fire(next_fire).unwrap();
+        let contents = fs::read_to_string(path).unwrap();
+        assert_eq!(
+            contents.lines().nth(2),
+            Some(next_fire.to_rfc3339().as_str())
+        );
+
+        // `status` reads it back, minus the mode the text format leaves out
+        let info = read_pid_info(path).unwrap();
+        assert_eq!(info.pid, pid_file.pid());
+        assert_eq!(info.started_at, pid_file.info.started_at);
+        assert_eq!(info.next_fire, Some(next_fire));
+        assert_eq!(info.mode, None);
+    }
+
+    #[test]
+    fn test_parse_text_pid_file() {
+        let info = PidInfo::parse("4242\n2024-03-12T09:00:00+00:00\n").unwrap();
+        assert_eq!(info.pid, 4242);
+        assert_eq!(
+            info.started_at,
+            Some(
+                Utc.with_ymd_and_hms(2024, 3, 12, 9, 0, 0)
+                    .unwrap()
+                    .with_timezone(&Local)
+            )
+        );
+        assert_eq!(info.next_fire, None);
+
+        assert!(PidInfo::parse("4242\nyesterday\n").is_err());
+    }
+}
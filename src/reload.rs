@@ -0,0 +1,67 @@
+use std::sync::Arc;
+use tokio::sync::Notify;
+
+/// Raised on SIGHUP to ask loop mode to re-read its `--config` file.
+///
+/// Like [`Shutdown`](crate::shutdown::Shutdown), the signal task only records
+/// the request. A request made while a cycle is running is kept until the loop
+/// next waits, so the new settings apply from the next fire time it computes.
+#[derive(Clone, Debug, Default)]
+pub struct Reload {
+    notify: Arc<Notify>,
+}
+
+impl Reload {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn request(&self) {
+        self.notify.notify_one();
+    }
+
+    /// Resolves once a reload has been requested, immediately if one is pending.
+    pub async fn requested(&self) {
+        self.notify.notified().await;
+    }
+
+    /// Requests a reload on every SIGHUP.
+    #[cfg(unix)]
+    pub fn listen_for_hangup(&self) -> std::io::Result<()> {
+        use tokio::signal::unix::{SignalKind, signal};
+
+        let mut hangup = signal(SignalKind::hangup())?;
+        let reload = self.clone();
+        tokio::spawn(async move {
+            while hangup.recv().await.is_some() {
+                reload.request();
+            }
+        });
+        Ok(())
+    }
+
+    /// There is no SIGHUP outside Unix, so nothing ever requests a reload.
+    #[cfg(not(unix))]
+    pub fn listen_for_hangup(&self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_request_is_kept_until_waited_for() {
+        let reload = Reload::new();
+        reload.request();
+        tokio::time::timeout(Duration::from_secs(1), reload.requested())
+            .await
+            .expect("a pending reload should resolve immediately");
+
+        // The request was used up
+        let waited = tokio::time::timeout(Duration::from_millis(20), reload.requested()).await;
+        assert!(waited.is_err());
+    }
+}
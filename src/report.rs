@@ -0,0 +1,252 @@
+use crate::export::{for_each_entry, response_length};
+use crate::logger::LogEntry;
+use anyhow::Result;
+use chrono::{NaiveDate, Timelike};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::Path;
+
+/// How `report` prints its results.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ReportFormat {
+    /// Aligned text for reading in a terminal
+    #[default]
+    Table,
+    /// A single JSON object, for scripts
+    Json,
+}
+
+/// Aggregate statistics over the runs in a log directory. Only executions
+/// (`success` and `error` entries) count as runs.
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct Report {
+    pub total_runs: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    /// Fraction of runs that succeeded, from 0 to 1.
+    pub success_rate: Option<f64>,
+    /// Failed runs on each day that had any.
+    pub failures_per_day: BTreeMap<NaiveDate, usize>,
+    pub average_response_length: Option<f64>,
+    pub p95_response_length: Option<usize>,
+    /// The hour of day (0-23) with the most runs, the earliest on a tie.
+    pub busiest_hour: Option<u32>,
+    /// Lines that were not JSON log entries.
+    pub skipped_lines: usize,
+}
+
+/// Builds the report for the entries logged on or after `since`.
+pub fn build_report(
+    entries: impl IntoIterator<Item = LogEntry>,
+    since: Option<NaiveDate>,
+) -> Report {
+    let mut report = Report::default();
+    let mut response_lengths = Vec::new();
+    let mut runs_per_hour = [0usize; 24];
+
+    for entry in entries {
+        let date = entry.timestamp.date_naive();
+        if since.is_some_and(|since| date < since) {
+            continue;
+        }
+        match entry.status.as_str() {
+            "success" => report.succeeded += 1,
+            "error" => {
+                report.failed += 1;
+                *report.failures_per_day.entry(date).or_default() += 1;
+            }
+            _ => continue,
+        }
+        runs_per_hour[entry.timestamp.hour() as usize] += 1;
+        if let Some(length) = response_length(&entry) {
+            response_lengths.push(length);
+        }
+    }
+
+    report.total_runs = report.succeeded + report.failed;
+    if report.total_runs > 0 {
+        report.success_rate = Some(report.succeeded as f64 / report.total_runs as f64);
+        // max_by_key keeps the last maximum, so search from the latest hour back
+        report.busiest_hour = (0..24u32)
+            .rev()
+            .max_by_key(|&hour| runs_per_hour[hour as usize]);
+    }
+    if !response_lengths.is_empty() {
+        response_lengths.sort_unstable();
+        let total: usize = response_lengths.iter().sum();
+        report.average_response_length = Some(total as f64 / response_lengths.len() as f64);
+        report.p95_response_length = Some(percentile(&response_lengths, 95));
+    }
+    report
+}
+
+/// The nearest-rank `percent`th percentile of the sorted, non-empty `values`.
+fn percentile(values: &[usize], percent: usize) -> usize {
+    let rank = (values.len() * percent).div_ceil(100);
+    values[rank.saturating_sub(1)]
+}
+
+/// Reads every log file under `log_dir` and reports on the entries logged on
+/// or after `since`. Malformed lines are counted and skipped.
+pub fn report_logs(log_dir: &Path, since: Option<NaiveDate>) -> Result<Report> {
+    let mut entries = Vec::new();
+    let skipped = for_each_entry(log_dir, |entry| {
+        entries.push(entry);
+        Ok(())
+    })?;
+    Ok(Report {
+        skipped_lines: skipped,
+        ..build_report(entries, since)
+    })
+}
+
+impl Report {
+    pub fn render(&self, format: ReportFormat) -> Result<String> {
+        Ok(match format {
+            ReportFormat::Table => self.table(),
+            ReportFormat::Json => serde_json::to_string_pretty(self)? + "\n",
+        })
+    }
+
+    fn table(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "Total runs:               {}", self.total_runs);
+        if let Some(rate) = self.success_rate {
+            let _ = writeln!(
+                out,
+                "Success rate:             {:.1}% ({} succeeded, {} failed)",
+                rate * 100.0,
+                self.succeeded,
+                self.failed
+            );
+        }
+        if let Some(average) = self.average_response_length {
+            let _ = writeln!(out, "Average response length:  {average:.0} characters");
+        }
+        if let Some(p95) = self.p95_response_length {
+            let _ = writeln!(out, "p95 response length:      {p95} characters");
+        }
+        if let Some(hour) = self.busiest_hour {
+            let _ = writeln!(out, "Busiest hour:             {hour:02}:00-{hour:02}:59");
+        }
+        if !self.failures_per_day.is_empty() {
+            out.push_str("Failures per day:\n");
+            for (date, failures) in &self.failures_per_day {
+                let _ = writeln!(out, "  {date}  {failures}");
+            }
+        }
+        if self.skipped_lines > 0 {
+            let _ = writeln!(out, "Skipped {} malformed lines", self.skipped_lines);
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Local, TimeZone};
+
+    fn entry(day: u32, hour: u32, status: &str, response_length: Option<usize>) -> LogEntry {
+        let mut entry = LogEntry::new_with_response("claude", status, None, None, None);
+        entry.timestamp = Local.with_ymd_and_hms(2024, 1, day, hour, 0, 0).unwrap();
+        entry.response_length = response_length;
+        entry
+    }
+
+    fn sample_entries() -> Vec<LogEntry> {
+        let mut entries = vec![
+            entry(1, 6, "success", Some(100)),
+            entry(1, 6, "error", None),
+            entry(2, 9, "success", Some(300)),
+            entry(2, 14, "error", None),
+            entry(2, 14, "error", None),
+            entry(3, 14, "success", Some(200)),
+            // Not runs
+            entry(3, 6, "start", None),
+            entry(3, 6, "retry", None),
+        ];
+        for _ in 0..16 {
+            entries.push(entry(3, 20, "success", Some(50)));
+        }
+        entries
+    }
+
+    #[test]
+    fn test_report_aggregates() {
+        let report = build_report(sample_entries(), None);
+        assert_eq!(report.total_runs, 22);
+        assert_eq!(report.succeeded, 19);
+        assert_eq!(report.failed, 3);
+        assert_eq!(report.success_rate, Some(19.0 / 22.0));
+        assert_eq!(
+            report.failures_per_day,
+            BTreeMap::from([
+                (NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(), 1),
+                (NaiveDate::from_ymd_opt(2024, 1, 2).unwrap(), 2),
+            ])
+        );
+        // (100 + 300 + 200 + 16 * 50) / 19
+        assert_eq!(report.average_response_length, Some(1400.0 / 19.0));
+        // The 19th of 19 sorted lengths
+        assert_eq!(report.p95_response_length, Some(300));
+        assert_eq!(report.busiest_hour, Some(20));
+    }
+
+    #[test]
+    fn test_report_since() {
+        let since = NaiveDate::from_ymd_opt(2024, 1, 2);
+        let report = build_report(sample_entries(), since);
+        assert_eq!(report.total_runs, 20);
+        assert_eq!(report.failed, 2);
+        assert_eq!(report.failures_per_day.len(), 1);
+
+        let report = build_report(sample_entries(), NaiveDate::from_ymd_opt(2024, 2, 1));
+        assert_eq!(report, Report::default());
+    }
+
+    #[test]
+    fn test_busiest_hour_tie_goes_to_earliest() {
+        let entries = [entry(1, 14, "success", None), entry(1, 9, "error", None)];
+        assert_eq!(build_report(entries, None).busiest_hour, Some(9));
+    }
+
+    #[test]
+    fn test_percentile() {
+        let values: Vec<usize> = (1..=100).collect();
+        assert_eq!(percentile(&values, 95), 95);
+        assert_eq!(percentile(&[7], 95), 7);
+        assert_eq!(percentile(&[1, 2, 3, 4], 95), 4);
+    }
+
+    #[test]
+    fn test_report_logs_skips_malformed_lines() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            temp_dir.path().join("2024-01-02.log"),
+            concat!(
+                r#"{"timestamp":"2024-01-02T06:00:00+00:00","action":"claude","status":"success","message":null,"response_content":"hello","cycle_number":null}"#,
+                "\n",
+                "not json\n",
+                r#"{"timestamp":"2024-01-02T07:00:00+00:00","action":"claude","status":"error","message":"failed","response_content":null,"cycle_number":null}"#,
+                "\n",
+            ),
+        )
+        .unwrap();
+
+        let report = report_logs(temp_dir.path(), None).unwrap();
+        assert_eq!(report.total_runs, 2);
+        assert_eq!(report.skipped_lines, 1);
+        assert_eq!(report.p95_response_length, Some(5));
+
+        let table = report.render(ReportFormat::Table).unwrap();
+        assert!(table.contains("Success rate:             50.0% (1 succeeded, 1 failed)\n"));
+        assert!(table.contains("Skipped 1 malformed lines\n"));
+
+        let json: serde_json::Value =
+            serde_json::from_str(&report.render(ReportFormat::Json).unwrap()).unwrap();
+        assert_eq!(json["total_runs"], 2);
+        assert_eq!(json["success_rate"], 0.5);
+    }
+}
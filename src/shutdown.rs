@@ -0,0 +1,108 @@
+use crate::console::Console;
+use std::sync::Arc;
+use tokio::sync::watch;
+
+/// Shared flag the Ctrl+C handler raises to ask the scheduler to stop.
+///
+/// Rather than exiting the process from the signal task, the handler only
+/// records the request; the run loop notices it between steps, finishes
+/// logging the current cycle and returns normally so the PID file is cleaned
+/// up in one place.
+#[derive(Clone, Debug)]
+pub struct Shutdown {
+    sender: Arc<watch::Sender<bool>>,
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        let (sender, _) = watch::channel(false);
+        Self {
+            sender: Arc::new(sender),
+        }
+    }
+
+    pub fn request(&self) {
+        self.sender.send_replace(true);
+    }
+
+    pub fn is_requested(&self) -> bool {
+        *self.sender.borrow()
+    }
+
+    /// Resolves once shutdown has been requested, immediately if it already was.
+    pub async fn requested(&self) {
+        let mut receiver = self.sender.subscribe();
+        // The sender lives as long as `self`, so this cannot fail
+        let _ = receiver.wait_for(|requested| *requested).await;
+    }
+
+    /// Requests shutdown on Ctrl+C, printing `message` first. A second Ctrl+C
+    /// exits immediately in case the current run is stuck.
+    pub fn listen_for_ctrl_c(&self, console: Console, message: &'static str) {
+        let shutdown = self.clone();
+        tokio::spawn(async move {
+            while tokio::signal::ctrl_c().await.is_ok() {
+                if shutdown.is_requested() {
+                    eprintln!("\nForced exit");
+                    std::process::exit(130);
+                }
+                console.line(message);
+                shutdown.request();
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logger::{LogEntry, Logger};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_pending_log_is_flushed_before_exit() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let logger = Logger::new(temp_dir.path().to_str().unwrap()).with_echo(false);
+        let shutdown = Shutdown::new();
+
+        // Simulates a cycle that is mid-run when Ctrl+C arrives: it keeps going
+        // until its result is logged, then sees the request and stops
+        let cycle = {
+            let shutdown = shutdown.clone();
+            tokio::spawn(async move {
+                shutdown.requested().await;
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                logger
+                    .log(LogEntry::claude_success("done", Some(1), Duration::ZERO))
+                    .unwrap();
+                logger.log_cycle_end(1).unwrap();
+                shutdown.is_requested()
+            })
+        };
+
+        shutdown.request();
+        assert!(cycle.await.unwrap());
+
+        let log_file = std::fs::read_dir(temp_dir.path())
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap()
+            .path();
+        let contents = std::fs::read_to_string(log_file).unwrap();
+        assert!(contents.contains("\"response_content\":\"done\""));
+        assert!(contents.contains("Completed cycle 1"));
+    }
+
+    #[tokio::test]
+    async fn test_requested_resolves_after_the_fact() {
+        let shutdown = Shutdown::new();
+        assert!(!shutdown.is_requested());
+
+        shutdown.request();
+        assert!(shutdown.is_requested());
+        tokio::time::timeout(Duration::from_secs(1), shutdown.requested())
+            .await
+            .expect("already-requested shutdown should resolve immediately");
+    }
+}
@@ -0,0 +1,171 @@
+use crate::logger::LogEntry;
+use anyhow::{Context, Result, anyhow};
+use rusqlite::{Connection, params};
+use std::sync::{Arc, Mutex};
+
+/// Created on first use, so an existing database keeps its rows.
+const SCHEMA: &str = "CREATE TABLE IF NOT EXISTS runs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    timestamp TEXT NOT NULL,
+    action TEXT NOT NULL,
+    status TEXT NOT NULL,
+    message TEXT,
+    response_content TEXT,
+    response_length INTEGER,
+    cycle_number INTEGER,
+    step INTEGER,
+    duration_ms INTEGER,
+    stderr_content TEXT,
+    exit_code INTEGER
+)";
+
+/// Inserts each log entry as a row of the `runs` table in a SQLite database
+/// (`--sqlite`), alongside the log file. Clones share the connection.
+#[derive(Clone)]
+pub struct SqliteSink {
+    connection: Arc<Mutex<Connection>>,
+}
+
+impl SqliteSink {
+    pub fn open(path: &str) -> Result<Self> {
+        let connection = Connection::open(path)
+            .with_context(|| format!("Failed to open SQLite database {path}"))?;
+        Self::with_connection(connection)
+    }
+
+    fn with_connection(connection: Connection) -> Result<Self> {
+        connection
+            .execute_batch(SCHEMA)
+            .context("Failed to create the runs table")?;
+        Ok(Self {
+            connection: Arc::new(Mutex::new(connection)),
+        })
+    }
+
+    pub fn insert(&self, entry: &LogEntry) -> Result<()> {
+        let connection = self
+            .connection
+            .lock()
+            .map_err(|_| anyhow!("SQLite connection is poisoned"))?;
+        connection
+            .execute(
+                "INSERT INTO runs (timestamp, action, status, message, response_content,
+                     response_length, cycle_number, step, duration_ms, stderr_content, exit_code)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+                params![
+                    entry.timestamp.to_rfc3339(),
+                    entry.action,
+                    entry.status,
+                    entry.message,
+                    entry.response_content,
+                    entry.response_length.map(|length| length as i64),
+                    entry.cycle_number,
+                    entry.step,
+                    entry.duration_ms.map(|ms| ms as i64),
+                    entry.stderr_content,
+                    entry.exit_code,
+                ],
+            )
+            .context("Failed to insert log entry into SQLite")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logger::Logger;
+    use std::time::Duration;
+
+    /// action, status, response_length, cycle_number, duration_ms, exit_code
+    type Row = (
+        String,
+        String,
+        Option<i64>,
+        Option<u32>,
+        Option<i64>,
+        Option<i32>,
+    );
+
+    #[test]
+    fn test_entries_are_queryable() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let sink = SqliteSink::with_connection(Connection::open_in_memory().unwrap()).unwrap();
+        let logger = Logger::new(temp_dir.path().to_str().unwrap())
+            .with_echo(false)
+            .with_sqlite(Some(sink.clone()));
+
+        logger
+            .log(LogEntry::claude_success(
+                "all done",
+                Some(2),
+                Duration::from_millis(1500),
+            ))
+            .unwrap();
+        logger
+            .log(LogEntry::error("claude", Some("failed".to_string())).with_exit_code(Some(1)))
+            .unwrap();
+
+        let connection = sink.connection.lock().unwrap();
+        let mut statement = connection
+            .prepare("SELECT action, status, response_length, cycle_number, duration_ms, exit_code FROM runs ORDER BY id")
+            .unwrap();
+        let rows: Vec<Row> = statement
+            .query_map([], |row| {
+                Ok((
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                    row.get(4)?,
+                    row.get(5)?,
+                ))
+            })
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert_eq!(
+            rows,
+            vec![
+                (
+                    "claude".to_string(),
+                    "success".to_string(),
+                    Some(8),
+                    Some(2),
+                    Some(1500),
+                    None
+                ),
+                (
+                    "claude".to_string(),
+                    "error".to_string(),
+                    None,
+                    None,
+                    None,
+                    Some(1)
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_schema_is_kept_on_reopen() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("runs.db");
+        let path = path.to_str().unwrap();
+
+        SqliteSink::open(path)
+            .unwrap()
+            .insert(&LogEntry::success("ping", None))
+            .unwrap();
+        let sink = SqliteSink::open(path).unwrap();
+        sink.insert(&LogEntry::success("ping", None)).unwrap();
+
+        let count: i64 = sink
+            .connection
+            .lock()
+            .unwrap()
+            .query_row("SELECT COUNT(*) FROM runs", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 2);
+    }
+}
@@ -0,0 +1,114 @@
+/// Running tally of loop-mode outcomes, reported when the loop exits.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct LoopSummary {
+    pub succeeded: u32,
+    pub failed: u32,
+    /// Sum of the response lengths of successful runs, in characters.
+    total_response_chars: usize,
+    /// Failed runs since the last successful one.
+    pub consecutive_failures: u32,
+}
+
+impl LoopSummary {
+    pub fn record_success(&mut self, response_chars: usize) {
+        self.succeeded += 1;
+        self.total_response_chars += response_chars;
+        self.consecutive_failures = 0;
+    }
+
+    pub fn record_failure(&mut self) {
+        self.failed += 1;
+        self.consecutive_failures += 1;
+    }
+
+    /// Whether the failure just recorded calls for an `--alert-after` alert: on the
+    /// `alert_after`th failure in a row, then each time the streak doubles, so a long
+    /// outage keeps alerting but less and less often.
+    pub fn should_alert(&self, alert_after: Option<u32>) -> bool {
+        let Some(alert_after) = alert_after else {
+            return false;
+        };
+        let failures = self.consecutive_failures;
+        failures >= alert_after
+            && failures.is_multiple_of(alert_after)
+            && (failures / alert_after).is_power_of_two()
+    }
+
+    pub fn cycles(&self) -> u32 {
+        self.succeeded + self.failed
+    }
+
+    /// Mean response length over successful runs, or `None` if none succeeded.
+    pub fn average_response_chars(&self) -> Option<usize> {
+        (self.succeeded > 0).then(|| self.total_response_chars / self.succeeded as usize)
+    }
+
+    pub fn describe(&self) -> String {
+        let mut text = format!(
+            "{} cycles ({} succeeded, {} failed",
+            self.cycles(),
+            self.succeeded,
+            self.failed
+        );
+        if let Some(average) = self.average_response_chars() {
+            text.push_str(&format!(", average response {average} characters"));
+        }
+        text.push(')');
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary_from_outcomes() {
+        let mut summary = LoopSummary::default();
+        assert_eq!(summary.average_response_chars(), None);
+        assert_eq!(summary.describe(), "0 cycles (0 succeeded, 0 failed)");
+
+        let outcomes: [Result<usize, ()>; 4] = [Ok(100), Err(()), Ok(250), Ok(30)];
+        for outcome in outcomes {
+            match outcome {
+                Ok(chars) => summary.record_success(chars),
+                Err(()) => summary.record_failure(),
+            }
+        }
+
+        assert_eq!(summary.cycles(), 4);
+        assert_eq!(summary.succeeded, 3);
+        assert_eq!(summary.failed, 1);
+        // Failures don't drag the average down
+        assert_eq!(summary.average_response_chars(), Some(126));
+        assert_eq!(
+            summary.describe(),
+            "4 cycles (3 succeeded, 1 failed, average response 126 characters)"
+        );
+    }
+
+    #[test]
+    fn test_should_alert_after_consecutive_failures() {
+        let mut summary = LoopSummary::default();
+        let mut alerts = Vec::new();
+        let outcomes = [
+            false, false, true, false, false, false, false, false, false, false, false, false,
+        ];
+        for (index, succeeded) in outcomes.into_iter().enumerate() {
+            if succeeded {
+                summary.record_success(0);
+            } else {
+                summary.record_failure();
+            }
+            if summary.should_alert(Some(2)) {
+                alerts.push(index + 1);
+            }
+            assert!(!summary.should_alert(None));
+        }
+
+        // The success after run 2 resets the streak; the next one alerts at 2, 4 and 8
+        // failures in a row
+        assert_eq!(alerts, vec![2, 5, 7, 11]);
+        assert_eq!(summary.consecutive_failures, 9);
+    }
+}
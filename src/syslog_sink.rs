@@ -0,0 +1,94 @@
+use crate::logger::{LogEntry, format_text_line};
+use anyhow::{Result, anyhow};
+use std::sync::Mutex;
+use syslog::{Facility, Formatter3164, LogFormat, LoggerBackend, Severity};
+
+/// Forwards log entries to the local syslog daemon, alongside the log file.
+pub struct SyslogSink {
+    logger: Mutex<syslog::Logger<LoggerBackend, Formatter3164>>,
+}
+
+impl SyslogSink {
+    /// Connects to the local syslog socket, tagging messages with `facility`.
+    pub fn connect(facility: Facility) -> Result<Self> {
+        let formatter = Formatter3164 {
+            facility,
+            hostname: None,
+            process: "ccschedule".to_string(),
+            pid: std::process::id(),
+        };
+        let logger =
+            syslog::unix(formatter).map_err(|e| anyhow!("Failed to connect to syslog: {e}"))?;
+        Ok(Self {
+            logger: Mutex::new(logger),
+        })
+    }
+
+    pub fn send(&self, entry: &LogEntry) -> Result<()> {
+        let mut logger = self
+            .logger
+            .lock()
+            .map_err(|_| anyhow!("Syslog connection is poisoned"))?;
+        let logger = &mut *logger;
+        logger
+            .formatter
+            .format(
+                &mut logger.backend,
+                syslog_severity(entry),
+                format_text_line(entry),
+            )
+            .map_err(|e| anyhow!("Failed to send log entry to syslog: {e}"))
+    }
+}
+
+/// The syslog severity an entry is sent at: failures as `err`, everything
+/// else as `info` unless it is a warning or debug detail.
+pub fn syslog_severity(entry: &LogEntry) -> Severity {
+    match entry.status.as_str() {
+        "error" => Severity::LOG_ERR,
+        "warning" | "retry" => Severity::LOG_WARNING,
+        "debug" => Severity::LOG_DEBUG,
+        _ => Severity::LOG_INFO,
+    }
+}
+
+/// Parses a syslog facility name such as `daemon`, `user` or `local0`.
+pub fn parse_facility(name: &str) -> Result<Facility> {
+    name.parse()
+        .map_err(|()| anyhow!("Unknown syslog facility '{name}'"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn severity_of(status: &str) -> u8 {
+        let mut entry = LogEntry::claude_success("response", None, Duration::ZERO);
+        entry.status = status.to_string();
+        syslog_severity(&entry) as u8
+    }
+
+    #[test]
+    fn test_syslog_severity() {
+        assert_eq!(severity_of("success"), Severity::LOG_INFO as u8);
+        assert_eq!(severity_of("start"), Severity::LOG_INFO as u8);
+        assert_eq!(severity_of("error"), Severity::LOG_ERR as u8);
+        assert_eq!(severity_of("warning"), Severity::LOG_WARNING as u8);
+        assert_eq!(severity_of("retry"), Severity::LOG_WARNING as u8);
+        assert_eq!(severity_of("debug"), Severity::LOG_DEBUG as u8);
+    }
+
+    #[test]
+    fn test_parse_facility() {
+        assert_eq!(
+            parse_facility("daemon").unwrap() as u8,
+            Facility::LOG_DAEMON as u8
+        );
+        assert_eq!(
+            parse_facility("LOCAL3").unwrap() as u8,
+            Facility::LOG_LOCAL3 as u8
+        );
+        assert!(parse_facility("nowhere").is_err());
+    }
+}
@@ -0,0 +1,114 @@
+use std::time::Duration;
+
+/// Reports readiness and liveness to systemd when running as a `Type=notify`
+/// service. Outside systemd (no `NOTIFY_SOCKET`), or when built without the
+/// `systemd` feature, every call is a no-op.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Systemd {
+    enabled: bool,
+    /// How often systemd expects a watchdog ping, from `WATCHDOG_USEC`.
+    watchdog_interval: Option<Duration>,
+}
+
+impl Systemd {
+    pub fn from_env() -> Self {
+        Self::from_lookup(|name| std::env::var(name).ok())
+    }
+
+    /// Reads the systemd environment through `lookup`, so tests don't have to
+    /// modify the real environment.
+    fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Self {
+        let enabled = cfg!(feature = "systemd")
+            && lookup("NOTIFY_SOCKET").is_some_and(|socket| !socket.is_empty());
+
+        // WATCHDOG_PID, when set, names the process the watchdog applies to
+        let watchdog_for_us = lookup("WATCHDOG_PID")
+            .is_none_or(|pid| pid.parse::<u32>().ok() == Some(std::process::id()));
+        let watchdog_interval = lookup("WATCHDOG_USEC")
+            .and_then(|usec| usec.parse().ok())
+            .filter(|_| enabled && watchdog_for_us)
+            .map(Duration::from_micros);
+
+        Self {
+            enabled,
+            watchdog_interval,
+        }
+    }
+
+    /// The longest the scheduler should go without pinging the watchdog:
+    /// half systemd's timeout, so one late tick doesn't trigger a restart.
+    pub fn watchdog_deadline(&self) -> Option<Duration> {
+        self.watchdog_interval.map(|interval| interval / 2)
+    }
+
+    /// Tells systemd that startup has finished.
+    pub fn ready(&self) {
+        self.notify("READY=1");
+    }
+
+    /// Tells systemd the scheduler is still alive.
+    pub fn watchdog(&self) {
+        if self.watchdog_interval.is_some() {
+            self.notify("WATCHDOG=1");
+        }
+    }
+
+    fn notify(&self, state: &str) {
+        if !self.enabled {
+            return;
+        }
+        #[cfg(feature = "systemd")]
+        if let Err(e) = sd_notify::notify(false, &[sd_notify::NotifyState::Custom(state)]) {
+            eprintln!("Warning: Failed to notify systemd ({state}): {e}");
+        }
+        #[cfg(not(feature = "systemd"))]
+        let _ = state;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn systemd_with(vars: &[(&str, &str)]) -> Systemd {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        Systemd::from_lookup(|name| vars.get(name).cloned())
+    }
+
+    #[test]
+    fn test_notify_is_gated_on_notify_socket() {
+        assert!(!systemd_with(&[]).enabled);
+        assert!(!systemd_with(&[("NOTIFY_SOCKET", "")]).enabled);
+        // Only builds with the feature ever talk to systemd
+        assert_eq!(
+            systemd_with(&[("NOTIFY_SOCKET", "/run/systemd/notify")]).enabled,
+            cfg!(feature = "systemd")
+        );
+
+        let without_socket = systemd_with(&[("WATCHDOG_USEC", "30000000")]);
+        assert_eq!(without_socket.watchdog_deadline(), None);
+    }
+
+    #[cfg(feature = "systemd")]
+    #[test]
+    fn test_watchdog_deadline() {
+        let pid = std::process::id().to_string();
+        let systemd = systemd_with(&[
+            ("NOTIFY_SOCKET", "/run/systemd/notify"),
+            ("WATCHDOG_USEC", "30000000"),
+            ("WATCHDOG_PID", &pid),
+        ]);
+        assert_eq!(systemd.watchdog_deadline(), Some(Duration::from_secs(15)));
+
+        let other_process = systemd_with(&[
+            ("NOTIFY_SOCKET", "/run/systemd/notify"),
+            ("WATCHDOG_USEC", "30000000"),
+            ("WATCHDOG_PID", "1"),
+        ]);
+        assert_eq!(other_process.watchdog_deadline(), None);
+    }
+}
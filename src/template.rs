@@ -0,0 +1,101 @@
+use anyhow::{Result, bail};
+use chrono::{DateTime, Local};
+
+/// Values substituted for the `{{...}}` tokens in a message, taken when the
+/// message is sent rather than when it was scheduled.
+#[derive(Clone, Copy, Debug)]
+pub struct TemplateVars {
+    pub now: DateTime<Local>,
+    /// The loop cycle being run; `{{cycle}}` is empty outside loop mode.
+    pub cycle_number: Option<u32>,
+}
+
+impl TemplateVars {
+    fn value(&self, token: &str) -> Option<String> {
+        let value = match token {
+            "date" => self.now.format("%Y-%m-%d").to_string(),
+            "time" => self.now.format("%H:%M:%S").to_string(),
+            "datetime" => self.now.format("%Y-%m-%d %H:%M:%S").to_string(),
+            "cycle" => self
+                .cycle_number
+                .map(|cycle| cycle.to_string())
+                .unwrap_or_default(),
+            _ => return None,
+        };
+        Some(value)
+    }
+}
+
+/// Replaces each `{{token}}` in `template` with its value. Unknown tokens are
+/// left as they are, or are an error when `strict`.
+pub fn render(template: &str, vars: &TemplateVars, strict: bool) -> Result<String> {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        let end = start + 2 + len + 2;
+        out.push_str(&rest[..start]);
+        let token = rest[start + 2..end - 2].trim();
+        match vars.value(token) {
+            Some(value) => out.push_str(&value),
+            None if strict => bail!("Unknown template token '{{{{{token}}}}}' in message"),
+            None => out.push_str(&rest[start..end]),
+        }
+        rest = &rest[end..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn vars(cycle_number: Option<u32>) -> TemplateVars {
+        TemplateVars {
+            now: Local.with_ymd_and_hms(2024, 3, 9, 7, 5, 30).unwrap(),
+            cycle_number,
+        }
+    }
+
+    #[test]
+    fn test_render_tokens() {
+        let vars = vars(Some(4));
+        let render = |template| render(template, &vars, false).unwrap();
+
+        assert_eq!(
+            render("Summarize commits since {{date}}"),
+            "Summarize commits since 2024-03-09"
+        );
+        assert_eq!(render("at {{time}}"), "at 07:05:30");
+        assert_eq!(render("{{datetime}}"), "2024-03-09 07:05:30");
+        assert_eq!(render("cycle {{ cycle }} of the day"), "cycle 4 of the day");
+        assert_eq!(render("no tokens"), "no tokens");
+        assert_eq!(render("{{date}}/{{date}}"), "2024-03-09/2024-03-09");
+        // An unclosed token isn't one
+        assert_eq!(render("{{date"), "{{date");
+    }
+
+    #[test]
+    fn test_render_cycle_outside_loop_mode() {
+        assert_eq!(render("[{{cycle}}]", &vars(None), false).unwrap(), "[]");
+    }
+
+    #[test]
+    fn test_render_unknown_token() {
+        let vars = vars(Some(1));
+        assert_eq!(
+            render("{{date}} {{branch}}", &vars, false).unwrap(),
+            "2024-03-09 {{branch}}"
+        );
+
+        let err = render("{{date}} {{branch}}", &vars, true).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Unknown template token '{{branch}}' in message"
+        );
+    }
+}
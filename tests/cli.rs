@@ -0,0 +1,45 @@
+#![cfg(unix)]
+
+use std::process::Command;
+
+/// The `--json` record printed by a dry run of `ccschedule` with `args` and `envs`.
+fn dry_run_record(args: &[&str], envs: &[(&str, &str)]) -> serde_json::Value {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_ccschedule"))
+        .args(["--dry-run", "--json", "--claude-bin", "true", "--log-dir"])
+        .arg(temp_dir.path())
+        .args(args)
+        .env_remove("CCS_MESSAGE")
+        .env_remove("CCS_TIME")
+        .envs(envs.iter().copied())
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    serde_json::from_slice(&output.stdout).unwrap()
+}
+
+#[test]
+fn test_message_and_time_fall_back_to_env() {
+    let envs = [
+        ("CCS_MESSAGE", "Summarize the changelog"),
+        ("CCS_TIME", "07:15"),
+    ];
+    let record = dry_run_record(&[], &envs);
+    assert!(
+        record["message"]
+            .as_str()
+            .unwrap()
+            .ends_with("\"Summarize the changelog\"")
+    );
+    assert!(record["fire_time"].as_str().unwrap().contains("T07:15:00"));
+
+    // Flags win over the environment
+    let record = dry_run_record(&["--message", "From the flag", "--time", "08:30"], &envs);
+    assert!(
+        record["message"]
+            .as_str()
+            .unwrap()
+            .ends_with("\"From the flag\"")
+    );
+    assert!(record["fire_time"].as_str().unwrap().contains("T08:30:00"));
+}
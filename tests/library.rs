@@ -0,0 +1,143 @@
+#![cfg(unix)]
+
+use chrono::Local;
+use claude_code_schedule::clock::{Clock, MockClock};
+use claude_code_schedule::{
+    ClaudeOptions, Config, LogEntry, Scheduler, SchedulerError, run_claude_command,
+};
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+fn write_stub(dir: &Path, script: &str) -> String {
+    let path = dir.join("claude-stub");
+    std::fs::write(&path, format!("#!/bin/sh\n{script}\n")).unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    path.to_str().unwrap().to_string()
+}
+
+#[tokio::test]
+async fn test_scheduler_runs_stub_command() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let log_dir = temp_dir.path().join("logs");
+
+    let config = Config {
+        time: vec!["+1s".to_string()],
+        message: vec!["hello from the library".to_string()],
+        claude_bin: write_stub(
+            temp_dir.path(),
+            r#"for arg; do last="$arg"; done; echo "got: $last""#,
+        ),
+        log_dir: log_dir.to_str().unwrap().to_string(),
+        quiet: true,
+        ..Config::default()
+    };
+
+    Scheduler::new(config).run().await.unwrap();
+
+    let log_file = std::fs::read_dir(&log_dir)
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap()
+        .path();
+    let entries: Vec<LogEntry> = std::fs::read_to_string(log_file)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    let run = entries
+        .iter()
+        .find(|entry| entry.action == "claude" && entry.status == "success")
+        .expect("a successful claude run should be logged");
+    assert_eq!(
+        run.response_content.as_deref(),
+        Some("got: hello from the library\n")
+    );
+}
+
+#[tokio::test]
+async fn test_scheduler_rejects_config_file() {
+    let config = Config {
+        config: Some("ccschedule.toml".to_string()),
+        time: vec!["+1s".to_string()],
+        ..Config::default()
+    };
+    let err = Scheduler::new(config).run().await.unwrap_err();
+    assert!(err.to_string().contains("doesn't read config files"));
+}
+
+#[tokio::test]
+async fn test_single_mode_fires_once_on_mock_clock() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let runs = temp_dir.path().join("runs");
+    let start = Local::now();
+    let clock = Arc::new(MockClock::new(start));
+
+    let config = Config {
+        time: vec!["+3h".to_string()],
+        claude_bin: write_stub(
+            temp_dir.path(),
+            &format!(
+                r#"[ "$1" = --version ] && echo 1.0 && exit 0; echo fired >> {}; echo ok"#,
+                runs.display()
+            ),
+        ),
+        log_dir: temp_dir.path().join("logs").to_str().unwrap().to_string(),
+        quiet: true,
+        ..Config::default()
+    };
+
+    // Three hours of waiting should take no real time at all
+    tokio::time::timeout(
+        Duration::from_secs(10),
+        Scheduler::new(config).with_clock(clock.clone()).run(),
+    )
+    .await
+    .expect("the mock clock should not wait in real time")
+    .unwrap();
+
+    assert_eq!(std::fs::read_to_string(&runs).unwrap(), "fired\n");
+    assert!(clock.now() >= start + chrono::Duration::hours(3));
+}
+
+#[tokio::test]
+async fn test_run_claude_command_directly() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let options = ClaudeOptions {
+        program: write_stub(temp_dir.path(), "echo response; echo note >&2"),
+        extra_args: Vec::new(),
+        timeout: None,
+        working_dir: None,
+        env: Vec::new(),
+        api_key_file: None,
+    };
+
+    let output = run_claude_command("prompt", &options).await.unwrap();
+    assert_eq!(output.response, "response\n");
+    assert_eq!(output.stderr.trim(), "note");
+    assert_eq!(output.exit_code, Some(0));
+}
+
+#[tokio::test]
+async fn test_failed_command_can_be_matched() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let options = ClaudeOptions {
+        program: write_stub(temp_dir.path(), "echo 'bad flag' >&2; exit 7"),
+        extra_args: Vec::new(),
+        timeout: None,
+        working_dir: None,
+        env: Vec::new(),
+        api_key_file: None,
+    };
+
+    let err = run_claude_command("prompt", &options).await.unwrap_err();
+    match err.downcast_ref::<SchedulerError>() {
+        Some(SchedulerError::CommandFailed { code, stderr, .. }) => {
+            assert_eq!(*code, Some(7));
+            assert_eq!(stderr.trim(), "bad flag");
+        }
+        other => panic!("expected CommandFailed, got {other:?}"),
+    }
+}